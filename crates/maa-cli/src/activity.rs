@@ -12,11 +12,14 @@ static STAGE_ACTIVITY: LazyLock<Option<StageActivityJson>> =
     LazyLock::new(|| load_stage_activity(maa_dirs::activity()).warn_err());
 
 pub fn has_side_story_open(client: ClientType) -> bool {
+    stage_activity(client).is_some_and(|c| c.has_side_story_open())
+}
+
+/// Stage activities of given client, `None` if the activity file is not loaded
+pub fn stage_activity(client: ClientType) -> Option<&'static StageActivityContent> {
     STAGE_ACTIVITY
         .as_ref()
         .and_then(|stage_activity| stage_activity.get_stage_activity(client))
-        .map(|c| c.has_side_story_open())
-        .unwrap_or(false)
 }
 
 pub fn display_stage_activity(client: ClientType) -> std::io::Result<()> {
@@ -27,9 +30,6 @@ pub fn display_stage_activity(client: ClientType) -> std::io::Result<()> {
     Ok(())
 }
 
-// TODO: use stage activity as alternative in fight task
-// This feature require allow custom input in select
-
 // TODO: use MinimumRequired to verify stage
 
 #[derive(Deserialize)]
@@ -116,6 +116,24 @@ impl StageActivityContent {
             .values()
             .any(|activity| activity.activity.is_active())
     }
+
+    /// Whether given stage is open at given time, `None` if it is not a stage of any side story
+    pub fn side_story_stage_open_at(&self, stage: &str, time: DateTime<Utc>) -> Option<bool> {
+        self.side_story_stage
+            .values()
+            .find(|activity| {
+                activity
+                    .stages
+                    .iter()
+                    .any(|info| info.value == stage || info.display == stage)
+            })
+            .map(|activity| activity.activity.time_info.is_active_at(time))
+    }
+
+    /// Whether all resource stages are open at given time
+    pub fn resource_collection_open_at(&self, time: DateTime<Utc>) -> bool {
+        self.resource_collection.time_info.is_active_at(time)
+    }
 }
 
 #[cfg_attr(test, derive(Debug, PartialEq, Clone))]
@@ -147,7 +165,8 @@ impl ActivityInfo {
 #[serde(rename_all = "PascalCase")]
 struct StageInfo {
     display: String,
-    // value: String,
+    #[serde(default)]
+    value: String,
     drop: String,
 }
 
@@ -60,6 +60,14 @@ pub(crate) enum Command {
         #[command(flatten)]
         common: config::cli::maa_core::CommonArgs,
     },
+    /// Roll back a component to a previously installed version
+    ///
+    /// Installed MaaCore and resources are kept before being replaced
+    /// by `maa install --force` or `maa update`,
+    /// use `maa version` to list versions available to roll back to.
+    #[cfg(feature = "core_installer")]
+    #[command(subcommand)]
+    Rollback(RollbackCommand),
     /// Manage maa-cli self
     ///
     /// This command is used to manage maa-cli self and maa-run.
@@ -104,7 +112,46 @@ pub(crate) enum Command {
         /// The task name is the name of the task file without the extension.
         /// The task file must be in the `tasks` directory of the config directory.
         /// The task file must be in the TOML, YAML or JSON format.
-        task: String,
+        /// When running multiple instances, it is the task of instances without a task file.
+        #[arg(required_unless_present = "fleet", verbatim_doc_comment)]
+        task: Option<String>,
+        #[command(flatten)]
+        fleet: run::FleetArgs,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Run custom tasks on a schedule in a long-running process
+    ///
+    /// The schedule is defined in `$MAA_CONFIG_DIR/schedule.toml` (or `.yaml`, `.json`),
+    /// which contains a list of jobs. Each job runs a task file on a cron expression
+    /// or a fixed interval, with an optional condition checked when the job is due.
+    /// Jobs are run one after another with the same profile,
+    /// and each run is recorded in the history in the state directory.
+    /// With `--dry-run`, the schedule and all task files will be checked,
+    /// and the upcoming runs of each job will be printed.
+    #[command(verbatim_doc_comment)]
+    Daemon {
+        /// Path of the schedule file, default to `$MAA_CONFIG_DIR/schedule.{toml,yaml,json}`
+        #[arg(long)]
+        schedule: Option<PathBuf>,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Serve a local HTTP API to control MaaCore
+    ///
+    /// This command keeps a MaaCore instance alive and serves a JSON API,
+    /// which can be used to append tasks, start or stop tasks, query the status,
+    /// fetch screenshots and stream callback messages.
+    /// The API has no authentication, so it only listens on localhost by default.
+    #[cfg(feature = "server")]
+    #[command(verbatim_doc_comment)]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8686")]
+        listen: String,
+        /// Client type of the game, used to load client specific resources
+        #[arg(long)]
+        client: Option<config::task::ClientType>,
         #[command(flatten)]
         common: run::CommonArgs,
     },
@@ -131,8 +178,63 @@ pub(crate) enum Command {
         #[command(flatten)]
         common: run::CommonArgs,
     },
-    /// Run copilot task
+    /// Run recruit task
+    Recruit {
+        #[command(flatten)]
+        params: run::preset::RecruitParams,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Run infrast task
+    Infrast {
+        #[command(flatten)]
+        params: run::preset::InfrastParams,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Run the daily routine: startup, fight, recruit, infrast, mall, award and closedown
+    ///
+    /// Options not given are read from the `[daily]` section of the profile.
+    Daily {
+        #[command(flatten)]
+        params: run::preset::DailyParams,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Run mall task to collect and spend credits
+    Mall {
+        #[command(flatten)]
+        params: run::preset::MallParams,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Run award task to collect rewards
+    Award {
+        #[command(flatten)]
+        params: run::preset::AwardParams,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Recognize items in the depot and export them
+    Depot {
+        #[command(flatten)]
+        params: run::preset::DepotParams,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Recognize owned operators and export them
+    #[command(name = "operbox")]
+    OperBox {
+        #[command(flatten)]
+        params: run::preset::OperBoxParams,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
+    /// Run copilot task, or search and show copilot tasks with subcommands
+    #[command(args_conflicts_with_subcommands = true)]
     Copilot {
+        #[command(subcommand)]
+        command: Option<run::preset::CopilotCommand>,
         #[command(flatten)]
         params: run::preset::CopilotParams,
         #[command(flatten)]
@@ -204,11 +306,37 @@ pub(crate) enum Command {
         #[arg(long)]
         timezone: Option<i8>,
     },
+    /// Show the next times matching a cron expression or upcoming periods in a calendar
+    ///
+    /// This command is used to check `Cron` and `Calendar` conditions in task files.
+    Next {
+        /// Cron expression, e.g. `* 4-11 * * mon-fri`
+        #[arg(required_unless_present = "calendar")]
+        cron: Option<config::cron::CronExpr>,
+        /// Calendar file, relative paths are resolved against the `calendars` directory
+        #[arg(long, conflicts_with = "cron")]
+        calendar: Option<PathBuf>,
+        /// Number of times or periods to show
+        #[arg(short = 'n', long, default_value_t = 5)]
+        count: usize,
+        /// Time zone, an offset from UTC like `8` or a client type like `Official`,
+        /// default to the local time zone
+        #[arg(long)]
+        timezone: Option<config::task::TimeOffset>,
+    },
     /// Clearing the caches of maa-cli and maa core
     Cleanup {
         /// Specify the path for deletion
         targets: Vec<cleanup::CleanupTarget>,
     },
+    /// Query the history of task runs
+    ///
+    /// Each run of custom tasks, predefined tasks and daemon jobs is recorded
+    /// in the state directory, with the profile, task file, errors and the summary of each task.
+    /// Use `list` to show recorded runs and `stats` to show aggregated statistics,
+    /// e.g. sanity used and drops of each stage in the last 7 days with `--days 7`.
+    #[command(subcommand, verbatim_doc_comment)]
+    History(crate::history::HistoryCommand),
     /// List all available tasks
     List,
     /// Import configuration files from a local path or a remote URL
@@ -259,6 +387,18 @@ pub(crate) enum SelfCommand {
     },
 }
 
+#[cfg(feature = "core_installer")]
+#[derive(Subcommand)]
+pub(crate) enum RollbackCommand {
+    /// Restore MaaCore and resources of a previously installed version
+    ///
+    /// The current version is kept, so it can be restored in the same way.
+    Core {
+        /// Version to roll back to, default to the newest version older than the current one
+        version: Option<semver::Version>,
+    },
+}
+
 #[derive(ValueEnum, Clone, Default)]
 pub(crate) enum Component {
     #[default]
@@ -286,6 +426,8 @@ pub(crate) enum Dir {
     HotUpdate,
     /// Directory of MaaCore's log
     Log,
+    /// Directory of state, including MaaCore's cache and run history
+    State,
 }
 
 #[cfg(test)]
@@ -382,6 +524,64 @@ mod test {
             } if url == "url"
         );
 
+        assert_matches!(
+            parse_from(["maa", "install", "--version", "v5.2.0"]).command,
+            Command::Install {
+                common: config::cli::maa_core::CommonArgs {
+                    version: Some(version),
+                    ..
+                },
+                ..
+            } if version == semver::Version::new(5, 2, 0)
+        );
+        assert!(Cli::try_parse_from(["maa", "install", "--version", "5.2"]).is_err());
+
+        assert_matches!(
+            parse_from(["maa", "install", "--connections", "4"]).command,
+            Command::Install {
+                common: config::cli::maa_core::CommonArgs {
+                    connections: Some(4),
+                    ..
+                },
+                ..
+            }
+        );
+        assert!(Cli::try_parse_from(["maa", "install", "--connections", "0"]).is_err());
+
+        assert_matches!(
+            parse_from(["maa", "install", "--from", "/mnt/maa"]).command,
+            Command::Install {
+                common: config::cli::maa_core::CommonArgs {
+                    from: Some(path),
+                    manifest: None,
+                    ..
+                },
+                ..
+            } if path == std::path::Path::new("/mnt/maa")
+        );
+        assert_matches!(
+            parse_from(["maa", "install", "--manifest", "file:///mnt/maa/stable.json"]).command,
+            Command::Install {
+                common: config::cli::maa_core::CommonArgs {
+                    from: None,
+                    manifest: Some(manifest),
+                    ..
+                },
+                ..
+            } if manifest == "file:///mnt/maa/stable.json"
+        );
+        assert!(
+            Cli::try_parse_from([
+                "maa",
+                "install",
+                "--from",
+                "/mnt/maa",
+                "--manifest",
+                "stable.json"
+            ])
+            .is_err()
+        );
+
         assert!(matches!(
             parse_from(["maa", "install", "--force"]).command,
             Command::Install { force: true, .. }
@@ -458,6 +658,24 @@ mod test {
         assert_matches!(parse_from(["maa", "dir", "log"]).command, Command::Dir {
             dir: Dir::Log
         });
+        assert_matches!(parse_from(["maa", "dir", "state"]).command, Command::Dir {
+            dir: Dir::State
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "core_installer")]
+    fn rollback() {
+        assert_matches!(
+            parse_from(["maa", "rollback", "core"]).command,
+            Command::Rollback(RollbackCommand::Core { version: None })
+        );
+        assert_matches!(
+            parse_from(["maa", "rollback", "core", "5.2.0"]).command,
+            Command::Rollback(RollbackCommand::Core { version: Some(version) })
+                if version == semver::Version::new(5, 2, 0)
+        );
+        assert!(Cli::try_parse_from(["maa", "rollback", "core", "latest"]).is_err());
     }
 
     #[test]
@@ -497,20 +715,69 @@ mod test {
         );
     }
 
+    #[test]
+    fn daemon() {
+        assert_matches!(parse_from(["maa", "daemon"]).command, Command::Daemon {
+            schedule: None,
+            common: run::CommonArgs { dry_run: false, .. },
+        });
+        assert_matches!(
+            parse_from(["maa", "daemon", "--schedule", "schedule.toml", "-p", "test"]).command,
+            Command::Daemon {
+                schedule: Some(schedule),
+                common: run::CommonArgs {
+                    profile: Some(profile),
+                    ..
+                },
+            } if schedule == Path::new("schedule.toml") && profile == "test"
+        );
+        assert_matches!(
+            parse_from(["maa", "daemon", "--dry-run"]).command,
+            Command::Daemon {
+                common: run::CommonArgs { dry_run: true, .. },
+                ..
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn serve() {
+        use config::task::ClientType;
+
+        assert_matches!(
+            parse_from(["maa", "serve"]).command,
+            Command::Serve {
+                listen,
+                client: None,
+                ..
+            } if listen == "127.0.0.1:8686"
+        );
+        assert_matches!(
+            parse_from(["maa", "serve", "--listen", "0.0.0.0:80", "--client", "YoStarEN"]).command,
+            Command::Serve {
+                listen,
+                client: Some(ClientType::YoStarEN),
+                ..
+            } if listen == "0.0.0.0:80"
+        );
+    }
+
     #[test]
     fn run() {
         assert_matches!(
             parse_from(["maa", "run", "task"]).command,
             Command::Run {
-                task,
+                task: Some(task),
+                fleet: run::FleetArgs { instances, fleet: None },
                 common: run::CommonArgs { .. },
-            } if task == "task"
+            } if task == "task" && instances.is_empty()
         );
 
         assert!(matches!(
             parse_from(["maa", "run", "task", "-a", "addr"]).command,
             Command::Run {
-                task,
+                task: Some(task),
                 common: run::CommonArgs {
                     addr: Some(addr),
                     ..
@@ -521,7 +788,7 @@ mod test {
         assert!(matches!(
             parse_from(["maa", "run", "task", "--addr", "addr"]).command,
             Command::Run {
-                task,
+                task: Some(task),
                 common: run::CommonArgs {
                     addr: Some(addr),
                     ..
@@ -533,7 +800,7 @@ mod test {
         assert!(matches!(
             parse_from(["maa", "run", "task", "--user-resource"]).command,
             Command::Run {
-                task,
+                task: Some(task),
                 common: run::CommonArgs {
                     user_resource: true,
                     ..
@@ -541,6 +808,82 @@ mod test {
                 ..
             } if task == "task"
         ));
+
+        assert_matches!(parse_from(["maa", "run", "task"]).command, Command::Run {
+            common: run::CommonArgs {
+                summary_format: run::SummaryFormat::Text,
+                summary_file: None,
+                ..
+            },
+            ..
+        });
+        assert_matches!(
+            parse_from([
+                "maa",
+                "run",
+                "task",
+                "--summary-format",
+                "json",
+                "--summary-file",
+                "summary.json"
+            ])
+            .command,
+            Command::Run {
+                common: run::CommonArgs {
+                    summary_format: run::SummaryFormat::Json,
+                    summary_file: Some(file),
+                    ..
+                },
+                ..
+            } if file == Path::new("summary.json")
+        );
+        assert!(
+            Cli::try_parse_from([
+                "maa",
+                "run",
+                "task",
+                "--no-summary",
+                "--summary-format",
+                "yaml"
+            ])
+            .is_err()
+        );
+
+        assert_matches!(
+            parse_from([
+                "maa",
+                "run",
+                "task",
+                "--instance",
+                "emu1@127.0.0.1:5555",
+                "--instance",
+                "emu2"
+            ])
+            .command,
+            Command::Run {
+                task: Some(task),
+                fleet: run::FleetArgs { instances, fleet: None },
+                ..
+            } if task == "task"
+                && instances.len() == 2
+                && instances[0].addr.as_deref() == Some("127.0.0.1:5555")
+                && instances[1].profile.as_deref() == Some("emu2")
+        );
+        assert_matches!(
+            parse_from(["maa", "run", "--fleet", "farm"]).command,
+            Command::Run {
+                task: None,
+                fleet: run::FleetArgs { fleet: Some(fleet), .. },
+                ..
+            } if fleet == Path::new("farm")
+        );
+        assert!(Cli::try_parse_from(["maa", "run"]).is_err());
+        assert!(Cli::try_parse_from(["maa", "run", "--instance", "emu1"]).is_err());
+        assert!(
+            Cli::try_parse_from(["maa", "run", "task", "--instance", "emu1", "-p", "emu2"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from(["maa", "run", "task", "--instance", "emu1@"]).is_err());
     }
 
     #[test]
@@ -613,6 +956,45 @@ mod test {
         );
     }
 
+    #[test]
+    fn next() {
+        assert_matches!(
+            parse_from(["maa", "next", "0 4 * * *"]).command,
+            Command::Next {
+                cron: Some(cron),
+                calendar: None,
+                count: 5,
+                timezone: None,
+            } if cron.to_string() == "0 4 * * *"
+        );
+
+        assert_matches!(
+            parse_from([
+                "maa",
+                "next",
+                "--calendar",
+                "holidays.ics",
+                "-n",
+                "3",
+                "--timezone",
+                "Official"
+            ])
+            .command,
+            Command::Next {
+                cron: None,
+                calendar: Some(calendar),
+                count: 3,
+                timezone: Some(config::task::TimeOffset::Client(
+                    config::task::ClientType::Official
+                )),
+            } if calendar == Path::new("holidays.ics")
+        );
+
+        assert!(Cli::try_parse_from(["maa", "next"]).is_err());
+        assert!(Cli::try_parse_from(["maa", "next", "0 25 * * *"]).is_err());
+        assert!(Cli::try_parse_from(["maa", "next", "@daily", "--timezone", "Mars"]).is_err());
+    }
+
     #[test]
     fn cleanup() {
         use cleanup::CleanupTarget::*;
@@ -637,6 +1019,63 @@ mod test {
         assert_matches!(parse_from(["maa", "list"]).command, Command::List);
     }
 
+    #[test]
+    fn history() {
+        use crate::history::{Filter, HistoryCommand};
+
+        assert_matches!(
+            parse_from(["maa", "history", "list"]).command,
+            Command::History(HistoryCommand::List { filter, limit: None })
+                if filter == Filter::default()
+        );
+        assert_matches!(
+            parse_from([
+                "maa", "history", "list", "-n", "5", "--since", "2024-01-01", "--until",
+                "2024-01-07", "-p", "alt", "--task", "daily"
+            ])
+            .command,
+            Command::History(HistoryCommand::List { filter, limit: Some(5) })
+                if filter == Filter {
+                    since: chrono::NaiveDate::from_ymd_opt(2024, 1, 1),
+                    until: chrono::NaiveDate::from_ymd_opt(2024, 1, 7),
+                    profile: Some("alt".to_owned()),
+                    task: Some("daily".to_owned()),
+                    ..Default::default()
+                }
+        );
+        assert_matches!(
+            parse_from(["maa", "history", "stats", "--days", "7", "--type", "Fight"]).command,
+            Command::History(HistoryCommand::Stats { filter })
+                if filter == Filter {
+                    days: Some(7),
+                    task_type: Some(maa_types::TaskType::Fight),
+                    ..Default::default()
+                }
+        );
+        assert_matches!(
+            parse_from(["maa", "history", "roguelike", "--days", "30", "--theme", "Sarkaz"])
+                .command,
+            Command::History(HistoryCommand::Roguelike { filter })
+                if filter == crate::history::roguelike::Filter {
+                    days: Some(30),
+                    theme: Some("Sarkaz".to_owned()),
+                    ..Default::default()
+                }
+        );
+        assert!(
+            Cli::try_parse_from([
+                "maa",
+                "history",
+                "stats",
+                "--days",
+                "7",
+                "--since",
+                "2024-01-01"
+            ])
+            .is_err()
+        );
+    }
+
     #[test]
     fn import() {
         assert_matches!(
@@ -10,6 +10,8 @@ use maa_core::Assistant;
 use maa_types::{InstanceOptionKey, StaticOptionKey, TouchMode};
 use serde::Deserialize;
 
+use super::daily::DailyConfig;
+
 #[cfg_attr(test, derive(Debug, PartialEq))]
 #[derive(Default, Clone)]
 pub struct AsstConfig {
@@ -18,6 +20,7 @@ pub struct AsstConfig {
     pub static_options: StaticOptions,
     pub instance_options: InstanceOptions,
     pub behavior: BehaviorConfig,
+    pub daily: DailyConfig,
 }
 
 impl AsstConfig {
@@ -27,6 +30,7 @@ impl AsstConfig {
         static_options: StaticOptions,
         mut instance_options: InstanceOptions,
         behavior: BehaviorConfig,
+        daily: DailyConfig,
     ) -> Self {
         if matches!(connection.preset, Preset::PlayCover) {
             info!("Detected connection with PlayTools");
@@ -40,6 +44,7 @@ impl AsstConfig {
             static_options,
             instance_options,
             behavior,
+            daily,
         }
     }
 }
@@ -61,6 +66,8 @@ impl<'de> Deserialize<'de> for AsstConfig {
             instance_options: InstanceOptions,
             #[serde(default)]
             behavior: BehaviorConfig,
+            #[serde(default)]
+            daily: DailyConfig,
         }
 
         let config = AsstConfigHelper::deserialize(deserializer)?;
@@ -71,6 +78,7 @@ impl<'de> Deserialize<'de> for AsstConfig {
             config.static_options,
             config.instance_options,
             config.behavior,
+            config.daily,
         ))
     }
 }
@@ -644,6 +652,7 @@ mod tests {
                     kill_adb_on_exit: Some(false),
                 },
                 behavior: BehaviorConfig::default(),
+                daily: DailyConfig::default(),
             });
         }
 
@@ -861,6 +870,7 @@ mod tests {
                         kill_adb_on_exit: None,
                     },
                     behavior: BehaviorConfig::default(),
+                    daily: DailyConfig::default(),
                 },
                 &[Token::Map { len: Some(0) }, Token::MapEnd],
             );
@@ -882,6 +892,7 @@ mod tests {
                         ..Default::default()
                     },
                     behavior: BehaviorConfig::default(),
+                    daily: DailyConfig::default(),
                 },
                 &[
                     Token::Map { len: Some(1) },
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::{cli::normalize_url, duration};
+
+/// Configuration of the copilot site used to download, search and show copilot tasks
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone)]
+pub struct Config {
+    #[serde(default = "default_api_url")]
+    api_url: String,
+    /// How long a downloaded copilot file is used before downloading it again,
+    /// cached files never expire if not set
+    #[serde(default, deserialize_with = "duration::deserialize_opt")]
+    cache_ttl: Option<Duration>,
+    /// How long search results and details of copilot tasks are cached
+    #[serde(
+        default = "default_search_ttl",
+        deserialize_with = "duration::deserialize"
+    )]
+    search_ttl: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            api_url: default_api_url(),
+            cache_ttl: None,
+            search_ttl: default_search_ttl(),
+        }
+    }
+}
+
+fn default_api_url() -> String {
+    "https://prts.maa.plus".to_owned()
+}
+
+fn default_search_ttl() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+impl Config {
+    pub fn api_url(&self) -> &str {
+        normalize_url(&self.api_url)
+    }
+
+    pub fn cache_ttl(&self) -> Option<Duration> {
+        self.cache_ttl
+    }
+
+    pub fn search_ttl(&self) -> Duration {
+        self.search_ttl
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub mod tests {
+    use super::*;
+
+    pub fn example_config() -> Config {
+        Config {
+            api_url: "https://prts.maa.plus".to_owned(),
+            cache_ttl: Some(Duration::from_secs(7 * 24 * 3600)),
+            search_ttl: Duration::from_secs(30 * 60),
+        }
+    }
+
+    #[test]
+    fn deserialize() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.api_url(), "https://prts.maa.plus");
+        assert_eq!(config.cache_ttl(), None);
+        assert_eq!(config.search_ttl(), Duration::from_secs(3600));
+
+        let config: Config = toml::from_str(
+            r#"
+            api_url = "http://127.0.0.1:8080/"
+            cache_ttl = "7d"
+            search_ttl = 600
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.api_url(), "http://127.0.0.1:8080");
+        assert_eq!(config.cache_ttl(), Some(Duration::from_secs(7 * 24 * 3600)));
+        assert_eq!(config.search_ttl(), Duration::from_secs(600));
+
+        assert!(toml::from_str::<Config>(r#"cache_ttl = "1 week""#).is_err());
+    }
+}
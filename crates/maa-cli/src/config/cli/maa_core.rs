@@ -1,4 +1,7 @@
+use std::path::PathBuf;
+
 use clap::Args;
+use semver::Version;
 use serde::Deserialize;
 
 use super::{Channel, normalize_url, return_true};
@@ -10,10 +13,16 @@ pub struct Config {
     channel: Channel,
     #[serde(default = "default_test_time")]
     test_time: u64,
+    /// Number of connections to download packages in parallel
+    #[serde(default = "default_connections")]
+    connections: usize,
     #[serde(default = "default_api_url")]
     api_url: String,
     #[serde(default)]
     components: Components,
+    /// Version to install, the latest version of the channel is installed if not set
+    #[serde(default, deserialize_with = "maa_version::deserialize_version_opt")]
+    version: Option<Version>,
 }
 
 impl Default for Config {
@@ -21,8 +30,10 @@ impl Default for Config {
         Config {
             channel: Default::default(),
             test_time: default_test_time(),
+            connections: default_connections(),
             api_url: default_api_url(),
             components: Default::default(),
+            version: None,
         }
     }
 }
@@ -46,8 +57,35 @@ impl Config {
         self
     }
 
+    /// Number of connections, clamped to `1..=MAX_CONNECTIONS`
+    pub fn connections(&self) -> usize {
+        self.connections.clamp(1, MAX_CONNECTIONS)
+    }
+
+    pub fn set_connections(&mut self, connections: usize) -> &Self {
+        self.connections = connections;
+        self
+    }
+
+    /// URL of the version manifest, the manifest of the pinned version if any
     pub fn api_url(&self) -> String {
-        format!("{}/{}.json", normalize_url(&self.api_url), self.channel())
+        format!("{}/{}", normalize_url(&self.api_url), self.manifest_file())
+    }
+
+    /// File name of the version manifest under the api url, also used in local mirrors
+    pub fn manifest_file(&self) -> String {
+        match self.version() {
+            Some(version) => format!("v{version}.json"),
+            None => format!("{}.json", self.channel()),
+        }
+    }
+
+    /// Name of the cached version manifest
+    pub fn manifest_name(&self) -> String {
+        match self.version() {
+            Some(version) => format!("core-manifest-v{version}.json"),
+            None => format!("core-manifest-{}.json", self.channel()),
+        }
     }
 
     pub fn set_api_url(&mut self, api_url: impl ToString) -> &Self {
@@ -64,6 +102,15 @@ impl Config {
         self
     }
 
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+
+    pub fn set_version(&mut self, version: Version) -> &Self {
+        self.version = Some(version);
+        self
+    }
+
     pub fn apply_args(mut self, args: &CommonArgs) -> Self {
         if let Some(channel) = args.channel {
             self.set_channel(channel);
@@ -71,12 +118,18 @@ impl Config {
         if let Some(test_time) = args.test_time {
             self.set_test_time(test_time);
         }
+        if let Some(connections) = args.connections {
+            self.set_connections(connections);
+        }
         if let Some(api_url) = &args.api_url {
             self.set_api_url(api_url);
         }
         if args.no_resource {
             self.set_components(|components| components.resource = false);
         }
+        if let Some(version) = &args.version {
+            self.set_version(version.clone());
+        }
         self
     }
 }
@@ -85,6 +138,13 @@ fn default_test_time() -> u64 {
     3
 }
 
+/// Maximum number of connections to download packages in parallel
+const MAX_CONNECTIONS: usize = 16;
+
+fn default_connections() -> usize {
+    1
+}
+
 fn default_api_url() -> String {
     String::from("https://api.maa.plus/MaaAssistantArknights/api/version/")
 }
@@ -147,12 +207,47 @@ pub struct CommonArgs {
     /// If test time is 0, speed test will be skipped.
     #[arg(short, long)]
     pub test_time: Option<u64>,
+    /// Number of connections to download maa-core prebuilt packages
+    ///
+    /// With more than one connection, the package is split into segments,
+    /// which are downloaded in parallel from the fastest mirror and other mirrors,
+    /// and an interrupted download can be resumed segment by segment.
+    /// This is useful when mirrors throttle the speed of each connection.
+    /// It falls back to one connection if the mirror does not support range requests.
+    /// You can also configure the default value in the cli configure file
+    /// `$MAA_CONFIG_DIR/cli.toml` with the key `core.connections`.
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=MAX_CONNECTIONS as u64))]
+    pub connections: Option<usize>,
     /// URL of api to get version information
     ///
     /// This flag is used to set the URL of api to get version information.
     /// It can also be changed by environment variable `MAA_API_URL`.
     #[arg(long)]
     pub api_url: Option<String>,
+    /// Version of maa-core to install, e.g. 5.2.0
+    ///
+    /// By default, the latest version of the channel is installed.
+    /// With this flag, the given version is installed from its own version manifest,
+    /// and the channel is ignored.
+    /// You can also pin the version in the cli configure file
+    /// `$MAA_CONFIG_DIR/cli.toml` with the key `core.version`,
+    /// then `maa update` will never update maa-core past the pinned version.
+    #[arg(long, value_parser = maa_version::parse_version)]
+    pub version: Option<Version>,
+    /// Install from a local archive or a local mirror directory, without network
+    ///
+    /// The archive is a prebuilt package of maa-core, e.g. `MAA-v5.2.0-linux-x86_64.tar.gz`.
+    /// A mirror directory contains version manifests named like the api,
+    /// e.g. `stable.json` or `v5.2.0.json`, and the packages they describe.
+    /// If a checksum file with the suffix `.sha256` is next to the package,
+    /// the package is verified against it.
+    #[arg(long, conflicts_with = "manifest")]
+    pub from: Option<PathBuf>,
+    /// Local version manifest to install from, e.g. `file:///mnt/maa/stable.json`
+    ///
+    /// The package described by the manifest is read from the directory of the manifest.
+    #[arg(long)]
+    pub manifest: Option<String>,
 }
 
 #[cfg(test)]
@@ -166,11 +261,13 @@ pub mod tests {
         Config {
             channel: Channel::Beta,
             test_time: 0,
+            connections: 4,
             api_url: "https://github.com/MaaAssistantArknights/MaaRelease/raw/main/MaaAssistantArknights/api/version/".to_string(),
             components: Components {
                 library: true,
                 resource: true,
             },
+            version: None,
         }
     }
 
@@ -215,11 +312,13 @@ pub mod tests {
                 &Config {
                     channel: Default::default(),
                     test_time: default_test_time(),
+                    connections: default_connections(),
                     api_url: default_api_url(),
                     components: Components {
                         library: true,
                         resource: true,
                     },
+                    version: None,
                 },
                 &[Token::Map { len: Some(0) }, Token::MapEnd],
             );
@@ -233,11 +332,13 @@ pub mod tests {
                 &Config {
                     channel: Channel::Beta,
                     test_time: 10,
+                    connections: default_connections(),
                     api_url: "https://foo.bar/api/".to_owned(),
                     components: Components {
                         library: false,
                         resource: false,
                     },
+                    version: None,
                 },
                 &[
                     Token::Map { len: Some(4) },
@@ -282,6 +383,14 @@ pub mod tests {
             assert_eq!(default_config().set_test_time(5).test_time(), 5);
         }
 
+        #[test]
+        fn connections() {
+            assert_eq!(DEFAULT_CONFIG.connections(), 1);
+            assert_eq!(default_config().set_connections(4).connections(), 4);
+            assert_eq!(default_config().set_connections(0).connections(), 1);
+            assert_eq!(default_config().set_connections(100).connections(), 16);
+        }
+
         #[test]
         fn api_url() {
             assert_eq!(
@@ -304,6 +413,38 @@ pub mod tests {
             );
         }
 
+        #[test]
+        fn version() {
+            assert_eq!(DEFAULT_CONFIG.version(), None);
+            assert_eq!(DEFAULT_CONFIG.manifest_name(), "core-manifest-stable.json");
+            assert_eq!(DEFAULT_CONFIG.manifest_file(), "stable.json");
+
+            let mut config = default_config();
+            config.set_channel(Channel::Beta);
+            config.set_version(Version::new(5, 2, 0));
+            assert_eq!(config.version(), Some(&Version::new(5, 2, 0)));
+            assert_eq!(
+                config.api_url(),
+                "https://api.maa.plus/MaaAssistantArknights/api/version/v5.2.0.json"
+            );
+            assert_eq!(config.manifest_name(), "core-manifest-v5.2.0.json");
+            assert_eq!(config.manifest_file(), "v5.2.0.json");
+
+            let config: Config = toml::from_str(r#"version = "v5.2.0""#).unwrap();
+            assert_eq!(config.version(), Some(&Version::new(5, 2, 0)));
+            assert!(toml::from_str::<Config>(r#"version = "latest""#).is_err());
+
+            assert_eq!(
+                default_config()
+                    .apply_args(&CommonArgs {
+                        version: Some(Version::new(5, 1, 0)),
+                        ..Default::default()
+                    })
+                    .version(),
+                Some(&Version::new(5, 1, 0))
+            );
+        }
+
         #[test]
         fn components() {
             assert!(matches!(
@@ -369,17 +510,23 @@ pub mod tests {
                 apply_to_default(&CommonArgs {
                     channel: Some(Channel::Beta),
                     test_time: Some(5),
+                    connections: Some(4),
                     api_url: Some("https://foo.bar/maa_core/".to_string()),
                     no_resource: true,
+                    version: None,
+                    from: None,
+                    manifest: None,
                 }),
                 Config {
                     channel: Channel::Beta,
                     test_time: 5,
+                    connections: 4,
                     api_url: "https://foo.bar/maa_core/".to_string(),
                     components: Components {
                         resource: false,
                         ..Default::default()
                     },
+                    version: None,
                 }
             );
         }
@@ -3,8 +3,12 @@ pub mod maa_cli;
 #[cfg(feature = "core_installer")]
 pub mod maa_core;
 
+pub mod copilot;
+pub mod notify;
 pub mod resource;
 pub mod secret;
+#[cfg(feature = "__installer")]
+pub mod signature;
 
 pub mod hot_update;
 
@@ -31,6 +35,13 @@ pub struct CLIConfig {
     resource: resource::Config,
     #[serde(default)]
     hot_update: hot_update::Config,
+    #[serde(default)]
+    notify: notify::Config,
+    #[serde(default)]
+    copilot: copilot::Config,
+    #[cfg(feature = "__installer")]
+    #[serde(default)]
+    signature: signature::Config,
 }
 
 impl CLIConfig {
@@ -51,6 +62,19 @@ impl CLIConfig {
     pub fn hot_update_config(&self) -> &hot_update::Config {
         &self.hot_update
     }
+
+    pub fn notify_config(&self) -> &notify::Config {
+        &self.notify
+    }
+
+    pub fn copilot_config(&self) -> &copilot::Config {
+        &self.copilot
+    }
+
+    #[cfg(feature = "__installer")]
+    pub fn signature_config(&self) -> &signature::Config {
+        &self.signature
+    }
 }
 
 pub(crate) static CLI_CONFIG: LazyLock<CLIConfig> = LazyLock::new(|| {
@@ -141,11 +165,13 @@ mod tests {
             &[
                 Token::Map { len: Some(1) },
                 Token::Str("core"),
-                Token::Map { len: Some(4) },
+                Token::Map { len: Some(5) },
                 Token::Str("channel"),
                 Channel::Beta.to_token(),
                 Token::Str("test_time"),
                 Token::I64(0),
+                Token::Str("connections"),
+                Token::U64(4),
                 Token::Str("api_url"),
                 Token::Str(
                     "https://github.com/MaaAssistantArknights/MaaRelease/raw/main/MaaAssistantArknights/api/version/",
@@ -237,6 +263,10 @@ mod tests {
             cli: maa_cli::tests::example_config(),
             hot_update: hot_update::tests::example_config(),
             resource: resource::tests::example_config(),
+            notify: notify::tests::example_config(),
+            copilot: copilot::tests::example_config(),
+            #[cfg(feature = "__installer")]
+            signature: signature::tests::example_config(),
         };
 
         assert_eq!(config, expect);
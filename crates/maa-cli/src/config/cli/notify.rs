@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{return_true, secret::Secret};
+
+/// Notification sinks triggered when a run is finished
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Default, Clone)]
+pub struct Config {
+    /// Send a POST request with the notification as JSON body
+    #[serde(default)]
+    pub webhook: Vec<Webhook>,
+    /// Run a local command with the notification as JSON on stdin
+    #[serde(default)]
+    pub command: Vec<Command>,
+    /// Send an email by SMTP
+    #[serde(default)]
+    pub smtp: Vec<Smtp>,
+}
+
+impl Config {
+    pub fn is_empty(&self) -> bool {
+        self.webhook.is_empty() && self.command.is_empty() && self.smtp.is_empty()
+    }
+}
+
+/// Events that trigger notifications
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// The run finished without error
+    #[serde(rename = "complete")]
+    Complete,
+    /// The run failed
+    #[serde(rename = "error")]
+    Error,
+    /// The run was stopped because the game went offline and auto reconnect is disabled
+    #[serde(rename = "offline")]
+    Offline,
+    /// Tags of a 6-star operator were detected in recruitment
+    #[serde(rename = "recruit_6star")]
+    Recruit6Star,
+    /// Rare tags like Top Operator were detected in recruitment
+    #[serde(rename = "recruit_rare_tag")]
+    RecruitRareTag,
+}
+
+impl Event {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Event::Complete => "complete",
+            Event::Error => "error",
+            Event::Offline => "offline",
+            Event::Recruit6Star => "recruit_6star",
+            Event::RecruitRareTag => "recruit_rare_tag",
+        }
+    }
+}
+
+/// Per sink filter of events, all events are enabled by default
+///
+/// Unknown keys of a sink are rejected, so a misspelled event is not silently enabled.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone)]
+#[serde(try_from = "EventsConfig")]
+pub struct Events {
+    on_complete: bool,
+    /// Also enables notifications when the game went offline
+    on_error: bool,
+    on_recruit_6star: bool,
+    on_recruit_rare_tag: bool,
+}
+
+/// Events with other keys of the sink
+///
+/// The events are flattened into the sink, where `deny_unknown_fields` has no effect, so the
+/// remaining keys are collected and checked instead.
+#[derive(Deserialize)]
+struct EventsConfig {
+    #[serde(default = "return_true")]
+    on_complete: bool,
+    #[serde(default = "return_true")]
+    on_error: bool,
+    #[serde(default = "return_true")]
+    on_recruit_6star: bool,
+    #[serde(default = "return_true")]
+    on_recruit_rare_tag: bool,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, serde::de::IgnoredAny>,
+}
+
+impl TryFrom<EventsConfig> for Events {
+    type Error = String;
+
+    fn try_from(config: EventsConfig) -> Result<Self, Self::Error> {
+        if let Some(key) = config.unknown.keys().next() {
+            return Err(format!(
+                "unknown field `{key}`, expected one of `on_complete`, `on_error`, \
+                 `on_recruit_6star`, `on_recruit_rare_tag`"
+            ));
+        }
+
+        Ok(Self {
+            on_complete: config.on_complete,
+            on_error: config.on_error,
+            on_recruit_6star: config.on_recruit_6star,
+            on_recruit_rare_tag: config.on_recruit_rare_tag,
+        })
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self {
+            on_complete: true,
+            on_error: true,
+            on_recruit_6star: true,
+            on_recruit_rare_tag: true,
+        }
+    }
+}
+
+impl Events {
+    pub fn accepts(&self, event: Event) -> bool {
+        match event {
+            Event::Complete => self.on_complete,
+            Event::Error | Event::Offline => self.on_error,
+            Event::Recruit6Star => self.on_recruit_6star,
+            Event::RecruitRareTag => self.on_recruit_rare_tag,
+        }
+    }
+
+    pub fn accepts_any(&self, events: &[Event]) -> bool {
+        events.iter().any(|&event| self.accepts(event))
+    }
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone)]
+pub struct Webhook {
+    pub url: String,
+    /// Extra headers of the request, e.g. `Authorization`
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+    #[serde(flatten)]
+    pub events: Events,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone)]
+pub struct Command {
+    /// Program and its arguments
+    pub command: Vec<String>,
+    #[serde(flatten)]
+    pub events: Events,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone)]
+pub struct Smtp {
+    pub host: String,
+    /// Port of the SMTP server, default to the standard port of the security mode
+    #[serde(default)]
+    port: Option<u16>,
+    #[serde(default)]
+    pub security: Security,
+    /// Username to login, login is skipped if not set
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Secret,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(flatten)]
+    pub events: Events,
+}
+
+impl Smtp {
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.security {
+            Security::Tls => 465,
+            Security::StartTls => 587,
+            Security::None => 25,
+        })
+    }
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// Connect with TLS directly
+    Tls,
+    /// Connect in plain text and upgrade to TLS with `STARTTLS`
+    #[default]
+    StartTls,
+    /// Plain text connection, only use it for local servers
+    None,
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub mod tests {
+    use super::*;
+
+    pub fn example_config() -> Config {
+        Config {
+            webhook: vec![Webhook {
+                url: "https://example.com/maa".to_owned(),
+                headers: [(
+                    "Authorization".to_owned(),
+                    Secret::Env("MAA_WEBHOOK_TOKEN".to_owned()),
+                )]
+                .into(),
+                events: Events::default(),
+            }],
+            command: vec![Command {
+                command: vec!["notify-maa".to_owned(), "--json".to_owned()],
+                events: Events {
+                    on_complete: false,
+                    ..Default::default()
+                },
+            }],
+            smtp: vec![Smtp {
+                host: "smtp.example.com".to_owned(),
+                port: None,
+                security: Security::Tls,
+                username: Some("maa@example.com".to_owned()),
+                password: Secret::Command(vec![
+                    "pass".to_owned(),
+                    "show".to_owned(),
+                    "smtp".to_owned(),
+                ]),
+                from: "maa@example.com".to_owned(),
+                to: vec!["me@example.com".to_owned()],
+                events: Events {
+                    on_complete: false,
+                    on_error: true,
+                    on_recruit_6star: true,
+                    on_recruit_rare_tag: false,
+                },
+            }],
+        }
+    }
+
+    #[test]
+    fn deserialize() {
+        let config: Config = toml::from_str(
+            r#"
+            [[webhook]]
+            url = "https://example.com/hook"
+
+            [[smtp]]
+            host = "localhost"
+            security = "none"
+            from = "a@example.com"
+            to = ["b@example.com"]
+            on_complete = false
+            on_recruit_rare_tag = false
+            "#,
+        )
+        .unwrap();
+
+        assert!(!config.is_empty());
+        assert!(config.command.is_empty());
+        assert_eq!(config.webhook[0].url, "https://example.com/hook");
+        assert!(config.webhook[0].headers.is_empty());
+        assert_eq!(config.webhook[0].events, Events::default());
+
+        let smtp = &config.smtp[0];
+        assert_eq!(smtp.security, Security::None);
+        assert_eq!(smtp.port(), 25);
+        assert_eq!(smtp.username, None);
+        assert_eq!(smtp.password, Secret::None);
+        assert!(!smtp.events.accepts(Event::Complete));
+        assert!(smtp.events.accepts(Event::Error));
+        assert!(smtp.events.accepts(Event::Offline));
+        assert!(
+            smtp.events
+                .accepts_any(&[Event::Complete, Event::Recruit6Star])
+        );
+        assert!(!smtp.events.accepts_any(&[Event::Complete]));
+        assert!(
+            !smtp
+                .events
+                .accepts_any(&[Event::Complete, Event::RecruitRareTag])
+        );
+
+        assert!(Config::default().is_empty());
+    }
+
+    #[test]
+    fn unknown_event() {
+        assert!(
+            toml::from_str::<Config>(
+                r#"
+                [[command]]
+                command = ["notify-send"]
+                on_compelte = false
+                "#,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn smtp_port() {
+        let smtp = |security, port| Smtp {
+            host: "localhost".to_owned(),
+            port,
+            security,
+            username: None,
+            password: Secret::None,
+            from: String::new(),
+            to: Vec::new(),
+            events: Events::default(),
+        };
+
+        assert_eq!(smtp(Security::Tls, None).port(), 465);
+        assert_eq!(smtp(Security::StartTls, None).port(), 587);
+        assert_eq!(smtp(Security::None, None).port(), 25);
+        assert_eq!(smtp(Security::Tls, Some(2525)).port(), 2525);
+    }
+}
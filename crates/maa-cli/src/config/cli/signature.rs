@@ -0,0 +1,59 @@
+use serde::Deserialize;
+
+/// Public key pinned at build time to verify signatures of MaaCore and maa-cli packages
+const PINNED_PUBLIC_KEY: Option<&str> = option_env!("MAA_SIGNATURE_PUBLIC_KEY");
+
+/// Configuration of signature verification of downloaded packages
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone, Default)]
+pub struct Config {
+    /// Public key in base64 to verify signatures, overriding the pinned one
+    public_key: Option<String>,
+    /// Whether to reject packages without signature
+    #[serde(default)]
+    required: bool,
+}
+
+impl Config {
+    pub fn public_key(&self) -> Option<&str> {
+        self.public_key.as_deref().or(PINNED_PUBLIC_KEY)
+    }
+
+    pub fn required(&self) -> bool {
+        self.required
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub mod tests {
+    use super::*;
+
+    pub fn example_config() -> Config {
+        Config {
+            public_key: None,
+            required: false,
+        }
+    }
+
+    #[test]
+    fn deserialize() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.public_key(), PINNED_PUBLIC_KEY);
+        assert!(!config.required());
+
+        let config: Config = toml::from_str(
+            r#"
+            public_key = "11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo="
+            required = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.public_key(),
+            Some("11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=")
+        );
+        assert!(config.required());
+    }
+}
@@ -0,0 +1,353 @@
+//! A minimal parser and evaluator of standard 5-field cron expressions.
+//!
+//! The supported syntax is `minute hour day-of-month month day-of-week`, where each field can be
+//! `*`, a single value, a range `a-b`, a step `*/n` or `a-b/n`, or a comma separated list of the
+//! above. Months and weekdays also accept three letter English names (`jan`, `mon`, ...), and both
+//! `0` and `7` stand for Sunday. Following the convention of cron, if both day-of-month and
+//! day-of-week are restricted, a day matches when either of them matches.
+//!
+//! The macros `@yearly`, `@annually`, `@monthly`, `@weekly`, `@daily`, `@midnight` and `@hourly`
+//! are also supported.
+
+use std::{fmt, str::FromStr};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde::Deserialize;
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone)]
+pub struct CronExpr {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Search at most this many days ahead before giving up, e.g. for `0 0 30 2 *`.
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+impl CronExpr {
+    pub fn parse(s: &str) -> Result<Self, InvalidCronError> {
+        let source = s.trim();
+        let expanded = match source {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            s => s,
+        };
+
+        let err = |msg: &str| InvalidCronError {
+            expr: source.to_owned(),
+            msg: msg.to_owned(),
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(err("expected 5 fields"));
+        };
+
+        let weekdays = parse_field(weekday, 0, 7, &WEEKDAY_NAMES).map_err(|e| err(&e))?;
+        // 7 is an alias of Sunday
+        let weekdays = (weekdays | (weekdays >> 7)) & 0x7f;
+
+        Ok(Self {
+            source: source.to_owned(),
+            minutes: parse_field(minute, 0, 59, &[]).map_err(|e| err(&e))?,
+            hours: parse_field(hour, 0, 23, &[]).map_err(|e| err(&e))?,
+            days: parse_field(day, 1, 31, &[]).map_err(|e| err(&e))?,
+            months: parse_field(month, 1, 12, &MONTH_NAMES).map_err(|e| err(&e))?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !bit(self.months, date.month()) {
+            return false;
+        }
+
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// Check whether the minute of given time matches the expression
+    pub fn matches(&self, time: NaiveDateTime) -> bool {
+        self.matches_date(time.date())
+            && bit(self.hours, time.hour())
+            && bit(self.minutes, time.minute())
+    }
+
+    /// Get the first time strictly after the given time that matches the expression
+    ///
+    /// Returns `None` if there is no such time in the next few years,
+    /// which means the expression can never be matched, e.g. `0 0 30 2 *`.
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = time.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+
+        let mut date = start.date();
+        let mut from = start.time();
+        for _ in 0..MAX_SEARCH_DAYS {
+            if self.matches_date(date)
+                && let Some(time) = self.first_time_from(from)
+            {
+                return Some(date.and_time(time));
+            }
+            date = date.succ_opt()?;
+            from = NaiveTime::MIN;
+        }
+
+        None
+    }
+
+    fn first_time_from(&self, from: NaiveTime) -> Option<NaiveTime> {
+        (from.hour()..24)
+            .filter(|h| bit(self.hours, *h))
+            .find_map(|h| {
+                let start_minute = if h == from.hour() { from.minute() } else { 0 };
+                (start_minute..60)
+                    .find(|m| bit(self.minutes, *m))
+                    .and_then(|m| NaiveTime::from_hms_opt(h, m, 0))
+            })
+    }
+}
+
+fn bit(set: u64, n: u32) -> bool {
+    set & (1 << n) != 0
+}
+
+/// Parse a single field into a bit set, where bit `n` is set if value `n` is allowed.
+///
+/// Names are mapped to values starting from `min`, e.g. `jan` is 1 and `sun` is 0.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let parse_value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_lowercase();
+        if let Some(index) = names.iter().position(|name| *name == lower) {
+            return Ok(index as u32 + min);
+        }
+        let value = s
+            .parse::<u32>()
+            .map_err(|_| format!("invalid value `{s}`"))?;
+        if value < min || value > max {
+            return Err(format!("value `{value}` out of range {min}-{max}"));
+        }
+        Ok(value)
+    };
+
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("invalid step `{step}`"))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let (start, end) = if range == "*" {
+            // `*` of weekdays should not include the alias 7
+            (min, if max == 7 { 6 } else { max })
+        } else if let Some((start, end)) = range.split_once('-') {
+            let (start, end) = (parse_value(start)?, parse_value(end)?);
+            if start > end {
+                return Err(format!("invalid range `{range}`"));
+            }
+            (start, end)
+        } else {
+            let value = parse_value(range)?;
+            // `a/n` means from `a` to the max with step `n`
+            (value, if step.is_some() { max } else { value })
+        };
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok(set)
+}
+
+impl FromStr for CronExpr {
+    type Err = InvalidCronError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for CronExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for CronExpr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug)]
+pub struct InvalidCronError {
+    expr: String,
+    msg: String,
+}
+
+impl fmt::Display for InvalidCronError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid cron expression `{}`: {}", self.expr, self.msg)
+    }
+}
+
+impl std::error::Error for InvalidCronError {}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    fn dt(y: i32, m: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
+
+    fn cron(s: &str) -> CronExpr {
+        CronExpr::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let expr = cron("*/15 4,16 1-7 * mon-fri");
+        assert_eq!(expr.minutes, 1 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(expr.hours, 1 << 4 | 1 << 16);
+        assert_eq!(expr.days, 0b1111_1110);
+        assert_eq!(expr.months, 0b1_1111_1111_1110);
+        assert_eq!(expr.weekdays, 0b011_1110);
+        assert!(!expr.any_day);
+        assert!(!expr.any_weekday);
+        assert_eq!(expr.to_string(), "*/15 4,16 1-7 * mon-fri");
+
+        assert_eq!(cron("0 0 * * 7").weekdays, 1);
+        assert_eq!(cron("0 0 * * 0").weekdays, 1);
+        assert_eq!(cron("0 0 * * SUN,sat").weekdays, 1 | 1 << 6);
+        assert_eq!(cron("0 0 * jan,Dec *").months, 1 << 1 | 1 << 12);
+        assert_eq!(cron("0 0 * * *").weekdays, 0x7f);
+        assert_eq!(cron("5/20 * * * *").minutes, 1 << 5 | 1 << 25 | 1 << 45);
+        assert_eq!(cron("@daily").minutes, 1);
+        assert_eq!(cron("@daily").to_string(), "@daily");
+
+        for invalid in [
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            "a * * * *",
+            "* * * foo *",
+            "@reboot",
+        ] {
+            assert!(CronExpr::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn matches_date() {
+        // 2024-01-01 is Monday
+        let expr = cron("0 0 13 * fri");
+        assert!(expr.matches_date(NaiveDate::from_ymd_opt(2024, 1, 13).unwrap())); // 13th
+        assert!(expr.matches_date(NaiveDate::from_ymd_opt(2024, 1, 5).unwrap())); // Friday
+        assert!(!expr.matches_date(NaiveDate::from_ymd_opt(2024, 1, 6).unwrap()));
+
+        let expr = cron("0 0 * * 1");
+        assert!(expr.matches_date(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()));
+        assert!(!expr.matches_date(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()));
+
+        let expr = cron("0 0 1 jun *");
+        assert!(expr.matches_date(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()));
+        assert!(!expr.matches_date(NaiveDate::from_ymd_opt(2024, 7, 1).unwrap()));
+    }
+
+    #[test]
+    fn matches() {
+        let expr = cron("* 4-11 * * mon-fri");
+        assert!(expr.matches(dt(2024, 1, 1, 4, 0)));
+        assert!(expr.matches(dt(2024, 1, 1, 11, 59) + Duration::seconds(30)));
+        assert!(!expr.matches(dt(2024, 1, 1, 12, 0)));
+        assert!(!expr.matches(dt(2024, 1, 6, 4, 0)));
+
+        let expr = cron("30 4 * * *");
+        assert!(expr.matches(dt(2024, 1, 1, 4, 30)));
+        assert!(!expr.matches(dt(2024, 1, 1, 4, 31)));
+    }
+
+    #[test]
+    fn next_after() {
+        let expr = cron("0 4 * * *");
+        assert_eq!(
+            expr.next_after(dt(2024, 1, 1, 3, 59)),
+            Some(dt(2024, 1, 1, 4, 0))
+        );
+        assert_eq!(
+            expr.next_after(dt(2024, 1, 1, 4, 0)),
+            Some(dt(2024, 1, 2, 4, 0))
+        );
+        assert_eq!(
+            expr.next_after(dt(2024, 12, 31, 5, 0)),
+            Some(dt(2025, 1, 1, 4, 0))
+        );
+
+        let expr = cron("*/20 * * * *");
+        assert_eq!(
+            expr.next_after(dt(2024, 1, 1, 23, 45)),
+            Some(dt(2024, 1, 2, 0, 0))
+        );
+        assert_eq!(
+            expr.next_after(dt(2024, 1, 1, 23, 0) + Duration::seconds(30)),
+            Some(dt(2024, 1, 1, 23, 20))
+        );
+
+        assert_eq!(
+            cron("0 0 29 2 *").next_after(dt(2024, 3, 1, 0, 0)),
+            Some(dt(2028, 2, 29, 0, 0))
+        );
+        assert_eq!(cron("0 0 30 2 *").next_after(dt(2024, 1, 1, 0, 0)), None);
+
+        assert_eq!(
+            cron("0 16 * * mon,thu").next_after(dt(2024, 1, 1, 17, 0)),
+            Some(dt(2024, 1, 4, 16, 0))
+        );
+    }
+}
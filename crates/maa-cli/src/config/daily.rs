@@ -0,0 +1,93 @@
+//! The `[daily]` section of a profile, which configures the `maa daily` command
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use super::task::ClientType;
+
+/// Steps of the daily routine, in the order they run
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, clap::ValueEnum, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Step {
+    Startup,
+    Fight,
+    Recruit,
+    Infrast,
+    Mall,
+    Award,
+    Closedown,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DailyConfig {
+    /// Client type of the game, used to start and close the game
+    #[serde(default)]
+    pub client_type: Option<ClientType>,
+    /// Stage to fight, leave empty to fight the current/last stage
+    #[serde(default)]
+    pub stage: Option<String>,
+    #[serde(default)]
+    pub medicine: Option<i32>,
+    #[serde(default)]
+    pub expiring_medicine: Option<i32>,
+    #[serde(default)]
+    pub stone: Option<i32>,
+    #[serde(default)]
+    pub recruit_times: Option<i32>,
+    /// Custom infrast plan file, leave empty to shift operators automatically
+    #[serde(default)]
+    pub infrast_plan: Option<PathBuf>,
+    /// Steps to skip
+    #[serde(default)]
+    pub skip: Vec<Step>,
+}
+
+impl DailyConfig {
+    pub fn skipped(&self, step: Step) -> bool {
+        self.skip.contains(&step)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        let config: DailyConfig = toml::from_str(
+            r#"
+            client_type = "YoStarEN"
+            stage = "1-7"
+            medicine = 2
+            recruit_times = 3
+            infrast_plan = "plan.json"
+            skip = ["mall", "closedown"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config, DailyConfig {
+            client_type: Some(ClientType::YoStarEN),
+            stage: Some("1-7".to_owned()),
+            medicine: Some(2),
+            recruit_times: Some(3),
+            infrast_plan: Some(PathBuf::from("plan.json")),
+            skip: vec![Step::Mall, Step::Closedown],
+            ..Default::default()
+        });
+        assert!(config.skipped(Step::Mall));
+        assert!(!config.skipped(Step::Fight));
+
+        assert_eq!(
+            toml::from_str::<DailyConfig>("").unwrap(),
+            DailyConfig::default()
+        );
+        assert!(toml::from_str::<DailyConfig>(r#"skip = ["kitchen"]"#).is_err());
+        assert!(toml::from_str::<DailyConfig>(r#"stages = "1-7""#).is_err());
+    }
+}
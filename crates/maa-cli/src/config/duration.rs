@@ -0,0 +1,159 @@
+//! Human readable durations used in config files, e.g. `90s`, `30m`, `1h30m` or `2d`.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Parse a human readable duration.
+///
+/// A duration is a sequence of `<number><unit>` pairs, where the unit is one of
+/// `d` (days), `h` (hours), `m` (minutes) and `s` (seconds). Whitespace between pairs is
+/// ignored. A bare number without unit is treated as seconds.
+pub fn parse(s: &str) -> Result<Duration, InvalidDurationError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(InvalidDurationError(s.to_owned()));
+    }
+
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total = 0u64;
+    let mut number: Option<u64> = None;
+    for c in s.chars() {
+        match c {
+            '0'..='9' => {
+                let digit = c as u64 - '0' as u64;
+                number = Some(
+                    number
+                        .unwrap_or_default()
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(digit))
+                        .ok_or_else(|| InvalidDurationError(s.to_owned()))?,
+                );
+            }
+            'd' | 'h' | 'm' | 's' => {
+                let n = number
+                    .take()
+                    .ok_or_else(|| InvalidDurationError(s.to_owned()))?;
+                let unit = match c {
+                    'd' => 24 * 60 * 60,
+                    'h' => 60 * 60,
+                    'm' => 60,
+                    _ => 1,
+                };
+                total = n
+                    .checked_mul(unit)
+                    .and_then(|secs| total.checked_add(secs))
+                    .ok_or_else(|| InvalidDurationError(s.to_owned()))?;
+            }
+            c if c.is_whitespace() && number.is_none() => {}
+            _ => return Err(InvalidDurationError(s.to_owned())),
+        }
+    }
+
+    // trailing number without unit, e.g. `1h30`
+    if number.is_some() {
+        return Err(InvalidDurationError(s.to_owned()));
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Deserialize a duration from a human readable string or an integer of seconds.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Seconds(u64),
+        Human(String),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Seconds(secs) => Ok(Duration::from_secs(secs)),
+        Raw::Human(s) => parse(&s).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Same as [`deserialize`], but for optional fields.
+pub fn deserialize_opt<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize")] Duration);
+
+    Option::<Wrapper>::deserialize(deserializer).map(|w| w.map(|Wrapper(d)| d))
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug)]
+pub struct InvalidDurationError(String);
+
+impl std::fmt::Display for InvalidDurationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid duration `{}`, expected something like `30m` or `1h30m`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidDurationError {}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn parse_duration() {
+        assert_eq!(parse("0"), Ok(secs(0)));
+        assert_eq!(parse("90"), Ok(secs(90)));
+        assert_eq!(parse("45s"), Ok(secs(45)));
+        assert_eq!(parse("30m"), Ok(secs(30 * 60)));
+        assert_eq!(parse("2h"), Ok(secs(2 * 3600)));
+        assert_eq!(parse("1d"), Ok(secs(86400)));
+        assert_eq!(parse("1h30m"), Ok(secs(5400)));
+        assert_eq!(parse("1h 30m 10s"), Ok(secs(5410)));
+        assert_eq!(parse(" 5m "), Ok(secs(300)));
+
+        assert!(parse("").is_err());
+        assert!(parse("h").is_err());
+        assert!(parse("1h30").is_err());
+        assert!(parse("1 h").is_err());
+        assert!(parse("1w").is_err());
+        assert!(parse("-1s").is_err());
+        assert!(parse("99999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn deserialize_duration() {
+        #[derive(Deserialize)]
+        struct Test {
+            #[serde(deserialize_with = "deserialize")]
+            a: Duration,
+            #[serde(default, deserialize_with = "deserialize_opt")]
+            b: Option<Duration>,
+        }
+
+        let t: Test = serde_json::from_str(r#"{"a": "1h", "b": 30}"#).unwrap();
+        assert_eq!(t.a, secs(3600));
+        assert_eq!(t.b, Some(secs(30)));
+
+        let t: Test = serde_json::from_str(r#"{"a": 10}"#).unwrap();
+        assert_eq!(t.a, secs(10));
+        assert_eq!(t.b, None);
+
+        assert!(serde_json::from_str::<Test>(r#"{"a": "1x"}"#).is_err());
+    }
+}
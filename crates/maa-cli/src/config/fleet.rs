@@ -0,0 +1,179 @@
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+
+/// Instances run in parallel by `maa run --fleet`
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FleetConfig {
+    #[serde(default)]
+    pub instances: Vec<Instance>,
+}
+
+impl FleetConfig {
+    /// Check that instances can be run together
+    pub fn validate(&self) -> Result<()> {
+        if self.instances.is_empty() {
+            bail!("No instance defined in fleet!");
+        }
+
+        let mut names = std::collections::HashSet::new();
+        for instance in &self.instances {
+            if !names.insert(instance.name()) {
+                bail!(
+                    "Duplicate instance name `{}` in fleet, set `name` to distinguish them!",
+                    instance.name()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A profile and device pair with the task to run on it
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Instance {
+    /// Name used in logs and the summary, default to the profile name or the address
+    #[serde(default)]
+    name: Option<String>,
+    /// Profile (asst config file) name, default to the default profile
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Address of the device, default to the address in the profile
+    #[serde(default)]
+    pub addr: Option<String>,
+    /// Name or path of the task file, default to the task given in command line
+    #[serde(default)]
+    pub task: Option<String>,
+}
+
+impl Instance {
+    pub fn name(&self) -> &str {
+        self.name
+            .as_deref()
+            .or(self.profile.as_deref())
+            .or(self.addr.as_deref())
+            .unwrap_or("default")
+    }
+}
+
+/// Parse an instance from `PROFILE[@ADDR]` given in command line
+impl FromStr for Instance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (profile, addr) = match s.split_once('@') {
+            Some((profile, addr)) => (profile, Some(addr)),
+            None => (s, None),
+        };
+        if addr.is_some_and(str::is_empty) {
+            return Err(format!("empty address in `{s}`"));
+        }
+
+        let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_owned());
+        let instance = Instance {
+            profile: non_empty(profile),
+            addr: addr.map(ToOwned::to_owned),
+            ..Default::default()
+        };
+        // Instances with the same profile are distinguished by address
+        let name = match (&instance.profile, &instance.addr) {
+            (Some(profile), Some(addr)) => format!("{profile}@{addr}"),
+            _ => instance.name().to_owned(),
+        };
+
+        Ok(Instance {
+            name: Some(name),
+            ..instance
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        let fleet: FleetConfig = toml::from_str(
+            r#"
+            [[instances]]
+            profile = "emu1"
+            task = "daily"
+
+            [[instances]]
+            name = "backup"
+            profile = "emu1"
+            addr = "127.0.0.1:5557"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(fleet.instances, [
+            Instance {
+                name: None,
+                profile: Some("emu1".to_owned()),
+                addr: None,
+                task: Some("daily".to_owned()),
+            },
+            Instance {
+                name: Some("backup".to_owned()),
+                profile: Some("emu1".to_owned()),
+                addr: Some("127.0.0.1:5557".to_owned()),
+                task: None,
+            },
+        ]);
+        assert_eq!(fleet.instances[0].name(), "emu1");
+        assert_eq!(fleet.instances[1].name(), "backup");
+        fleet.validate().unwrap();
+
+        assert!(toml::from_str::<FleetConfig>("[[instances]]\nunknown = 1").is_err());
+    }
+
+    #[test]
+    fn validate() {
+        assert!(FleetConfig::default().validate().is_err());
+
+        let fleet = FleetConfig {
+            instances: vec![
+                Instance {
+                    profile: Some("emu1".to_owned()),
+                    ..Default::default()
+                },
+                Instance {
+                    profile: Some("emu1".to_owned()),
+                    ..Default::default()
+                },
+            ],
+        };
+        assert!(fleet.validate().is_err());
+    }
+
+    #[test]
+    fn parse() {
+        let parse = |s: &str| s.parse::<Instance>();
+
+        let instance = parse("emu1").unwrap();
+        assert_eq!(instance.profile.as_deref(), Some("emu1"));
+        assert_eq!(instance.addr, None);
+        assert_eq!(instance.name(), "emu1");
+
+        let instance = parse("emu1@127.0.0.1:5555").unwrap();
+        assert_eq!(instance.profile.as_deref(), Some("emu1"));
+        assert_eq!(instance.addr.as_deref(), Some("127.0.0.1:5555"));
+        assert_eq!(instance.name(), "emu1@127.0.0.1:5555");
+
+        let instance = parse("@127.0.0.1:5555").unwrap();
+        assert_eq!(instance.profile, None);
+        assert_eq!(instance.name(), "127.0.0.1:5555");
+
+        assert_eq!(parse("").unwrap().name(), "default");
+        assert!(parse("emu1@").is_err());
+    }
+}
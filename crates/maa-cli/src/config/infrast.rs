@@ -0,0 +1,271 @@
+//! Custom infrast plan files, which are used by Infrast tasks in custom mode (`mode = 10000`)
+//!
+//! Plan files are read by MaaCore, so only the structure is checked here,
+//! to report mistakes before the task is started.
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result, bail};
+use chrono::{NaiveTime, Timelike};
+use serde::Deserialize;
+
+/// Mode of Infrast task to use a custom plan file
+pub const CUSTOM_MODE: i32 = 10000;
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+pub struct PlanFile {
+    pub plans: Vec<Plan>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+pub struct Plan {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Time ranges in a day to use this plan, e.g. `[["22:00", "23:59"], ["00:00", "05:59"]]`
+    #[serde(default)]
+    pub period: Vec<[Clock; 2]>,
+    // following fields are only used by MaaCore, and deserialized to check their structure
+    #[allow(dead_code)]
+    #[serde(default, rename = "Fiammetta")]
+    fiammetta: Option<Fiammetta>,
+    #[allow(dead_code)]
+    #[serde(default)]
+    drones: Option<Drones>,
+    #[allow(dead_code)]
+    #[serde(default)]
+    rooms: BTreeMap<Room, Vec<RoomPlan>>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct Fiammetta {
+    #[serde(default)]
+    enable: bool,
+    #[serde(default)]
+    target: Option<String>,
+    #[serde(default)]
+    order: Option<Order>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct Drones {
+    #[serde(default)]
+    enable: bool,
+    #[serde(default)]
+    room: Option<Room>,
+    #[serde(default)]
+    index: Option<u32>,
+    #[serde(default)]
+    rule: Option<String>,
+    #[serde(default)]
+    order: Option<Order>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Order {
+    Pre,
+    Post,
+}
+
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+enum Room {
+    Control,
+    Manufacture,
+    Trading,
+    Power,
+    Meeting,
+    Hire,
+    Dormitory,
+    Processing,
+    Training,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct RoomPlan {
+    #[serde(default)]
+    operators: Vec<String>,
+    #[serde(default)]
+    candidates: Vec<String>,
+    #[serde(default)]
+    sort: bool,
+    #[serde(default)]
+    skip: bool,
+    #[serde(default)]
+    autofill: bool,
+    #[serde(default)]
+    product: Option<String>,
+}
+
+/// Time of a day in `HH:MM` format
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Clock(NaiveTime);
+
+impl TryFrom<String> for Clock {
+    type Error = chrono::ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        NaiveTime::parse_from_str(&value, "%H:%M").map(Self)
+    }
+}
+
+impl std::fmt::Display for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.format("%H:%M"))
+    }
+}
+
+impl PlanFile {
+    /// Load and check a plan file
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open plan file {}", path.display()))?;
+        let plan_file: Self = serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("Invalid plan file {}", path.display()))?;
+
+        if plan_file.plans.is_empty() {
+            bail!("No plan in plan file {}", path.display());
+        }
+
+        Ok(plan_file)
+    }
+
+    /// Get the plan of given index, with an error if the index is out of range
+    pub fn plan(&self, index: usize) -> Result<&Plan> {
+        match self.plans.get(index) {
+            Some(plan) => Ok(plan),
+            None => bail!(
+                "Plan index {index} is out of range, there are only {} plans",
+                self.plans.len()
+            ),
+        }
+    }
+
+    /// Index of the first plan whose period contains given time
+    pub fn select(&self, time: NaiveTime) -> Option<usize> {
+        self.plans.iter().position(|plan| plan.contains(time))
+    }
+}
+
+impl Plan {
+    /// Whether the period of this plan contains given time
+    ///
+    /// Both ends of a range are inclusive in minutes, i.e. `23:59` contains `23:59:30`,
+    /// and a range ends before it starts crosses midnight.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let minutes = |t: NaiveTime| t.hour() * 60 + t.minute();
+        let now = minutes(time);
+        self.period.iter().any(|[start, end]| {
+            let (start, end) = (minutes(start.0), minutes(end.0));
+            if start <= end {
+                start <= now && now <= end
+            } else {
+                start <= now || now <= end
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 30).unwrap()
+    }
+
+    fn example() -> PlanFile {
+        serde_json::from_str(
+            r#"{
+                "title": "Example",
+                "plans": [
+                    {
+                        "name": "Night",
+                        "period": [["22:00", "23:59"], ["00:00", "05:59"]],
+                        "Fiammetta": { "enable": true, "target": "巫恋", "order": "pre" },
+                        "drones": { "enable": true, "room": "trading", "index": 1, "order": "post" },
+                        "rooms": {
+                            "control": [{ "operators": ["阿米娅"] }],
+                            "manufacture": [{ "operators": ["清流"], "product": "Battle Record" }]
+                        }
+                    },
+                    {
+                        "name": "Day",
+                        "period": [["06:00", "21:59"]],
+                        "rooms": { "dormitory": [{ "autofill": true }] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn deserialize() {
+        let plan_file = example();
+        assert_eq!(plan_file.plans.len(), 2);
+        assert_eq!(plan_file.plans[0].name.as_deref(), Some("Night"));
+        assert_eq!(plan_file.plans[0].period[1][1].to_string(), "05:59");
+        assert_eq!(plan_file.plans[0].rooms.len(), 2);
+
+        let invalid = |s: &str| serde_json::from_str::<PlanFile>(s).is_err();
+        assert!(invalid(r#"{}"#));
+        assert!(invalid(r#"{ "plans": [{ "period": [["6:00"]] }] }"#));
+        assert!(invalid(
+            r#"{ "plans": [{ "period": [["25:00", "26:00"]] }] }"#
+        ));
+        assert!(invalid(r#"{ "plans": [{ "rooms": { "kitchen": [] } }] }"#));
+        assert!(invalid(
+            r#"{ "plans": [{ "rooms": { "control": [{ "operators": "阿米娅" }] } }] }"#
+        ));
+        assert!(invalid(
+            r#"{ "plans": [{ "drones": { "order": "later" } }] }"#
+        ));
+    }
+
+    #[test]
+    fn select() {
+        let plan_file = example();
+        assert_eq!(plan_file.select(time(23, 59)), Some(0));
+        assert_eq!(plan_file.select(time(0, 0)), Some(0));
+        assert_eq!(plan_file.select(time(6, 0)), Some(1));
+        assert_eq!(plan_file.select(time(21, 59)), Some(1));
+
+        let wrapped: PlanFile =
+            serde_json::from_str(r#"{ "plans": [{}, { "period": [["22:00", "06:00"]] }] }"#)
+                .unwrap();
+        assert_eq!(wrapped.select(time(23, 0)), Some(1));
+        assert_eq!(wrapped.select(time(6, 0)), Some(1));
+        assert_eq!(wrapped.select(time(12, 0)), None);
+
+        assert!(plan_file.plan(1).is_ok());
+        assert!(plan_file.plan(2).is_err());
+    }
+
+    #[test]
+    fn load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plan.json");
+
+        std::fs::write(&path, r#"{ "plans": [{ "name": "A" }] }"#).unwrap();
+        assert_eq!(PlanFile::load(&path).unwrap().plans.len(), 1);
+
+        std::fs::write(&path, r#"{ "plans": [] }"#).unwrap();
+        assert!(PlanFile::load(&path).is_err());
+
+        assert!(PlanFile::load(&dir.path().join("missing.json")).is_err());
+    }
+}
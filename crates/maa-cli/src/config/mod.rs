@@ -181,6 +181,18 @@ pub mod task;
 
 pub mod init;
 
+pub mod cron;
+
+pub mod duration;
+
+pub mod schedule;
+
+pub mod fleet;
+
+pub mod infrast;
+
+pub mod daily;
+
 #[cfg(test)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 mod tests {
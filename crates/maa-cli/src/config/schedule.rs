@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use super::{
+    cron::CronExpr,
+    duration,
+    task::{Condition, TimeOffset},
+};
+
+/// Schedule of the daemon, loaded from `$MAA_CONFIG_DIR/schedule.{toml,yaml,json}` by default
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    #[serde(default)]
+    pub jobs: Vec<Job>,
+}
+
+impl ScheduleConfig {
+    /// Check that the schedule can be used by the daemon
+    pub fn validate(&self) -> Result<()> {
+        if self.jobs.is_empty() {
+            bail!("No job defined in schedule!");
+        }
+
+        let mut names = std::collections::HashSet::new();
+        for job in &self.jobs {
+            if !names.insert(job.name()) {
+                bail!("Duplicate job name `{}` in schedule!", job.name());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A task file run by the daemon on a schedule
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+#[serde(try_from = "JobRaw")]
+pub struct Job {
+    name: Option<String>,
+    /// Name or path of the task file, resolved in the same way as `maa run`
+    pub task: String,
+    pub trigger: Trigger,
+    /// Extra condition checked when the job is due, the job is skipped if it is not active
+    pub condition: Condition,
+}
+
+impl Job {
+    /// Name of the job, default to the name of the task file
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.task)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobRaw {
+    #[serde(default)]
+    name: Option<String>,
+    task: String,
+    #[serde(default)]
+    cron: Option<CronExpr>,
+    #[serde(default, deserialize_with = "duration::deserialize_opt")]
+    interval: Option<Duration>,
+    #[serde(default)]
+    timezone: Option<TimeOffset>,
+    #[serde(default)]
+    condition: Condition,
+}
+
+impl TryFrom<JobRaw> for Job {
+    type Error = String;
+
+    fn try_from(raw: JobRaw) -> Result<Self, Self::Error> {
+        let trigger = match (raw.cron, raw.interval) {
+            (Some(expr), None) => Trigger::Cron {
+                expr,
+                timezone: raw.timezone.unwrap_or_default(),
+            },
+            (None, Some(interval)) => {
+                if raw.timezone.is_some() {
+                    return Err(format!(
+                        "job `{}`: `timezone` is only allowed with `cron`",
+                        raw.task
+                    ));
+                }
+                if interval.is_zero() {
+                    return Err(format!("job `{}`: `interval` must be positive", raw.task));
+                }
+                Trigger::Interval(interval)
+            }
+            (Some(_), Some(_)) => {
+                return Err(format!(
+                    "job `{}`: only one of `cron` and `interval` can be set",
+                    raw.task
+                ));
+            }
+            (None, None) => {
+                return Err(format!(
+                    "job `{}`: one of `cron` and `interval` must be set",
+                    raw.task
+                ));
+            }
+        };
+
+        Ok(Job {
+            name: raw.name,
+            task: raw.task,
+            trigger,
+            condition: raw.condition,
+        })
+    }
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum Trigger {
+    /// Run when the cron expression matches, evaluated in the given time zone
+    Cron {
+        expr: CronExpr,
+        timezone: TimeOffset,
+    },
+    /// Run repeatedly with the given interval between the start of two runs
+    Interval(Duration),
+}
+
+impl Trigger {
+    /// Get the next time to run after the given time
+    ///
+    /// Returns `None` if the job will never be run.
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Trigger::Cron { expr, timezone } => {
+                let mut naive = timezone.date_time(time);
+                loop {
+                    naive = expr.next_after(naive)?;
+                    // skip local times that do not exist due to DST transition
+                    if let Some(next) = timezone.to_utc(naive) {
+                        return Some(next);
+                    }
+                }
+            }
+            Trigger::Interval(interval) => Some(time + *interval),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::config::task::ClientType;
+
+    #[test]
+    fn deserialize() {
+        let config: ScheduleConfig = toml::from_str(
+            r#"
+            [[jobs]]
+            task = "daily"
+            cron = "0 4,16 * * *"
+            timezone = "Official"
+
+            [[jobs]]
+            name = "fight"
+            task = "fight.toml"
+            interval = "6h"
+            condition = { type = "Weekday", weekdays = ["Sat", "Sun"] }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.jobs.len(), 2);
+        assert_eq!(config.jobs[0].name(), "daily");
+        assert_eq!(config.jobs[0].trigger, Trigger::Cron {
+            expr: CronExpr::parse("0 4,16 * * *").unwrap(),
+            timezone: TimeOffset::Client(ClientType::Official),
+        });
+        assert_eq!(config.jobs[0].condition, Condition::Always);
+
+        assert_eq!(config.jobs[1].name(), "fight");
+        assert_eq!(config.jobs[1].task, "fight.toml");
+        assert_eq!(
+            config.jobs[1].trigger,
+            Trigger::Interval(Duration::from_secs(6 * 3600))
+        );
+        assert_eq!(config.jobs[1].condition, Condition::Weekday {
+            weekdays: vec![chrono::Weekday::Sat, chrono::Weekday::Sun],
+            timezone: TimeOffset::Local,
+        });
+        config.validate().unwrap();
+
+        for invalid in [
+            r#"{ "task": "a" }"#,
+            r#"{ "task": "a", "cron": "0 4 * * *", "interval": "1h" }"#,
+            r#"{ "task": "a", "interval": "1h", "timezone": 8 }"#,
+            r#"{ "task": "a", "interval": "0s" }"#,
+            r#"{ "task": "a", "cron": "0 25 * * *" }"#,
+            r#"{ "task": "a", "cron": "0 4 * * *", "unknown": 1 }"#,
+        ] {
+            assert!(serde_json::from_str::<Job>(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn validate() {
+        assert!(ScheduleConfig::default().validate().is_err());
+
+        let config: ScheduleConfig = serde_json::from_str(
+            r#"{ "jobs": [
+                { "task": "daily", "interval": "1h" },
+                { "task": "daily", "cron": "@daily" }
+            ] }"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn next_after() {
+        let time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        let trigger = Trigger::Interval(Duration::from_secs(90));
+        assert_eq!(
+            trigger.next_after(time),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 1, 30).unwrap())
+        );
+
+        let trigger = Trigger::Cron {
+            expr: CronExpr::parse("0 4 * * *").unwrap(),
+            timezone: TimeOffset::TimeZone(8),
+        };
+        assert_eq!(
+            trigger.next_after(time),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 20, 0, 0).unwrap())
+        );
+
+        // Official server time zone is shifted by 4 hours, so 00:00 is 04:00 in UTC+8
+        let trigger = Trigger::Cron {
+            expr: CronExpr::parse("0 0 * * *").unwrap(),
+            timezone: TimeOffset::Client(ClientType::Official),
+        };
+        assert_eq!(
+            trigger.next_after(time),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 20, 0, 0).unwrap())
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! Calendars of date time periods, e.g. maintenance windows and event periods.
+//!
+//! A calendar can be an iCalendar (`.ics`) file, or a TOML, JSON or YAML file like:
+//!
+//! ```toml
+//! [[periods]]
+//! name = "Maintenance"
+//! start = "2024-08-01T10:00:00"
+//! end = "2024-08-01T16:00:00"
+//!
+//! # a date without time is the start of the day for start,
+//! # and the end of the day for end
+//! [[periods]]
+//! name = "Holiday"
+//! start = "2024-10-01"
+//! end = "2024-10-07"
+//! ```
+//!
+//! Date times are in the time zone of the condition, except for UTC times in iCalendar files.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+
+use super::TimeOffset;
+use crate::{
+    config::{FindFile, FromFile},
+    dirs,
+};
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CalendarFile {
+    #[serde(default)]
+    periods: Vec<PeriodFile>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PeriodFile {
+    #[serde(default)]
+    name: Option<String>,
+    start: Bound,
+    end: Bound,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Bound {
+    DateTime(NaiveDateTime),
+    Date(NaiveDate),
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Period {
+    pub name: Option<String>,
+    /// Start of the period, inclusive
+    pub start: NaiveDateTime,
+    /// End of the period, exclusive
+    pub end: NaiveDateTime,
+}
+
+impl Period {
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Calendar {
+    pub periods: Vec<Period>,
+}
+
+impl Calendar {
+    /// Load a calendar file with date times in given time zone
+    ///
+    /// Relative paths are resolved against the `calendars` directory in the config directory,
+    /// and the extension can be omitted for TOML, JSON and YAML files like task files.
+    pub fn load(path: &Path, timezone: TimeOffset) -> Result<Self> {
+        let path = dirs::expand_tilde(path);
+        let path = dirs::abs_config(&path, Some("calendars")).unwrap_or_else(|| path.into_owned());
+
+        let is_ics = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("ics"));
+        if is_ics {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            return Self::from_ics(&content, timezone)
+                .with_context(|| format!("Failed to parse {}", path.display()));
+        }
+
+        let file = if path.exists() {
+            CalendarFile::from_file(&path)
+        } else {
+            CalendarFile::find_file(&path)
+        }
+        .with_context(|| format!("Failed to load calendar {}", path.display()))?;
+
+        Ok(file.into())
+    }
+
+    /// Parse events in an iCalendar file
+    ///
+    /// Only `DTSTART`, `DTEND` and `SUMMARY` of `VEVENT` are used, and recurrence rules are not
+    /// supported. `TZID` is ignored, so such times are treated as in the time zone of the
+    /// condition.
+    fn from_ics(content: &str, timezone: TimeOffset) -> Result<Self> {
+        // Long lines are folded by a line break followed by a space or a tab
+        let unfolded = content
+            .replace("\r\n", "\n")
+            .replace("\n ", "")
+            .replace("\n\t", "");
+
+        let mut periods = Vec::new();
+        let mut event: Option<Event> = None;
+        for line in unfolded.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (name, params) = key.split_once(';').unwrap_or((key, ""));
+
+            match (name, value) {
+                ("BEGIN", "VEVENT") => event = Some(Event::default()),
+                ("END", "VEVENT") => {
+                    if let Some(event) = event.take() {
+                        periods.push(event.into_period()?);
+                    }
+                }
+                _ => {
+                    let Some(event) = &mut event else {
+                        continue;
+                    };
+                    match name {
+                        "SUMMARY" => event.summary = Some(value.to_owned()),
+                        "DTSTART" => event.start = Some(parse_ics_time(value, params, timezone)?),
+                        "DTEND" => event.end = Some(parse_ics_time(value, params, timezone)?),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(CalendarFile { periods }.into())
+    }
+
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        self.periods.iter().any(|period| period.contains(time))
+    }
+
+    /// Periods not ended at given time, sorted by their start
+    pub fn upcoming(&self, time: NaiveDateTime) -> Vec<&Period> {
+        let mut periods: Vec<_> = self.periods.iter().filter(|p| p.end > time).collect();
+        periods.sort_by_key(|p| p.start);
+        periods
+    }
+}
+
+#[derive(Default)]
+struct Event {
+    summary: Option<String>,
+    start: Option<Bound>,
+    end: Option<Bound>,
+}
+
+impl Event {
+    fn into_period(self) -> Result<PeriodFile> {
+        let Some(start) = self.start else {
+            bail!("Event without DTSTART");
+        };
+        let end = match (self.end, &start) {
+            // `DTEND` of all-day events is exclusive, while an end date in calendar files is
+            // inclusive, so convert it to the previous day
+            (Some(Bound::Date(date)), _) => Bound::Date(date.pred_opt().unwrap_or(date)),
+            (Some(end), _) => end,
+            // an all-day event without end lasts one day
+            (None, Bound::Date(date)) => Bound::Date(*date),
+            (None, Bound::DateTime(time)) => Bound::DateTime(*time),
+        };
+
+        Ok(PeriodFile {
+            name: self.summary,
+            start,
+            end,
+        })
+    }
+}
+
+fn parse_ics_time(value: &str, params: &str, timezone: TimeOffset) -> Result<Bound> {
+    if params.split(';').any(|param| param == "VALUE=DATE") {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d")
+            .with_context(|| format!("Invalid date `{value}`"))?;
+        return Ok(Bound::Date(date));
+    }
+
+    let (value, utc) = match value.strip_suffix('Z') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .with_context(|| format!("Invalid date time `{value}`"))?;
+
+    Ok(Bound::DateTime(if utc {
+        timezone.date_time(Utc.from_utc_datetime(&time))
+    } else {
+        time
+    }))
+}
+
+impl From<CalendarFile> for Calendar {
+    fn from(file: CalendarFile) -> Self {
+        let periods = file
+            .periods
+            .into_iter()
+            .map(|period| Period {
+                name: period.name,
+                start: match period.start {
+                    Bound::DateTime(time) => time,
+                    Bound::Date(date) => date.and_time(NaiveTime::MIN),
+                },
+                end: match period.end {
+                    Bound::DateTime(time) => time,
+                    Bound::Date(date) => date.succ_opt().unwrap_or(date).and_time(NaiveTime::MIN),
+                },
+            })
+            .collect();
+
+        Self { periods }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    fn dt(y: i32, m: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, mi, 0)
+            .unwrap()
+    }
+
+    fn period(name: &str, start: NaiveDateTime, end: NaiveDateTime) -> Period {
+        Period {
+            name: Some(name.to_owned()),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn from_file() {
+        let file: CalendarFile = toml::from_str(
+            r#"
+            [[periods]]
+            name = "Maintenance"
+            start = "2024-08-01T10:00:00"
+            end = "2024-08-01T16:00:00"
+
+            [[periods]]
+            name = "Holiday"
+            start = "2024-10-01"
+            end = "2024-10-07"
+            "#,
+        )
+        .unwrap();
+
+        let calendar = Calendar::from(file);
+        assert_eq!(calendar, Calendar {
+            periods: vec![
+                period("Maintenance", dt(2024, 8, 1, 10, 0), dt(2024, 8, 1, 16, 0)),
+                period("Holiday", dt(2024, 10, 1, 0, 0), dt(2024, 10, 8, 0, 0)),
+            ],
+        });
+
+        assert!(calendar.contains(dt(2024, 8, 1, 10, 0)));
+        assert!(!calendar.contains(dt(2024, 8, 1, 16, 0)));
+        assert!(calendar.contains(dt(2024, 10, 7, 23, 59)));
+        assert!(!calendar.contains(dt(2024, 10, 8, 0, 0)));
+
+        let upcoming = calendar.upcoming(dt(2024, 9, 1, 0, 0));
+        assert_eq!(upcoming.len(), 1);
+        assert_eq!(upcoming[0].name.as_deref(), Some("Holiday"));
+
+        assert!(toml::from_str::<CalendarFile>("[[periods]]\nstart = \"2024-08-01\"").is_err());
+    }
+
+    #[test]
+    fn from_ics() {
+        let content = "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Main\r\n \
+            tenance\r\n\
+            DTSTART:20240801T020000Z\r\n\
+            DTEND:20240801T080000Z\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Holiday\r\n\
+            DTSTART;VALUE=DATE:20241001\r\n\
+            DTEND;VALUE=DATE:20241008\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;TZID=Asia/Shanghai:20241225T040000\r\n\
+            DTEND;TZID=Asia/Shanghai:20241225T160000\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Anniversary\r\n\
+            DTSTART;VALUE=DATE:20250501\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let calendar = Calendar::from_ics(content, TimeOffset::TimeZone(8)).unwrap();
+        assert_eq!(calendar, Calendar {
+            periods: vec![
+                period("Maintenance", dt(2024, 8, 1, 10, 0), dt(2024, 8, 1, 16, 0)),
+                period("Holiday", dt(2024, 10, 1, 0, 0), dt(2024, 10, 8, 0, 0)),
+                Period {
+                    name: None,
+                    start: dt(2024, 12, 25, 4, 0),
+                    end: dt(2024, 12, 25, 16, 0),
+                },
+                period("Anniversary", dt(2025, 5, 1, 0, 0), dt(2025, 5, 2, 0, 0)),
+            ],
+        });
+
+        assert!(
+            Calendar::from_ics(
+                "BEGIN:VEVENT\nSUMMARY:Broken\nEND:VEVENT\n",
+                TimeOffset::Local
+            )
+            .is_err()
+        );
+        assert!(
+            Calendar::from_ics(
+                "BEGIN:VEVENT\nDTSTART:2024\nEND:VEVENT\n",
+                TimeOffset::Local
+            )
+            .is_err()
+        );
+    }
+}
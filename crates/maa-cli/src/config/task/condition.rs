@@ -1,8 +1,21 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
 use chrono::{DateTime, Datelike, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use log::warn;
 use serde::Deserialize;
 
-use super::client_type::ClientType;
-use crate::activity::has_side_story_open;
+use super::{calendar, client_type::ClientType};
+use crate::{
+    activity::has_side_story_open,
+    config::{cron::CronExpr, duration},
+    depot::Depot,
+    dirs, history,
+};
 
 #[cfg_attr(test, derive(PartialEq, Debug))]
 #[derive(Deserialize)]
@@ -63,6 +76,47 @@ pub enum Condition {
         #[serde(default)]
         client: ClientType,
     },
+    /// The task is active if the current minute matches the cron expression
+    ///
+    /// For example, `* 4-11 * * mon-fri` is active from 04:00:00 to 11:59:59 on weekdays.
+    Cron {
+        expr: CronExpr,
+        #[serde(default)]
+        timezone: TimeOffset,
+    },
+    /// The task is active if the current time is in any period of the calendar file
+    Calendar {
+        file: PathBuf,
+        #[serde(default)]
+        timezone: TimeOffset,
+    },
+    /// The task is active if the given task was not completed within the duration
+    ///
+    /// The task is matched by its name or type in the history of runs of the same profile,
+    /// and a task never completed is considered as completed long ago.
+    LastRunOlderThan {
+        task: String,
+        #[serde(deserialize_with = "duration::deserialize")]
+        duration: Duration,
+    },
+    /// The task is active if the file exists
+    ///
+    /// Relative paths are resolved against the config directory.
+    FileExists { path: PathBuf },
+    /// The task is active if the environment variable is set
+    ///
+    /// If `value` is given, the variable must also be equal to it.
+    EnvVar {
+        name: String,
+        #[serde(default)]
+        value: Option<String>,
+    },
+    /// The task is active if the count of the item is below the given count
+    ///
+    /// The item is matched by its name or id in the depot recognized by the last Depot task
+    /// of the same profile.
+    /// The task is inactive if the depot has never been recognized or the item is not found.
+    DepotItemBelow { item: String, count: i64 },
     /// The task is active if all the sub-conditions are met
     #[serde(alias = "Combined")]
     And { conditions: Vec<Condition> },
@@ -89,7 +143,7 @@ impl TimeOffset {
     }
 
     /// Get the naive date time of the given date time in the given time zone
-    fn date_time<TZ: TimeZone>(self, datetime: DateTime<TZ>) -> NaiveDateTime {
+    pub fn date_time<TZ: TimeZone>(self, datetime: DateTime<TZ>) -> NaiveDateTime {
         use TimeOffset::*;
         match self {
             TimeZone(tz) => datetime.with_timezone(&tz_to_offset(tz)).naive_local(),
@@ -99,6 +153,45 @@ impl TimeOffset {
             Local => datetime.with_timezone(&chrono::Local).naive_local(),
         }
     }
+
+    /// Interpret the naive date time in the given time zone
+    ///
+    /// Returns `None` if the local time does not exist, e.g. skipped by DST transition.
+    /// If the local time is ambiguous, the earliest one is returned.
+    pub fn to_utc(self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        use TimeOffset::*;
+        let offset = match self {
+            TimeZone(tz) => tz_to_offset(tz),
+            Client(client) => tz_to_offset(client.server_time_zone()),
+            Local => {
+                return chrono::Local
+                    .from_local_datetime(&naive)
+                    .earliest()
+                    .map(|dt| dt.with_timezone(&Utc));
+            }
+        };
+        offset
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+}
+
+/// Parse a time offset from an offset from UTC like `8`, a client type like `Official` or `Local`
+impl FromStr for TimeOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(TimeOffset::Local);
+        }
+        if let Ok(tz) = s.parse::<i8>() {
+            return Ok(TimeOffset::TimeZone(tz));
+        }
+        s.parse()
+            .map(TimeOffset::Client)
+            .map_err(|_| format!("invalid time zone `{s}`, expected an offset or a client type"))
+    }
 }
 
 fn tz_to_offset(tz: i8) -> chrono::FixedOffset {
@@ -106,7 +199,8 @@ fn tz_to_offset(tz: i8) -> chrono::FixedOffset {
 }
 
 impl Condition {
-    pub fn is_active(&self) -> bool {
+    /// Whether the condition is met, history and depot are looked up in given profile
+    pub fn is_active(&self, profile: Option<&str>) -> bool {
         use Condition::*;
         match *self {
             Always => true,
@@ -147,9 +241,30 @@ impl Condition {
                 }
             }
             OnSideStory { client } => has_side_story_open(client),
+            Cron { ref expr, timezone } => expr.matches(timezone.naive_now()),
+            Calendar { ref file, timezone } => match calendar::Calendar::load(file, timezone) {
+                Ok(calendar) => calendar.contains(timezone.naive_now()),
+                Err(e) => {
+                    warn!("{e:#}");
+                    false
+                }
+            },
+            LastRunOlderThan { ref task, duration } => last_run_older_than(profile, task, duration),
+            FileExists { ref path } => {
+                let path = dirs::expand_tilde(path);
+                dirs::abs_config(&path, None::<&str>)
+                    .unwrap_or_else(|| path.into_owned())
+                    .exists()
+            }
+            EnvVar {
+                ref name,
+                ref value,
+            } => std::env::var(name)
+                .is_ok_and(|actual| value.as_ref().is_none_or(|value| &actual == value)),
+            DepotItemBelow { ref item, count } => depot_item_below(profile, item, count),
             And { ref conditions } => {
                 for condition in conditions {
-                    if !condition.is_active() {
+                    if !condition.is_active(profile) {
                         return false;
                     }
                 }
@@ -157,13 +272,46 @@ impl Condition {
             }
             Or { ref conditions } => {
                 for condition in conditions {
-                    if condition.is_active() {
+                    if condition.is_active(profile) {
                         return true;
                     }
                 }
                 false
             }
-            Not { ref condition } => !condition.is_active(),
+            Not { ref condition } => !condition.is_active(profile),
+        }
+    }
+}
+
+fn last_run_older_than(profile: Option<&str>, task: &str, duration: Duration) -> bool {
+    match history::last_completed(profile, task) {
+        Ok(Some(last)) => (chrono::Local::now() - last)
+            .to_std()
+            .is_ok_and(|elapsed| elapsed > duration),
+        Ok(None) => true,
+        Err(e) => {
+            warn!("Failed to load history, assuming task {task} was never run: {e:#}");
+            true
+        }
+    }
+}
+
+fn depot_item_below(profile: Option<&str>, item: &str, count: i64) -> bool {
+    match Depot::load(profile) {
+        Ok(Some(depot)) => match depot.count(item) {
+            Some(have) => have < count,
+            None => {
+                warn!("Item {item} is not found in the depot, check the name or id");
+                false
+            }
+        },
+        Ok(None) => {
+            warn!("Depot has never been recognized, run a Depot task first");
+            false
+        }
+        Err(e) => {
+            warn!("Failed to load depot: {e:#}");
+            false
         }
     }
 }
@@ -176,6 +324,59 @@ fn time_in_range(time: NaiveTime, start: NaiveTime, end: NaiveTime) -> bool {
     }
 }
 
+/// Describe the next times matching a cron expression or the upcoming periods in a calendar
+///
+/// This is used to check `Cron` and `Calendar` conditions.
+pub fn describe_next(
+    cron: Option<&CronExpr>,
+    calendar: Option<&Path>,
+    count: usize,
+    timezone: TimeOffset,
+) -> anyhow::Result<String> {
+    let now = timezone.naive_now();
+    let calendar = calendar
+        .map(|file| calendar::Calendar::load(file, timezone))
+        .transpose()?;
+    Ok(describe_next_from(now, cron, calendar.as_ref(), count))
+}
+
+fn describe_next_from(
+    now: NaiveDateTime,
+    cron: Option<&CronExpr>,
+    calendar: Option<&calendar::Calendar>,
+    count: usize,
+) -> String {
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+    let mut output = String::new();
+    if let Some(cron) = cron {
+        let times = std::iter::successors(cron.next_after(now), |&time| cron.next_after(time));
+        for time in times.take(count) {
+            let _ = writeln!(output, "{}", time.format(FORMAT));
+        }
+    }
+
+    if let Some(calendar) = calendar {
+        for period in calendar.upcoming(now).into_iter().take(count) {
+            let _ = write!(
+                output,
+                "{} - {}",
+                period.start.format(FORMAT),
+                period.end.format(FORMAT)
+            );
+            if let Some(name) = &period.name {
+                let _ = write!(output, " {name}");
+            }
+            if period.contains(now) {
+                let _ = write!(output, " (active)");
+            }
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
 pub fn remainder_of_day_mod(tz: TimeOffset, divisor: u32) -> u32 {
     tz.naive_now().num_days_from_ce() as u32 % divisor
 }
@@ -183,7 +384,7 @@ pub fn remainder_of_day_mod(tz: TimeOffset, divisor: u32) -> u32 {
 #[cfg(test)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 mod tests {
-    use chrono::{Local, TimeZone};
+    use chrono::{Local, NaiveDate, TimeZone};
 
     use super::*;
 
@@ -201,7 +402,7 @@ mod tests {
 
         #[test]
         fn always() {
-            assert!(Condition::Always.is_active());
+            assert!(Condition::Always.is_active(None));
         }
 
         #[test]
@@ -220,14 +421,14 @@ mod tests {
                     weekdays: vec![weekday],
                     timezone: Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 !Condition::Weekday {
                     weekdays: vec![weekday.pred(), weekday.succ()],
                     timezone: Local,
                 }
-                .is_active()
+                .is_active(None)
             );
 
             assert_eq!(
@@ -235,7 +436,7 @@ mod tests {
                     weekdays: vec![weekday_in_cn],
                     timezone: Client(ClientType::Official),
                 }
-                .is_active(),
+                .is_active(None),
                 !should_be_prev_day
             );
 
@@ -244,7 +445,7 @@ mod tests {
                     weekdays: vec![weekday_in_cn.pred(), weekday_in_cn.succ()],
                     timezone: Client(ClientType::Official),
                 }
-                .is_active(),
+                .is_active(None),
                 should_be_prev_day
             );
         }
@@ -371,7 +572,7 @@ mod tests {
                     remainder: 0,
                     timezone: TimeOffset::Local,
                 }
-                .is_active()
+                .is_active(None)
             );
 
             assert_eq!(
@@ -380,7 +581,7 @@ mod tests {
                     remainder: 0,
                     timezone: TimeOffset::Local
                 }
-                .is_active(),
+                .is_active(None),
                 num_days.is_multiple_of(2)
             );
 
@@ -390,7 +591,7 @@ mod tests {
                     remainder: 1,
                     timezone: TimeOffset::Local
                 }
-                .is_active(),
+                .is_active(None),
                 num_days % 2 == 1
             );
         }
@@ -410,7 +611,7 @@ mod tests {
                     end: Some(now_time + seconds(10)),
                     timezone: TimeOffset::Local,
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 Condition::Time {
@@ -418,7 +619,7 @@ mod tests {
                     end: None,
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 Condition::Time {
@@ -426,7 +627,7 @@ mod tests {
                     end: Some(now_time + seconds(10)),
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 Condition::Time {
@@ -434,7 +635,7 @@ mod tests {
                     end: None,
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 !Condition::Time {
@@ -442,7 +643,7 @@ mod tests {
                     end: Some(now_time + seconds(20)),
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 !Condition::Time {
@@ -450,7 +651,7 @@ mod tests {
                     end: None,
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 !Condition::Time {
@@ -458,7 +659,7 @@ mod tests {
                     end: Some(now_time + seconds(-10)),
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
         }
 
@@ -498,7 +699,7 @@ mod tests {
                     end: Some(now_datetime + seconds(10)),
                     timezone: TimeOffset::Local,
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 Condition::DateTime {
@@ -506,7 +707,7 @@ mod tests {
                     end: None,
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 Condition::DateTime {
@@ -514,7 +715,7 @@ mod tests {
                     end: Some(now_datetime + seconds(10)),
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 Condition::DateTime {
@@ -522,7 +723,7 @@ mod tests {
                     end: None,
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 !Condition::DateTime {
@@ -530,7 +731,7 @@ mod tests {
                     end: Some(now_datetime + seconds(20)),
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 !Condition::DateTime {
@@ -538,7 +739,7 @@ mod tests {
                     end: None,
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 !Condition::DateTime {
@@ -546,7 +747,7 @@ mod tests {
                     end: Some(now_datetime + seconds(-10)),
                     timezone: TimeOffset::Local
                 }
-                .is_active()
+                .is_active(None)
             );
         }
 
@@ -554,13 +755,142 @@ mod tests {
         // #[test]
         // fn on_side_story() {}
 
+        #[test]
+        fn cron() {
+            use chrono::Timelike;
+
+            let now = TimeOffset::TimeZone(8).naive_now();
+            let expr = format!("* {} * * *", now.hour());
+            // skip the test if the hour changed during the test
+            let active = Condition::Cron {
+                expr: expr.parse().unwrap(),
+                timezone: TimeOffset::TimeZone(8),
+            }
+            .is_active(None);
+            if TimeOffset::TimeZone(8).naive_now().hour() == now.hour() {
+                assert!(active);
+            }
+
+            assert!(
+                !Condition::Cron {
+                    expr: "0 0 30 2 *".parse().unwrap(),
+                    timezone: TimeOffset::Local,
+                }
+                .is_active(None)
+            );
+        }
+
+        #[test]
+        fn calendar() {
+            use maa_dirs::Ensure;
+
+            let dir = std::env::temp_dir().join("maa-test-condition-calendar");
+            dir.ensure_clean().unwrap();
+            let file = dir.join("calendar.toml");
+
+            let tz = TimeOffset::TimeZone(8);
+            let now = tz.naive_now();
+            let format = |time: NaiveDateTime| time.format("%Y-%m-%dT%H:%M:%S").to_string();
+            std::fs::write(
+                &file,
+                format!(
+                    "[[periods]]\nstart = \"{}\"\nend = \"{}\"\n",
+                    format(now - Duration::hours(1)),
+                    format(now + Duration::hours(1)),
+                ),
+            )
+            .unwrap();
+
+            assert!(
+                Condition::Calendar {
+                    file: file.clone(),
+                    timezone: tz,
+                }
+                .is_active(None)
+            );
+            // the extension can be omitted
+            assert!(
+                Condition::Calendar {
+                    file: dir.join("calendar"),
+                    timezone: tz,
+                }
+                .is_active(None)
+            );
+            assert!(
+                !Condition::Calendar {
+                    file: file.clone(),
+                    timezone: TimeOffset::TimeZone(-4),
+                }
+                .is_active(None)
+            );
+            assert!(
+                !Condition::Calendar {
+                    file: dir.join("not-exists.toml"),
+                    timezone: TimeOffset::Local,
+                }
+                .is_active(None)
+            );
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn file_exists() {
+            assert!(
+                Condition::FileExists {
+                    path: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"),
+                }
+                .is_active(None)
+            );
+            assert!(
+                !Condition::FileExists {
+                    path: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("not-exists"),
+                }
+                .is_active(None)
+            );
+        }
+
+        #[test]
+        fn env_var() {
+            // set by cargo when running tests
+            let name = "CARGO_PKG_NAME".to_owned();
+            assert!(
+                Condition::EnvVar {
+                    name: name.clone(),
+                    value: None,
+                }
+                .is_active(None)
+            );
+            assert!(
+                Condition::EnvVar {
+                    name: name.clone(),
+                    value: Some(env!("CARGO_PKG_NAME").to_owned()),
+                }
+                .is_active(None)
+            );
+            assert!(
+                !Condition::EnvVar {
+                    name,
+                    value: Some("other".to_owned()),
+                }
+                .is_active(None)
+            );
+            assert!(
+                !Condition::EnvVar {
+                    name: "MAA_TEST_NOT_EXISTS".to_owned(),
+                    value: None,
+                }
+                .is_active(None)
+            );
+        }
+
         #[test]
         fn boolean() {
             assert!(
                 Condition::And {
                     conditions: vec![Condition::Always, Condition::Always]
                 }
-                .is_active()
+                .is_active(None)
             );
             assert!(
                 !Condition::And {
@@ -568,7 +898,7 @@ mod tests {
                         condition: Box::new(Condition::Always)
                     },]
                 }
-                .is_active()
+                .is_active(None)
             );
 
             assert!(
@@ -577,7 +907,7 @@ mod tests {
                         condition: Box::new(Condition::Always)
                     }]
                 }
-                .is_active()
+                .is_active(None)
             );
 
             assert!(
@@ -591,16 +921,163 @@ mod tests {
                         }
                     ]
                 }
-                .is_active()
+                .is_active(None)
             );
 
             assert!(
                 !Condition::Not {
                     condition: Box::new(Condition::Always)
                 }
-                .is_active()
+                .is_active(None)
             );
         }
+
+        #[test]
+        fn last_run_older_than() {
+            use maa_types::TaskType;
+
+            use crate::history::{self, Record, Status, TaskRecord};
+
+            let tmp = tempfile::tempdir().unwrap();
+            crate::state::set_test_dir(Some(tmp.path().to_owned()));
+
+            let condition = |task: &str| Condition::LastRunOlderThan {
+                task: task.to_owned(),
+                duration: std::time::Duration::from_secs(24 * 3600),
+            };
+            let record = |end, profile: Option<&str>, name: &str| Record {
+                start: end,
+                end,
+                profile: profile.map(str::to_owned),
+                task: None,
+                job: None,
+                status: Status::Succeeded,
+                error: None,
+                tasks: vec![TaskRecord {
+                    id: 1,
+                    name: Some(name.to_owned()),
+                    task_type: TaskType::Fight,
+                    start_time: None,
+                    end_time: None,
+                    reason: "Completed".to_owned(),
+                    detail: serde_json::Value::Null,
+                }],
+            };
+
+            // never run
+            assert!(condition("Fight").is_active(None));
+
+            let now = chrono::Local::now();
+            history::append(&record(now - Duration::hours(1), None, "recent")).unwrap();
+            history::append(&record(now - Duration::days(2), None, "old")).unwrap();
+            history::append(&record(now - Duration::hours(1), Some("alt"), "old")).unwrap();
+
+            assert!(!condition("recent").is_active(None));
+            assert!(!condition("Fight").is_active(None));
+            assert!(condition("old").is_active(None));
+            // runs are checked in given profile
+            assert!(!condition("old").is_active(Some("alt")));
+            assert!(condition("recent").is_active(Some("alt")));
+        }
+
+        #[test]
+        fn depot_item_below() {
+            use crate::depot::Item;
+
+            let tmp = tempfile::tempdir().unwrap();
+            crate::state::set_test_dir(Some(tmp.path().to_owned()));
+
+            let condition = |item: &str| Condition::DepotItemBelow {
+                item: item.to_owned(),
+                count: 100,
+            };
+
+            // never recognized
+            assert!(!condition("固源岩").is_active(None));
+
+            Depot {
+                time: chrono::Local::now(),
+                items: vec![
+                    Item {
+                        id: "30012".to_owned(),
+                        name: "固源岩".to_owned(),
+                        count: 25,
+                    },
+                    Item {
+                        id: "30062".to_owned(),
+                        name: "装置".to_owned(),
+                        count: 200,
+                    },
+                ],
+            }
+            .save(None)
+            .unwrap();
+
+            assert!(condition("固源岩").is_active(None));
+            assert!(condition("30012").is_active(None));
+            assert!(!condition("装置").is_active(None));
+            // unknown items
+            assert!(!condition("糖").is_active(None));
+            // the depot is recognized in another profile
+            assert!(!condition("固源岩").is_active(Some("alt")));
+        }
+    }
+
+    #[test]
+    fn parse_time_offset() {
+        assert_eq!("8".parse(), Ok(TimeOffset::TimeZone(8)));
+        assert_eq!("-7".parse(), Ok(TimeOffset::TimeZone(-7)));
+        assert_eq!(
+            "Official".parse(),
+            Ok(TimeOffset::Client(ClientType::Official))
+        );
+        assert_eq!("local".parse(), Ok(TimeOffset::Local));
+        assert!("Mars".parse::<TimeOffset>().is_err());
+    }
+
+    #[test]
+    fn describe_next() {
+        let now = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        let at = |d: u32, h: u32| {
+            NaiveDate::from_ymd_opt(2024, 1, d)
+                .unwrap()
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+        };
+
+        let cron: CronExpr = "0 4,16 * * *".parse().unwrap();
+        assert_eq!(
+            describe_next_from(now, Some(&cron), None, 3),
+            "2024-01-01 16:00:00\n2024-01-02 04:00:00\n2024-01-02 16:00:00\n"
+        );
+
+        let calendar = calendar::Calendar {
+            periods: vec![
+                calendar::Period {
+                    name: Some("Event".to_owned()),
+                    start: at(5, 4),
+                    end: at(10, 4),
+                },
+                calendar::Period {
+                    name: None,
+                    start: at(1, 10),
+                    end: at(1, 16),
+                },
+                calendar::Period {
+                    name: Some("Past".to_owned()),
+                    start: at(1, 0),
+                    end: at(1, 4),
+                },
+            ],
+        };
+        assert_eq!(
+            describe_next_from(now, None, Some(&calendar), 5),
+            "2024-01-01 10:00:00 - 2024-01-01 16:00:00 (active)\n\
+             2024-01-05 04:00:00 - 2024-01-10 04:00:00 Event\n"
+        );
     }
 
     mod serde {
@@ -808,6 +1285,107 @@ mod tests {
             );
         }
 
+        #[test]
+        fn cron_and_calendar() {
+            assert_de_tokens(
+                &Condition::Cron {
+                    expr: "* 4-11 * * mon-fri".parse().unwrap(),
+                    timezone: TimeOffset::Client(ClientType::Official),
+                },
+                &[
+                    Token::Map { len: Some(3) },
+                    Token::Str("type"),
+                    Token::Str("Cron"),
+                    Token::Str("expr"),
+                    Token::Str("* 4-11 * * mon-fri"),
+                    Token::Str("timezone"),
+                    Token::Str("Official"),
+                    Token::MapEnd,
+                ],
+            );
+
+            assert_de_tokens(
+                &Condition::Calendar {
+                    file: PathBuf::from("holidays.ics"),
+                    timezone: TimeOffset::Local,
+                },
+                &[
+                    Token::Map { len: Some(2) },
+                    Token::Str("type"),
+                    Token::Str("Calendar"),
+                    Token::Str("file"),
+                    Token::Str("holidays.ics"),
+                    Token::MapEnd,
+                ],
+            );
+        }
+
+        #[test]
+        fn state() {
+            assert_de_tokens(
+                &Condition::LastRunOlderThan {
+                    task: "Annihilation".to_owned(),
+                    duration: std::time::Duration::from_secs(7 * 24 * 60 * 60),
+                },
+                &[
+                    Token::Map { len: Some(3) },
+                    Token::Str("type"),
+                    Token::Str("LastRunOlderThan"),
+                    Token::Str("task"),
+                    Token::Str("Annihilation"),
+                    Token::Str("duration"),
+                    Token::Str("7d"),
+                    Token::MapEnd,
+                ],
+            );
+
+            assert_de_tokens(
+                &Condition::FileExists {
+                    path: PathBuf::from("flags/fight"),
+                },
+                &[
+                    Token::Map { len: Some(2) },
+                    Token::Str("type"),
+                    Token::Str("FileExists"),
+                    Token::Str("path"),
+                    Token::Str("flags/fight"),
+                    Token::MapEnd,
+                ],
+            );
+
+            assert_de_tokens(
+                &Condition::EnvVar {
+                    name: "MAA_FIGHT".to_owned(),
+                    value: None,
+                },
+                &[
+                    Token::Map { len: Some(2) },
+                    Token::Str("type"),
+                    Token::Str("EnvVar"),
+                    Token::Str("name"),
+                    Token::Str("MAA_FIGHT"),
+                    Token::MapEnd,
+                ],
+            );
+
+            assert_de_tokens(
+                &Condition::DepotItemBelow {
+                    item: "固源岩".to_owned(),
+                    count: 100,
+                },
+                &[
+                    Token::Map { len: Some(3) },
+                    Token::Str("type"),
+                    Token::Str("DepotItemBelow"),
+                    Token::Str("item"),
+                    Token::Str("固源岩"),
+                    Token::Str("count"),
+                    Token::I64(100),
+                    Token::MapEnd,
+                ],
+            );
+        }
+
         #[test]
         fn boolean() {
             assert_de_tokens(
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// A task which is run only after another named task finished with expected result
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, PartialEq)]
+pub struct Dependency {
+    /// Name of the predecessor task
+    pub after: String,
+    pub when: WhenResult,
+}
+
+/// Final status of a task after all retries
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultStatus {
+    #[default]
+    Succeeded,
+    Failed,
+    /// The task finished, no matter it succeeded or failed
+    Any,
+}
+
+/// Expected result of the predecessor task
+///
+/// It can be given as a status only, e.g. `"failed"`,
+/// or a table with the status and expected values in the detail of the summary,
+/// e.g. `{ status = "succeeded", detail = { medicine = { min = 1 } } }`.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, Clone, PartialEq, Default)]
+#[serde(from = "WhenResultRepr")]
+pub struct WhenResult {
+    pub status: ResultStatus,
+    /// Expected values in the detail, keyed by dot separated path, e.g. `total_drops.龙门币`
+    pub detail: BTreeMap<String, DetailMatch>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WhenResultRepr {
+    Status(ResultStatus),
+    Full {
+        #[serde(default)]
+        status: ResultStatus,
+        #[serde(default)]
+        detail: BTreeMap<String, DetailMatch>,
+    },
+}
+
+impl From<WhenResultRepr> for WhenResult {
+    fn from(repr: WhenResultRepr) -> Self {
+        match repr {
+            WhenResultRepr::Status(status) => Self {
+                status,
+                detail: BTreeMap::new(),
+            },
+            WhenResultRepr::Full { status, detail } => Self { status, detail },
+        }
+    }
+}
+
+impl WhenResult {
+    /// Check whether the result of the predecessor is expected
+    ///
+    /// `detail` is the serialized detail of the predecessor in the summary.
+    pub fn matches(&self, succeeded: bool, detail: &Value) -> bool {
+        let status = match self.status {
+            ResultStatus::Succeeded => succeeded,
+            ResultStatus::Failed => !succeeded,
+            ResultStatus::Any => true,
+        };
+
+        status
+            && self
+                .detail
+                .iter()
+                .all(|(path, expected)| lookup(detail, path).is_some_and(|v| expected.matches(v)))
+    }
+}
+
+/// Get a value in the detail by a dot separated path, array elements are indexed by numbers
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// Expected value in the detail, a missing value never matches
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DetailMatch {
+    Range(Range),
+    /// A value equal to the given one
+    Equal(Value),
+}
+
+/// A number in the given range, both bounds are inclusive
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Range {
+    #[serde(default)]
+    min: Option<f64>,
+    #[serde(default)]
+    max: Option<f64>,
+}
+
+impl DetailMatch {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::Range(Range { min, max }) => value
+                .as_f64()
+                .is_some_and(|v| min.is_none_or(|min| v >= min) && max.is_none_or(|max| v <= max)),
+            Self::Equal(expected) => match (expected.as_f64(), value.as_f64()) {
+                // compare numbers by value, so that `1` matches `1.0`
+                (Some(expected), Some(value)) => expected == value,
+                _ => expected == value,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            when_result: WhenResult,
+        }
+
+        let parse = |s: &str| toml::from_str::<Wrapper>(s).unwrap().when_result;
+
+        assert_eq!(parse(r#"when_result = "failed""#), WhenResult {
+            status: ResultStatus::Failed,
+            detail: BTreeMap::new(),
+        });
+        assert_eq!(
+            parse(r#"when_result = { detail = { medicine = { min = 1 }, stage = "1-7" } }"#),
+            WhenResult {
+                status: ResultStatus::Succeeded,
+                detail: [
+                    (
+                        "medicine".to_owned(),
+                        DetailMatch::Range(Range {
+                            min: Some(1.0),
+                            max: None
+                        })
+                    ),
+                    ("stage".to_owned(), DetailMatch::Equal(json!("1-7"))),
+                ]
+                .into(),
+            }
+        );
+        assert_eq!(
+            parse(r#"when_result = { status = "any", detail = { times = 0 } }"#),
+            WhenResult {
+                status: ResultStatus::Any,
+                detail: [("times".to_owned(), DetailMatch::Equal(json!(0)))].into(),
+            }
+        );
+
+        // tables with unknown keys are compared as a whole
+        assert_eq!(
+            parse(r#"when_result = { detail = { total_drops = { "固源岩" = 2 } } }"#).detail["total_drops"],
+            DetailMatch::Equal(json!({ "固源岩": 2 }))
+        );
+
+        assert!(toml::from_str::<Wrapper>(r#"when_result = "unknown""#).is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let detail = json!({
+            "stage": "1-7",
+            "times": 0,
+            "medicine": 2,
+            "drops": [{ "固源岩": 2 }],
+            "total_drops": { "固源岩": 2 },
+        });
+        let when = |status, detail: &[(&str, DetailMatch)]| WhenResult {
+            status,
+            detail: detail
+                .iter()
+                .map(|(k, v)| ((*k).to_owned(), v.clone()))
+                .collect(),
+        };
+        let range = |min, max| DetailMatch::Range(Range { min, max });
+
+        assert!(when(ResultStatus::Succeeded, &[]).matches(true, &detail));
+        assert!(!when(ResultStatus::Succeeded, &[]).matches(false, &detail));
+        assert!(when(ResultStatus::Failed, &[]).matches(false, &detail));
+        assert!(!when(ResultStatus::Failed, &[]).matches(true, &detail));
+        assert!(when(ResultStatus::Any, &[]).matches(false, &detail));
+
+        let used_medicine = when(ResultStatus::Succeeded, &[(
+            "medicine",
+            range(Some(1.0), None),
+        )]);
+        assert!(used_medicine.matches(true, &detail));
+        assert!(!used_medicine.matches(true, &json!({ "medicine": 0 })));
+        assert!(!used_medicine.matches(true, &json!(null)));
+
+        assert!(
+            when(ResultStatus::Any, &[
+                ("times", DetailMatch::Equal(json!(0.0))),
+                ("stage", DetailMatch::Equal(json!("1-7"))),
+                ("drops.0.固源岩", range(None, Some(2.0))),
+                ("total_drops.固源岩", range(Some(2.0), Some(2.0))),
+            ])
+            .matches(true, &detail)
+        );
+        assert!(
+            !when(ResultStatus::Any, &[(
+                "total_drops.糖",
+                range(None, Some(0.0))
+            )])
+            .matches(true, &detail)
+        );
+        assert!(
+            !when(ResultStatus::Any, &[(
+                "drops.x",
+                DetailMatch::Equal(json!(1))
+            )])
+            .matches(true, &detail)
+        );
+    }
+}
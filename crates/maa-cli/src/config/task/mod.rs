@@ -1,16 +1,23 @@
 mod client_type;
 pub use client_type::ClientType;
 
+mod calendar;
+
 mod condition;
-use std::path::PathBuf;
+mod dependency;
+mod stage;
+use std::{path::PathBuf, time::Duration};
 
-use anyhow::{Context, Result};
-use condition::Condition;
-pub use condition::{TimeOffset, remainder_of_day_mod};
+use anyhow::{Context, Result, bail};
+pub use condition::{Condition, TimeOffset, describe_next, remainder_of_day_mod};
+pub use dependency::{Dependency, WhenResult};
 use maa_types::TaskType;
 use maa_value::prelude::*;
 use serde::Deserialize;
+pub use stage::AUTO_STAGE;
+use stage::resolve_auto_stage;
 
+use super::duration;
 use crate::dirs;
 
 #[cfg_attr(test, derive(PartialEq, Debug))]
@@ -24,8 +31,8 @@ pub struct TaskVariant {
 }
 
 impl TaskVariant {
-    pub fn is_active(&self) -> bool {
-        self.condition.is_active()
+    pub fn is_active(&self, profile: Option<&str>) -> bool {
+        self.condition.is_active(profile)
     }
 
     pub fn params(&self) -> &MAAValueTemplate {
@@ -48,6 +55,31 @@ pub enum Strategy {
     Merge,
 }
 
+/// What to do with remaining tasks when a task failed after all retries
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OnFailure {
+    /// Run remaining tasks as usual
+    #[default]
+    Continue,
+    /// Stop immediately, the external app is not closed
+    Abort,
+    /// Skip remaining tasks but finish the run as usual, e.g. close the external app
+    SkipRest,
+}
+
+/// How a task is retried and what to do if it failed
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Policy {
+    /// Times to retry a failed task
+    pub retry: u32,
+    /// Max time of each attempt, the task is stopped and considered as failed if exceeded
+    pub timeout: Option<Duration>,
+    pub on_failure: OnFailure,
+}
+
 #[cfg_attr(test, derive(PartialEq, Debug))]
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -62,20 +94,45 @@ pub struct TaskTemplate {
     strategy: Strategy,
     #[serde(default)]
     variants: Vec<TaskVariant>,
+    #[serde(default)]
+    retry: u32,
+    #[serde(default, deserialize_with = "duration::deserialize_opt")]
+    timeout: Option<Duration>,
+    #[serde(default)]
+    on_failure: OnFailure,
+    /// Name of the task which must be finished before this task
+    #[serde(default)]
+    after: Option<String>,
+    #[serde(default)]
+    when_result: Option<WhenResult>,
 }
 
-// Constructor for Task
-#[cfg(test)]
 impl TaskTemplate {
-    fn new(task_type: TaskType, params: MAAValueTemplate) -> Self {
+    pub fn new(task_type: TaskType, params: MAAValueTemplate) -> Self {
         Self {
             name: None,
             task_type,
             strategy: Strategy::default(),
             params,
             variants: Vec::new(),
+            retry: 0,
+            timeout: None,
+            on_failure: OnFailure::default(),
+            after: None,
+            when_result: None,
         }
     }
+}
+
+// Builders for Task
+#[cfg(test)]
+impl TaskTemplate {
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.retry = policy.retry;
+        self.timeout = policy.timeout;
+        self.on_failure = policy.on_failure;
+        self
+    }
 
     pub fn with_name(mut self, name: String) -> Self {
         self.name = Some(name);
@@ -103,22 +160,37 @@ impl TaskTemplate {
         self.task_type
     }
 
-    pub fn is_active(&self) -> bool {
+    pub fn is_active(&self, profile: Option<&str>) -> bool {
         if self.variants.is_empty() {
             return true;
         }
         for variant in self.variants.iter() {
-            if variant.is_active() {
+            if variant.is_active(profile) {
                 return true;
             }
         }
         false
     }
 
-    pub fn params(&self) -> MAAValueTemplate {
+    pub fn policy(&self) -> Policy {
+        Policy {
+            retry: self.retry,
+            timeout: self.timeout,
+            on_failure: self.on_failure,
+        }
+    }
+
+    pub fn dependency(&self) -> Option<Dependency> {
+        self.after.as_ref().map(|after| Dependency {
+            after: after.clone(),
+            when: self.when_result.clone().unwrap_or_default(),
+        })
+    }
+
+    pub fn params(&self, profile: Option<&str>) -> MAAValueTemplate {
         let mut params = self.params.clone();
         for variant in &self.variants {
-            if variant.is_active() {
+            if variant.is_active(profile) {
                 params.merge_from(variant.params());
                 if matches!(self.strategy, Strategy::First) {
                     break;
@@ -138,7 +210,61 @@ pub struct TaskConfigTemplate {
 }
 
 impl TaskConfigTemplate {
-    pub fn init(&self) -> anyhow::Result<TaskConfig> {
+    /// Build a template from tasks, startup and closedown tasks are inserted by [`Self::init`]
+    pub fn new(
+        client_type: Option<ClientType>,
+        startup: bool,
+        closedown: bool,
+        tasks: Vec<TaskTemplate>,
+    ) -> Self {
+        Self {
+            client_type,
+            startup: Some(startup),
+            closedown: Some(closedown),
+            tasks,
+        }
+    }
+
+    /// Check that dependencies of tasks refer to other named tasks without cycles
+    fn validate_dependencies(&self) -> Result<()> {
+        let after_of = |name: &str| {
+            self.tasks
+                .iter()
+                .find(|task| task.name.as_deref() == Some(name))
+                .map(|task| task.after.as_deref())
+        };
+
+        for task in &self.tasks {
+            let name = task.name.as_deref().unwrap_or(task.task_type.to_str());
+            let Some(after) = task.after.as_deref() else {
+                if task.when_result.is_some() {
+                    bail!("Task {name} has `when_result` but no `after`!");
+                }
+                continue;
+            };
+            let Some(mut next) = after_of(after) else {
+                bail!("Task {name} runs after unknown task {after}!");
+            };
+
+            // Each task has at most one predecessor, so there is a cycle
+            // if the chain of predecessors is longer than the number of tasks
+            let mut length = 1;
+            while let Some(after) = next {
+                length += 1;
+                if length > self.tasks.len() {
+                    bail!("Task {name} has cyclic dependencies!");
+                }
+                next = after_of(after).flatten();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the task config from active tasks, conditions are checked in given profile
+    pub fn init(&self, profile: Option<&str>) -> anyhow::Result<TaskConfig> {
+        self.validate_dependencies()?;
+
         let mut startup = self.startup;
         let mut closedown = self.closedown;
         let mut client_type = self.client_type;
@@ -150,12 +276,12 @@ impl TaskConfigTemplate {
         use TaskType::*;
 
         for task in self.tasks.iter() {
-            if !task.is_active() {
+            if !task.is_active(profile) {
                 continue;
             }
 
             let task_type = task.task_type();
-            let mut params = task.params().resolve()?;
+            let mut params = task.params(profile).resolve()?;
 
             // If startup task is not enabled, enable it automatically
             match task_type {
@@ -217,7 +343,8 @@ impl TaskConfigTemplate {
                 _ => {}
             }
 
-            let mut inited_task = Task::new(task_type, params);
+            let mut inited_task = Task::new(task_type, params).with_policy(task.policy());
+            inited_task.dependency = task.dependency();
 
             if let Some(name) = &task.name {
                 inited_task = inited_task.with_name(name.to_owned());
@@ -237,6 +364,10 @@ impl TaskConfigTemplate {
             if matches!(task_type, StartUp | Fight | CloseDown) {
                 insert!(*params, "client_type" => client_type.to_str());
             }
+
+            if task_type == Fight {
+                task.note = resolve_auto_stage(params, client_type)?;
+            }
         }
 
         if prepend_startup {
@@ -286,6 +417,9 @@ impl TaskConfig {
             .transpose()?
             .unwrap_or(ClientType::Official);
         normalize_task_params(task.task_type, &mut task.params)?;
+        if task.task_type == TaskType::Fight {
+            task.note = resolve_auto_stage(&mut task.params, client_type)?;
+        }
 
         match task.task_type {
             TaskType::StartUp => Ok(Self {
@@ -337,6 +471,11 @@ pub struct Task {
     pub name: Option<String>,
     pub task_type: TaskType,
     pub params: MAAValue,
+    pub policy: Policy,
+    /// Run the task only if the predecessor finished with expected result
+    pub dependency: Option<Dependency>,
+    /// How params are chosen automatically, e.g. the stage of a Fight task, shown in the summary
+    pub note: Option<String>,
 }
 
 impl Task {
@@ -345,6 +484,13 @@ impl Task {
             name: None,
             task_type,
             params,
+            policy: Policy {
+                retry: 0,
+                timeout: None,
+                on_failure: OnFailure::Continue,
+            },
+            dependency: None,
+            note: None,
         }
     }
 
@@ -353,6 +499,11 @@ impl Task {
         self
     }
 
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     pub fn name_or_default(&self) -> &str {
         self.name
             .as_deref()
@@ -374,7 +525,7 @@ mod tests {
                 assert_eq!(
                     TaskTemplate::new(TaskType::StartUp, template!())
                         .with_variants(variants)
-                        .is_active(),
+                        .is_active(None),
                     expected
                 );
             }
@@ -426,7 +577,7 @@ mod tests {
                     });
                 }
 
-                assert_eq!(task.params(), expected);
+                assert_eq!(task.params(None), expected);
             }
 
             test_with_variants(
@@ -507,7 +658,7 @@ mod tests {
                         condition: Condition::Always,
                         params: template!("a" => 3, "b" => 4),
                     });
-                    task.params()
+                    task.params(None)
                 },
                 template!("a" => 3, "b" => 4, "c" => 5),
             );
@@ -636,7 +787,12 @@ mod tests {
                             timezone: TimeOffset::Local,
                         },
                         params: template!(),
-                    }]),
+                    }])
+                    .with_policy(Policy {
+                        retry: 2,
+                        timeout: Some(std::time::Duration::from_secs(600)),
+                        on_failure: OnFailure::Continue,
+                    }),
                 );
 
                 task_list.push(TaskTemplate::new(CloseDown, template!()));
@@ -689,7 +845,7 @@ mod tests {
                     closedown: None,
                     tasks: vec![],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: Official,
@@ -715,7 +871,7 @@ mod tests {
                             }]),
                     ],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: Official,
@@ -741,7 +897,7 @@ mod tests {
                         .with_name(String::from("StartUp"))
                     ],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: YoStarEN,
@@ -775,7 +931,7 @@ mod tests {
                         )
                     )],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: YoStarEN,
@@ -804,7 +960,7 @@ mod tests {
                         template!("client_type" => "YoStarEN")
                     )],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: YoStarEN,
@@ -830,7 +986,7 @@ mod tests {
                         )
                     )],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: YoStarEN,
@@ -855,7 +1011,7 @@ mod tests {
                     closedown: None,
                     tasks: vec![TaskTemplate::new(CloseDown, template!())],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: Official,
@@ -878,7 +1034,7 @@ mod tests {
                         template!("client_type" => "YoStarEN")
                     )],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: YoStarEN,
@@ -908,7 +1064,7 @@ mod tests {
                         TaskTemplate::new(CloseDown, template!()),
                     ],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: Official,
@@ -952,7 +1108,7 @@ mod tests {
                         TaskTemplate::new(CloseDown, template!("enable" => false)),
                     ],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: Official,
@@ -998,7 +1154,7 @@ mod tests {
                     closedown: Some(true),
                     tasks: vec![TaskTemplate::new(Fight, template!("stage" => "1-7"))],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: Official,
@@ -1038,7 +1194,7 @@ mod tests {
                     closedown: Some(true),
                     tasks: vec![TaskTemplate::new(Fight, template!("stage" => "1-7"))],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: YoStarEN,
@@ -1082,7 +1238,7 @@ mod tests {
                         TaskTemplate::new(CloseDown, template!("client_type" => "YoStarJP")),
                     ],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: Official,
@@ -1113,7 +1269,7 @@ mod tests {
                         TaskTemplate::new(Infrast, template!("filename" => "/tmp/daily.json")),
                     ],
                 }
-                .init()
+                .init(None)
                 .unwrap(),
                 TaskConfig {
                     client_type: Official,
@@ -1130,6 +1286,113 @@ mod tests {
             );
         }
 
+        #[test]
+        fn policy() {
+            let task_config: TaskConfigTemplate = toml::from_str(
+                r#"
+                [[tasks]]
+                type = "Recruit"
+                retry = 2
+                timeout = "30m"
+                on_failure = "skip_rest"
+
+                [[tasks]]
+                type = "Fight"
+                "#,
+            )
+            .unwrap();
+            let task_config = task_config.init(None).unwrap();
+
+            assert_eq!(task_config.tasks[0].policy, Policy {
+                retry: 2,
+                timeout: Some(std::time::Duration::from_secs(30 * 60)),
+                on_failure: OnFailure::SkipRest,
+            });
+            assert_eq!(task_config.tasks[1].policy, Policy::default());
+
+            assert!(
+                toml::from_str::<TaskConfigTemplate>(
+                    "[[tasks]]\ntype = \"Fight\"\non_failure = \"retry\""
+                )
+                .is_err()
+            );
+        }
+
+        #[test]
+        fn dependency() {
+            let task_config: TaskConfigTemplate = toml::from_str(
+                r#"
+                [[tasks]]
+                name = "Fight"
+                type = "Fight"
+
+                [[tasks]]
+                type = "Fight"
+                after = "Fight"
+                when_result = "failed"
+
+                [[tasks]]
+                type = "Mall"
+                after = "Fight"
+                "#,
+            )
+            .unwrap();
+            let task_config = task_config.init(None).unwrap();
+
+            assert_eq!(task_config.tasks[0].dependency, None);
+            assert_eq!(
+                task_config.tasks[1].dependency,
+                Some(Dependency {
+                    after: "Fight".to_owned(),
+                    when: serde_json::from_value(serde_json::json!("failed")).unwrap(),
+                })
+            );
+            assert_eq!(
+                task_config.tasks[2].dependency,
+                Some(Dependency {
+                    after: "Fight".to_owned(),
+                    when: WhenResult::default(),
+                })
+            );
+
+            let init_err = |tasks: &str| {
+                toml::from_str::<TaskConfigTemplate>(tasks)
+                    .unwrap()
+                    .init(None)
+                    .unwrap_err()
+                    .to_string()
+            };
+
+            assert_eq!(
+                init_err("[[tasks]]\ntype = \"Mall\"\nafter = \"Fight\""),
+                "Task Mall runs after unknown task Fight!"
+            );
+            assert_eq!(
+                init_err("[[tasks]]\ntype = \"Mall\"\nwhen_result = \"failed\""),
+                "Task Mall has `when_result` but no `after`!"
+            );
+            assert_eq!(
+                init_err(
+                    r#"
+                    [[tasks]]
+                    name = "A"
+                    type = "Fight"
+                    after = "B"
+
+                    [[tasks]]
+                    name = "B"
+                    type = "Mall"
+                    after = "A"
+                    "#
+                ),
+                "Task A has cyclic dependencies!"
+            );
+            assert_eq!(
+                init_err("[[tasks]]\nname = \"A\"\ntype = \"Mall\"\nafter = \"A\""),
+                "Task A has cyclic dependencies!"
+            );
+        }
+
         #[test]
         fn new_task() {
             let task =
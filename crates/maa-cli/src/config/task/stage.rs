@@ -0,0 +1,379 @@
+//! Automatic stage selection of Fight tasks with `stage = "auto"`
+//!
+//! Candidates are given by the `stages` param in priority order, and stages not open now are
+//! skipped. If `target_item` is given, the most efficient open stage to farm the item in the
+//! drop table `$MAA_CONFIG_DIR/drops.{toml,json,yaml}` is preferred.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Datelike, Utc, Weekday};
+use log::{info, warn};
+use maa_value::prelude::*;
+use serde::Deserialize;
+
+use super::{ClientType, TimeOffset};
+use crate::{
+    activity::{StageActivityContent, stage_activity},
+    config::FindFile,
+    dirs,
+};
+
+/// Value of the `stage` param to select the stage automatically
+pub const AUTO_STAGE: &str = "auto";
+
+/// Stages and their drop rates, used to find the best stage for an item
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+pub struct DropTable {
+    stages: Vec<StageDrops>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize)]
+struct StageDrops {
+    stage: String,
+    /// Sanity cost of the stage
+    sanity: u32,
+    /// Expected count of each item (by name or id) per run
+    drops: BTreeMap<String, f32>,
+}
+
+impl DropTable {
+    fn load() -> Result<Option<Self>> {
+        let path = dirs::config().join("drops");
+        Self::find_file_or_none(&path)
+            .with_context(|| format!("Failed to load drop table {}", path.display()))
+    }
+
+    /// The open stage with the most drops of given item per sanity
+    fn best(&self, item: &str, is_open: impl Fn(&str) -> bool) -> Option<(&str, f32)> {
+        self.stages
+            .iter()
+            .filter(|stage| stage.sanity > 0)
+            .filter_map(|stage| {
+                let rate = *stage.drops.get(item)?;
+                Some((stage.stage.as_str(), rate / stage.sanity as f32))
+            })
+            .filter(|(stage, _)| is_open(stage))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
+
+/// Weekdays in server time when a resource stage is open, `None` if the stage is always open
+fn open_weekdays(stage: &str) -> Option<&'static [Weekday]> {
+    use Weekday::*;
+
+    let stage = stage.to_ascii_uppercase();
+    let (zone, rest) = stage.split_once('-')?;
+    Some(match zone {
+        // LMD
+        "CE" => &[Tue, Thu, Sat, Sun],
+        // Purchase Certificates
+        "AP" => &[Mon, Thu, Sat, Sun],
+        // Skill Summaries
+        "CA" => &[Tue, Wed, Fri, Sun],
+        // Carbon
+        "SK" => &[Mon, Wed, Fri, Sat],
+        // Chips
+        "PR" => match rest.split_once('-')?.0 {
+            "A" => &[Mon, Thu, Fri, Sun],
+            "B" => &[Mon, Tue, Fri, Sat],
+            "C" => &[Wed, Thu, Sat, Sun],
+            "D" => &[Tue, Wed, Sat, Sun],
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+/// Check whether given stage is open at given time, with the reason if not
+///
+/// Stages of side stories in the activity file are open only during the side story,
+/// resource stages are open on their weekdays or during resource collection events,
+/// and other stages are always considered open.
+fn check_open(
+    stage: &str,
+    client: ClientType,
+    activity: Option<&StageActivityContent>,
+    now: DateTime<Utc>,
+) -> std::result::Result<(), String> {
+    if let Some(activity) = activity {
+        match activity.side_story_stage_open_at(stage, now) {
+            Some(true) => return Ok(()),
+            Some(false) => return Err("side story not open".to_owned()),
+            None => {}
+        }
+        if activity.resource_collection_open_at(now) {
+            return Ok(());
+        }
+    }
+
+    if let Some(weekdays) = open_weekdays(stage) {
+        let weekday = TimeOffset::Client(client).date_time(now).weekday();
+        if !weekdays.contains(&weekday) {
+            return Err(format!("not open on {weekday}"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+struct Selection {
+    stage: String,
+    reason: String,
+}
+
+fn select(
+    candidates: &[String],
+    target: Option<(&str, Option<&DropTable>)>,
+    check_open: impl Fn(&str) -> std::result::Result<(), String>,
+) -> Result<Selection> {
+    if let Some((item, table)) = target {
+        match table.and_then(|table| table.best(item, |stage| check_open(stage).is_ok())) {
+            Some((stage, efficiency)) => {
+                return Ok(Selection {
+                    stage: stage.to_owned(),
+                    reason: format!("best open stage for {item}, {efficiency:.3} per sanity"),
+                });
+            }
+            None if candidates.is_empty() => {
+                bail!("No open stage drops {item} in the drop table");
+            }
+            None => warn!("No open stage drops {item} in the drop table, use given stages"),
+        }
+    }
+
+    if candidates.is_empty() {
+        bail!("No stages given to select automatically");
+    }
+
+    let mut skipped = Vec::new();
+    for stage in candidates {
+        match check_open(stage) {
+            Ok(()) if skipped.is_empty() => {
+                return Ok(Selection {
+                    stage: stage.clone(),
+                    reason: "first given stage, open now".to_owned(),
+                });
+            }
+            Ok(()) => {
+                return Ok(Selection {
+                    stage: stage.clone(),
+                    reason: format!("skipped {}", skipped.join(", ")),
+                });
+            }
+            Err(why) => skipped.push(format!("{stage} ({why})")),
+        }
+    }
+
+    bail!("None of given stages is open: {}", skipped.join(", "))
+}
+
+/// Select the stage of a Fight task if its stage is `auto`, returning the reason of selection
+///
+/// The `stages` and `target_item` params are only used by maa-cli, and removed from the params.
+pub fn resolve_auto_stage(params: &mut MAAValue, client: ClientType) -> Result<Option<String>> {
+    if params.get_typed::<&str>("stage") != Some(AUTO_STAGE) {
+        return Ok(None);
+    }
+
+    let map = params.as_mut_map().context("params must be an object")?;
+    let candidates = match map.shift_remove("stages") {
+        Some(MAAValue::Array(stages)) => stages
+            .iter()
+            .map(|stage| stage.as_str().map(ToOwned::to_owned))
+            .collect::<Option<Vec<_>>>()
+            .context("stages must be an array of strings")?,
+        Some(_) => bail!("stages must be an array of strings"),
+        None => Vec::new(),
+    };
+    let target = match map.shift_remove("target_item") {
+        Some(item) => Some(
+            item.as_str()
+                .context("target_item must be a string")?
+                .to_owned(),
+        ),
+        None => None,
+    };
+    let table = match target {
+        Some(_) => DropTable::load()?,
+        None => None,
+    };
+
+    let activity = stage_activity(client);
+    let now = Utc::now();
+    let selection = select(
+        &candidates,
+        target.as_deref().map(|item| (item, table.as_ref())),
+        |stage| check_open(stage, client, activity, now),
+    )?;
+    info!("Select stage {}: {}", selection.stage, selection.reason);
+
+    params.insert("stage", selection.stage.into());
+    Ok(Some(selection.reason))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+
+    use super::*;
+
+    fn stages(stages: &[&str]) -> Vec<String> {
+        stages.iter().map(|&s| s.to_owned()).collect()
+    }
+
+    fn table() -> DropTable {
+        serde_json::from_str(
+            r#"{
+                "stages": [
+                    { "stage": "1-7", "sanity": 6, "drops": { "固源岩": 1.2 } },
+                    { "stage": "S4-1", "sanity": 12, "drops": { "固源岩": 1.8, "酮凝集": 0.5 } },
+                    { "stage": "SK-5", "sanity": 30, "drops": { "固源岩": 9.0 } }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn weekdays() {
+        use Weekday::*;
+
+        assert_eq!(open_weekdays("CE-6"), Some(&[Tue, Thu, Sat, Sun][..]));
+        assert_eq!(open_weekdays("ce-6"), Some(&[Tue, Thu, Sat, Sun][..]));
+        assert_eq!(open_weekdays("PR-D-2"), Some(&[Tue, Wed, Sat, Sun][..]));
+        assert_eq!(open_weekdays("LS-6"), None);
+        assert_eq!(open_weekdays("1-7"), None);
+        assert_eq!(open_weekdays("Annihilation"), None);
+        assert_eq!(open_weekdays("PR-X-1"), None);
+    }
+
+    #[test]
+    fn open() {
+        let activity: BTreeMap<String, StageActivityContent> =
+            serde_json::from_str(include_str!("../../../fixtures/activity.json")).unwrap();
+        let activity = &activity["Official"];
+        let official = ClientType::Official;
+        // Monday 2025-12-22 12:00 in UTC+8, during SSReopen but not UR
+        let monday = chrono::FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .from_local_datetime(
+                &NaiveDate::from_ymd_opt(2025, 12, 22)
+                    .unwrap()
+                    .and_hms_opt(12, 0, 0)
+                    .unwrap(),
+            )
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(check_open("1-7", official, None, monday), Ok(()));
+        assert_eq!(check_open("AP-5", official, None, monday), Ok(()));
+        assert_eq!(
+            check_open("CE-6", official, None, monday),
+            Err("not open on Mon".to_owned())
+        );
+        assert_eq!(check_open("EP-8", official, Some(activity), monday), Ok(()));
+        assert_eq!(
+            check_open("UR-8", official, Some(activity), monday),
+            Err("side story not open".to_owned())
+        );
+        // side story stages are only known with the activity file
+        assert_eq!(check_open("UR-8", official, None, monday), Ok(()));
+        // Monday 02:00 in UTC+8 is still Sunday in server time
+        let early = monday - chrono::Duration::hours(10);
+        assert_eq!(check_open("CE-6", official, None, early), Ok(()));
+    }
+
+    #[test]
+    fn select_stage() {
+        let closed = |stage: &str| match stage {
+            "CE-6" | "SK-5" => Err("not open on Mon".to_owned()),
+            "UR-8" => Err("side story not open".to_owned()),
+            _ => Ok(()),
+        };
+
+        assert_eq!(
+            select(&stages(&["1-7", "CE-6"]), None, closed).unwrap(),
+            Selection {
+                stage: "1-7".to_owned(),
+                reason: "first given stage, open now".to_owned(),
+            }
+        );
+        assert_eq!(
+            select(&stages(&["UR-8", "CE-6", "1-7"]), None, closed).unwrap(),
+            Selection {
+                stage: "1-7".to_owned(),
+                reason: "skipped UR-8 (side story not open), CE-6 (not open on Mon)".to_owned(),
+            }
+        );
+        assert!(select(&stages(&["UR-8", "CE-6"]), None, closed).is_err());
+        assert!(select(&[], None, closed).is_err());
+
+        let table = table();
+        // 1-7: 0.2 per sanity, S4-1: 0.15 per sanity, SK-5: 0.3 per sanity but closed
+        assert_eq!(
+            select(&[], Some(("固源岩", Some(&table))), closed)
+                .unwrap()
+                .stage,
+            "1-7"
+        );
+        assert_eq!(
+            select(&[], Some(("固源岩", Some(&table))), |_| Ok(()))
+                .unwrap()
+                .stage,
+            "SK-5"
+        );
+        assert_eq!(
+            select(&[], Some(("酮凝集", Some(&table))), closed)
+                .unwrap()
+                .stage,
+            "S4-1"
+        );
+        // fallback to given stages if no stage in the table drops the item
+        assert_eq!(
+            select(
+                &stages(&["CE-6", "1-7"]),
+                Some(("糖", Some(&table))),
+                closed
+            )
+            .unwrap()
+            .stage,
+            "1-7"
+        );
+        assert_eq!(
+            select(&stages(&["1-7"]), Some(("固源岩", None)), closed)
+                .unwrap()
+                .stage,
+            "1-7"
+        );
+        assert!(select(&[], Some(("糖", Some(&table))), closed).is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let client = ClientType::Official;
+
+        let mut params = object!("stage" => "1-7");
+        assert_eq!(resolve_auto_stage(&mut params, client).unwrap(), None);
+        assert_eq!(params, object!("stage" => "1-7"));
+
+        let mut params = object!(
+            "stage" => "auto",
+            "stages" => ["LS-6", "1-7"],
+            "medicine" => 1,
+        );
+        assert!(resolve_auto_stage(&mut params, client).unwrap().is_some());
+        assert_eq!(params, object!("stage" => "LS-6", "medicine" => 1));
+
+        let mut params = object!("stage" => "auto", "stages" => "1-7");
+        assert!(resolve_auto_stage(&mut params, client).is_err());
+        let mut params = object!("stage" => "auto", "stages" => [1]);
+        assert!(resolve_auto_stage(&mut params, client).is_err());
+        let mut params = object!("stage" => "auto");
+        assert!(resolve_auto_stage(&mut params, client).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Items in the depot recognized by the last Depot task of each profile, saved to be used in task
+//! conditions
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use maa_dirs::Ensure;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize)]
+pub struct Depot {
+    /// When the depot was recognized
+    pub time: DateTime<Local>,
+    pub items: Vec<Item>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize)]
+pub struct Item {
+    pub id: String,
+    pub name: String,
+    pub count: i64,
+}
+
+/// Path of the depot saved for given profile, `None` for the default profile
+pub fn path(profile: Option<&str>) -> PathBuf {
+    crate::state::dir()
+        .join("depot")
+        .join(format!("{}.json", profile.unwrap_or("default")))
+}
+
+impl Depot {
+    /// Parse the details of a `DepotInfo` message, `None` if the recognition is not done
+    pub fn from_details(details: &Value) -> Option<Self> {
+        if !details.get("done")?.as_bool()? {
+            return None;
+        }
+
+        let items = details
+            .get("arkplanner")?
+            .get("object")?
+            .get("items")?
+            .as_array()?
+            .iter()
+            .filter_map(|item| {
+                Some(Item {
+                    id: item.get("id")?.as_str()?.to_owned(),
+                    name: item.get("name")?.as_str()?.to_owned(),
+                    count: item.get("have")?.as_i64()?,
+                })
+            })
+            .collect();
+
+        Some(Self {
+            time: Local::now(),
+            items,
+        })
+    }
+
+    /// Load the last saved depot of given profile, `None` if the depot has never been recognized
+    pub fn load(profile: Option<&str>) -> Result<Option<Self>> {
+        let path = path(profile);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = std::fs::File::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let depot = serde_json::from_reader(file)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        Ok(Some(depot))
+    }
+
+    pub fn save(&self, profile: Option<&str>) -> Result<()> {
+        let path = path(profile);
+        if let Some(parent) = path.parent() {
+            parent.ensure()?;
+        }
+
+        crate::atomic_fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Count of given item by its name or id, `None` if the item is not in the depot
+    pub fn count(&self, item: &str) -> Option<i64> {
+        self.items
+            .iter()
+            .find(|i| i.name == item || i.id == item)
+            .map(|i| i.count)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn from_details() {
+        let details = json!({
+            "done": true,
+            "arkplanner": {
+                "object": {
+                    "items": [
+                        { "id": "2004", "have": 4, "name": "高级作战记录" },
+                        { "id": "30012", "have": 25, "name": "固源岩" },
+                        { "id": "broken" },
+                    ],
+                    "@type": "@penguin-statistics/depot",
+                },
+                "data": "",
+            },
+            "lolicon": { "object": { "2004": 4, "30012": 25 }, "data": "" },
+        });
+
+        let depot = Depot::from_details(&details).unwrap();
+        assert_eq!(depot.items, [
+            Item {
+                id: "2004".to_owned(),
+                name: "高级作战记录".to_owned(),
+                count: 4,
+            },
+            Item {
+                id: "30012".to_owned(),
+                name: "固源岩".to_owned(),
+                count: 25,
+            },
+        ]);
+        assert_eq!(depot.count("固源岩"), Some(25));
+        assert_eq!(depot.count("2004"), Some(4));
+        assert_eq!(depot.count("糖"), None);
+
+        assert!(Depot::from_details(&json!({ "done": false })).is_none());
+        assert!(Depot::from_details(&json!({})).is_none());
+    }
+
+    #[test]
+    fn path_per_profile() {
+        let dir = crate::state::dir().join("depot");
+        assert_eq!(path(None), dir.join("default.json"));
+        assert_eq!(path(Some("alt")), dir.join("alt.json"));
+    }
+}
@@ -0,0 +1,783 @@
+//! Persistent history of task runs and queries on it
+
+pub mod roguelike;
+mod store;
+
+use std::{collections::BTreeMap as Map, fmt::Write};
+
+use anyhow::Result;
+use chrono::{DateTime, Days, Local, NaiveDate};
+use maa_types::{TaskType, primitive::AsstTaskId};
+use serde::{Deserialize, Serialize};
+pub use store::JsonLines;
+
+/// Final status of a run
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Succeeded,
+    Failed,
+}
+
+impl Status {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Status::Succeeded => "succeeded",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+/// A finished run of a custom task, a predefined task or a daemon job
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize)]
+pub struct Record {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// Name of the profile, `None` for the default profile
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Name or path of the task file, `None` for predefined tasks
+    #[serde(default)]
+    pub task: Option<String>,
+    /// Name of the daemon job, `None` if not run by the daemon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<String>,
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Summary of each task, the same as the output of `--summary-format json`
+    #[serde(default)]
+    pub tasks: Vec<TaskRecord>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize)]
+pub struct TaskRecord {
+    pub id: AsstTaskId,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub task_type: TaskType,
+    #[serde(default)]
+    pub start_time: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub end_time: Option<DateTime<Local>>,
+    pub reason: String,
+    #[serde(default)]
+    pub detail: serde_json::Value,
+}
+
+fn store() -> JsonLines<Record> {
+    JsonLines::new(join!(crate::state::dir(), "history", "runs.jsonl"))
+}
+
+/// Append a finished run to the history, explorations of roguelike tasks are also stored
+pub fn append(record: &Record) -> Result<()> {
+    store().append(record)?;
+    roguelike::append(record)
+}
+
+/// When a task with given name or type was completed last time in given profile
+/// (`None` for the default profile), `None` if never completed
+pub fn last_completed(profile: Option<&str>, task: &str) -> Result<Option<DateTime<Local>>> {
+    Ok(last_completed_in(&store().records()?, profile, task))
+}
+
+fn last_completed_in(
+    records: &[Record],
+    profile: Option<&str>,
+    task: &str,
+) -> Option<DateTime<Local>> {
+    let profile = profile.unwrap_or("default");
+    records
+        .iter()
+        .filter(|record| record.profile.as_deref().unwrap_or("default") == profile)
+        .flat_map(|record| {
+            record
+                .tasks
+                .iter()
+                .filter(|t| t.reason == "Completed")
+                .filter(|t| t.name.as_deref() == Some(task) || t.task_type.to_str() == task)
+                .map(|t| t.end_time.unwrap_or(record.end))
+        })
+        .max()
+}
+
+/// When given daemon job was started last time in given profile, `None` if never started
+pub fn last_job_start(profile: Option<&str>, job: &str) -> Result<Option<DateTime<Local>>> {
+    Ok(last_job_start_in(&store().records()?, profile, job))
+}
+
+fn last_job_start_in(
+    records: &[Record],
+    profile: Option<&str>,
+    job: &str,
+) -> Option<DateTime<Local>> {
+    let profile = profile.unwrap_or("default");
+    records
+        .iter()
+        .filter(|record| record.profile.as_deref().unwrap_or("default") == profile)
+        .filter(|record| record.job.as_deref() == Some(job))
+        .map(|record| record.start)
+        .max()
+}
+
+#[derive(clap::Subcommand)]
+pub enum HistoryCommand {
+    /// List recorded runs
+    List {
+        #[command(flatten)]
+        filter: Filter,
+        /// Only show the last given number of runs
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+    /// Show aggregated statistics of recorded runs
+    ///
+    /// The statistics include the number of runs and tasks,
+    /// the fight times, sanity, medicine and stone used and the drops of each stage,
+    /// the recruit and refresh times, and the results of roguelike explorations.
+    #[command(verbatim_doc_comment)]
+    Stats {
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// Compare strategies of roguelike explorations
+    ///
+    /// Explorations are grouped by theme, mode, squad, roles and core operator,
+    /// and the pass rate, average floor reached, battles, ingots invested,
+    /// exp gained and duration of each strategy are shown.
+    #[command(verbatim_doc_comment)]
+    Roguelike {
+        #[command(flatten)]
+        filter: roguelike::Filter,
+    },
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(clap::Args, Default)]
+pub struct Filter {
+    /// Only include runs started on or after given date, e.g. 2024-01-01
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+    /// Only include runs started on or before given date
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+    /// Only include runs started in the last given number of days, including today
+    #[arg(long, conflicts_with = "since")]
+    pub days: Option<u64>,
+    /// Only include tasks of given type, e.g. Fight
+    #[arg(long = "type")]
+    pub task_type: Option<TaskType>,
+    /// Only include runs with given profile
+    #[arg(short, long)]
+    pub profile: Option<String>,
+    /// Only include runs of given task file
+    #[arg(long)]
+    pub task: Option<String>,
+}
+
+impl Filter {
+    fn since(&self, today: NaiveDate) -> Option<NaiveDate> {
+        match self.days {
+            Some(days) => today.checked_sub_days(Days::new(days.saturating_sub(1))),
+            None => self.since,
+        }
+    }
+
+    fn matches(&self, record: &Record, today: NaiveDate) -> bool {
+        let date = record.start.date_naive();
+        self.since(today).is_none_or(|since| date >= since)
+            && self.until.is_none_or(|until| date <= until)
+            && self
+                .profile
+                .as_deref()
+                .is_none_or(|profile| record.profile.as_deref().unwrap_or("default") == profile)
+            && self
+                .task
+                .as_deref()
+                .is_none_or(|task| record.task.as_deref() == Some(task))
+            && (self.task_type.is_none() || self.tasks(record).next().is_some())
+    }
+
+    /// Tasks of a record that match the task type
+    fn tasks<'a>(&self, record: &'a Record) -> impl Iterator<Item = &'a TaskRecord> {
+        let task_type = self.task_type;
+        record
+            .tasks
+            .iter()
+            .filter(move |task| task_type.is_none_or(|t| task.task_type == t))
+    }
+
+    fn select<'a>(&self, records: &'a [Record], today: NaiveDate) -> Vec<&'a Record> {
+        records
+            .iter()
+            .filter(|record| self.matches(record, today))
+            .collect()
+    }
+}
+
+pub fn history(command: HistoryCommand) -> Result<()> {
+    let records = store().records()?;
+    let today = Local::now().date_naive();
+
+    match command {
+        HistoryCommand::List { filter, limit } => {
+            let selected = filter.select(&records, today);
+            let skip = limit.map_or(0, |limit| selected.len().saturating_sub(limit));
+            print!("{}", list(&selected[skip..], &filter));
+        }
+        HistoryCommand::Stats { filter } => {
+            let selected = filter.select(&records, today);
+            print!("{}", Stats::collect(&selected, &filter));
+        }
+        HistoryCommand::Roguelike { filter } => roguelike::compare(&filter)?,
+    }
+
+    Ok(())
+}
+
+fn list(records: &[&Record], filter: &Filter) -> String {
+    let mut output = String::new();
+    for record in records {
+        let seconds = (record.end - record.start).num_seconds();
+        let _ = write!(
+            output,
+            "{} {:02}:{:02}:{:02} {:<9} {} {}",
+            record.start.format("%Y-%m-%d %H:%M:%S"),
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            record.status.to_str(),
+            record.profile.as_deref().unwrap_or("default"),
+            record.task.as_deref().unwrap_or("-"),
+        );
+        for (i, task) in filter.tasks(record).enumerate() {
+            let sep = if i == 0 { ": " } else { ", " };
+            let _ = write!(output, "{sep}{} {}", task.task_type, task.reason);
+        }
+        output.push('\n');
+        if let Some(error) = record.error.as_deref() {
+            let _ = writeln!(output, "  error: {error}");
+        }
+    }
+    output
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FightStats {
+    times: i64,
+    sanity: i64,
+    medicine: i64,
+    expiring_medicine: i64,
+    stone: i64,
+    total_drops: Map<String, i64>,
+}
+
+impl FightStats {
+    fn add(&mut self, other: &FightStats) {
+        self.times += other.times;
+        self.sanity += other.sanity;
+        self.medicine += other.medicine;
+        self.expiring_medicine += other.expiring_medicine;
+        self.stone += other.stone;
+        for (item, count) in &other.total_drops {
+            *self.total_drops.entry(item.clone()).or_default() += count;
+        }
+    }
+}
+
+impl std::fmt::Display for FightStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} times, {} sanity", self.times, self.sanity)?;
+        if self.medicine != 0 {
+            write!(f, ", {} medicine", self.medicine)?;
+            if self.expiring_medicine != 0 {
+                write!(f, " ({} expiring)", self.expiring_medicine)?;
+            }
+        }
+        if self.stone != 0 {
+            write!(f, ", {} stone", self.stone)?;
+        }
+        let mut drops = self.total_drops.iter();
+        if let Some((item, count)) = drops.next() {
+            write!(f, ", drops: {item} × {count}")?;
+            for (item, count) in drops {
+                write!(f, ", {item} × {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RecruitStats {
+    recruit_times: Option<i64>,
+    refresh_times: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RoguelikeStats {
+    explorations: Vec<Exploration>,
+}
+
+#[derive(Deserialize)]
+struct Exploration {
+    state: String,
+    #[serde(default)]
+    invest: Option<i64>,
+    #[serde(default)]
+    exp: Option<i64>,
+}
+
+/// Statistics aggregated from a set of runs
+#[derive(Default)]
+struct Stats {
+    runs: usize,
+    failed: usize,
+    tasks: Map<String, usize>,
+    /// Fight statistics of each stage
+    stages: Map<String, FightStats>,
+    recruit_times: i64,
+    refresh_times: i64,
+    explorations: Map<String, usize>,
+    invest: i64,
+    exp: i64,
+}
+
+impl Stats {
+    fn collect(records: &[&Record], filter: &Filter) -> Self {
+        let mut stats = Stats::default();
+        for record in records {
+            stats.runs += 1;
+            if matches!(record.status, Status::Failed) {
+                stats.failed += 1;
+            }
+            for task in filter.tasks(record) {
+                stats.add_task(task);
+            }
+        }
+        stats
+    }
+
+    fn add_task(&mut self, task: &TaskRecord) {
+        *self.tasks.entry(task.task_type.to_string()).or_default() += 1;
+
+        // Details in records written by other versions may be malformed, skip them silently
+        let detail = task.detail.clone();
+        match task.task_type {
+            TaskType::Fight => {
+                #[derive(Deserialize)]
+                struct Detail {
+                    stage: Option<String>,
+                    #[serde(flatten)]
+                    stats: FightStats,
+                }
+
+                if let Ok(Detail {
+                    stage: Some(stage),
+                    stats,
+                }) = serde_json::from_value(detail)
+                {
+                    self.stages.entry(stage).or_default().add(&stats);
+                }
+            }
+            TaskType::Recruit => {
+                if let Ok(detail) = serde_json::from_value::<RecruitStats>(detail) {
+                    self.recruit_times += detail.recruit_times.unwrap_or_default();
+                    self.refresh_times += detail.refresh_times.unwrap_or_default();
+                }
+            }
+            TaskType::Roguelike => {
+                if let Ok(detail) = serde_json::from_value::<RoguelikeStats>(detail) {
+                    for exploration in detail.explorations {
+                        *self.explorations.entry(exploration.state).or_default() += 1;
+                        self.invest += exploration.invest.unwrap_or_default();
+                        self.exp += exploration.exp.unwrap_or_default();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Runs: {} ({} failed)", self.runs, self.failed)?;
+
+        if !self.tasks.is_empty() {
+            write!(f, "Tasks:")?;
+            for (i, (task, count)) in self.tasks.iter().enumerate() {
+                let sep = if i == 0 { " " } else { ", " };
+                write!(f, "{sep}{task} {count}")?;
+            }
+            writeln!(f)?;
+        }
+
+        if !self.stages.is_empty() {
+            writeln!(f, "Fight:")?;
+            let mut total = FightStats::default();
+            for (stage, stats) in &self.stages {
+                writeln!(f, "  {stage}: {stats}")?;
+                total.add(stats);
+            }
+            if self.stages.len() > 1 {
+                writeln!(f, "  Total: {total}")?;
+            }
+        }
+
+        if self.tasks.contains_key(TaskType::Recruit.to_str()) {
+            writeln!(
+                f,
+                "Recruit: recruited {} times, refreshed {} times",
+                self.recruit_times, self.refresh_times
+            )?;
+        }
+
+        if !self.explorations.is_empty() {
+            write!(
+                f,
+                "Roguelike: {} explorations (",
+                self.explorations.values().sum::<usize>()
+            )?;
+            for (i, (state, count)) in self.explorations.iter().enumerate() {
+                let sep = if i == 0 { "" } else { ", " };
+                write!(f, "{sep}{state} {count}")?;
+            }
+            writeln!(
+                f,
+                "), invested {} originium ingots, gained {} exp",
+                self.invest, self.exp
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    fn time(day: u32, hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn task(id: AsstTaskId, task_type: TaskType, detail: serde_json::Value) -> TaskRecord {
+        TaskRecord {
+            id,
+            name: None,
+            task_type,
+            start_time: None,
+            end_time: None,
+            reason: "Completed".to_owned(),
+            detail,
+        }
+    }
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record {
+                start: time(1, 4),
+                end: time(1, 5),
+                profile: None,
+                task: Some("daily".to_owned()),
+                job: None,
+                status: Status::Succeeded,
+                error: None,
+                tasks: vec![
+                    task(
+                        1,
+                        TaskType::Fight,
+                        json!({
+                            "stage": "1-7",
+                            "times": 5,
+                            "sanity": 30,
+                            "medicine": 1,
+                            "expiring_medicine": 1,
+                            "stone": 0,
+                            "drops": [],
+                            "total_drops": { "固源岩": 10 },
+                        }),
+                    ),
+                    task(
+                        2,
+                        TaskType::Recruit,
+                        json!({ "recruit_times": 4, "refresh_times": 1, "records": [] }),
+                    ),
+                ],
+            },
+            Record {
+                start: time(3, 4),
+                end: time(3, 4),
+                profile: Some("alt".to_owned()),
+                task: None,
+                job: None,
+                status: Status::Failed,
+                error: Some("Interrupted by user!".to_owned()),
+                tasks: vec![task(
+                    1,
+                    TaskType::Fight,
+                    json!({
+                        "stage": "CE-6",
+                        "times": 1,
+                        "sanity": 36,
+                        "total_drops": { "龙门币": 10000 },
+                    }),
+                )],
+            },
+            Record {
+                start: time(7, 16),
+                end: time(7, 18),
+                profile: None,
+                task: Some("daily".to_owned()),
+                job: Some("daily".to_owned()),
+                status: Status::Succeeded,
+                error: None,
+                tasks: vec![
+                    task(
+                        1,
+                        TaskType::Fight,
+                        json!({
+                            "stage": "1-7",
+                            "times": 2,
+                            "sanity": 12,
+                            "total_drops": { "固源岩": 3, "源岩": 1 },
+                        }),
+                    ),
+                    task(
+                        2,
+                        TaskType::Roguelike,
+                        json!({ "explorations": [
+                            { "state": "Passed", "invest": 10, "exp": 200 },
+                            { "state": "Failed", "invest": null, "exp": 50 },
+                        ] }),
+                    ),
+                    task(3, TaskType::CloseDown, json!(null)),
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn last_completed() {
+        let mut records = records();
+        records[0].tasks[1].name = Some("Recruit Daily".to_owned());
+
+        assert_eq!(
+            last_completed_in(&records, None, "Fight"),
+            Some(time(7, 18))
+        );
+        assert_eq!(
+            last_completed_in(&records, None, "Recruit"),
+            Some(time(1, 5))
+        );
+        assert_eq!(
+            last_completed_in(&records, None, "Recruit Daily"),
+            Some(time(1, 5))
+        );
+        assert_eq!(last_completed_in(&records, None, "Mall"), None);
+
+        records[2].tasks[0].reason = "Error".to_owned();
+        assert_eq!(last_completed_in(&records, None, "Fight"), Some(time(1, 5)));
+
+        // runs of other profiles are not counted
+        assert_eq!(
+            last_completed_in(&records, Some("alt"), "Fight"),
+            Some(time(3, 4))
+        );
+        assert_eq!(
+            last_completed_in(&records, Some("default"), "Fight"),
+            Some(time(1, 5))
+        );
+        assert_eq!(last_completed_in(&records, Some("alt"), "Recruit"), None);
+    }
+
+    #[test]
+    fn last_job_start() {
+        let records = records();
+
+        assert_eq!(
+            last_job_start_in(&records, None, "daily"),
+            Some(time(7, 16))
+        );
+        assert_eq!(last_job_start_in(&records, Some("alt"), "daily"), None);
+        assert_eq!(last_job_start_in(&records, None, "fight"), None);
+    }
+
+    #[test]
+    fn serde_record() {
+        let record = &records()[0];
+        let line = serde_json::to_string(record).unwrap();
+        assert_eq!(&serde_json::from_str::<Record>(&line).unwrap(), record);
+
+        // task records are compatible with the summary
+        let task: TaskRecord = serde_json::from_value(json!({
+            "id": 1,
+            "name": "Fight TS",
+            "type": "Fight",
+            "start_time": "2024-01-01T04:00:00+08:00",
+            "end_time": null,
+            "reason": "Unfinished",
+            "detail": null,
+        }))
+        .unwrap();
+        assert_eq!(task.task_type, TaskType::Fight);
+        assert_eq!(task.name.as_deref(), Some("Fight TS"));
+        assert!(task.start_time.is_some());
+    }
+
+    #[test]
+    fn filter() {
+        let records = records();
+        let today = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let select = |filter: Filter| {
+            filter
+                .select(&records, today)
+                .into_iter()
+                .map(|r| r.start)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(select(Filter::default()), [
+            time(1, 4),
+            time(3, 4),
+            time(7, 16)
+        ]);
+        assert_eq!(
+            select(Filter {
+                since: NaiveDate::from_ymd_opt(2024, 1, 2),
+                ..Default::default()
+            }),
+            [time(3, 4), time(7, 16)]
+        );
+        assert_eq!(
+            select(Filter {
+                until: NaiveDate::from_ymd_opt(2024, 1, 3),
+                ..Default::default()
+            }),
+            [time(1, 4), time(3, 4)]
+        );
+        assert_eq!(
+            select(Filter {
+                days: Some(5),
+                ..Default::default()
+            }),
+            [time(3, 4), time(7, 16)]
+        );
+        assert_eq!(
+            select(Filter {
+                task_type: Some(TaskType::Recruit),
+                ..Default::default()
+            }),
+            [time(1, 4)]
+        );
+        assert_eq!(
+            select(Filter {
+                profile: Some("default".to_owned()),
+                ..Default::default()
+            }),
+            [time(1, 4), time(7, 16)]
+        );
+        assert_eq!(
+            select(Filter {
+                profile: Some("alt".to_owned()),
+                ..Default::default()
+            }),
+            [time(3, 4)]
+        );
+        assert_eq!(
+            select(Filter {
+                task: Some("daily".to_owned()),
+                ..Default::default()
+            }),
+            [time(1, 4), time(7, 16)]
+        );
+    }
+
+    #[test]
+    fn list_records() {
+        let records = records();
+        let selected: Vec<_> = records.iter().collect();
+
+        assert_eq!(
+            list(&selected, &Filter::default()),
+            "2024-01-01 04:00:00 01:00:00 succeeded default daily: \
+             Fight Completed, Recruit Completed\n\
+             2024-01-03 04:00:00 00:00:00 failed    alt -: Fight Completed\n  \
+             error: Interrupted by user!\n\
+             2024-01-07 16:00:00 02:00:00 succeeded default daily: \
+             Fight Completed, Roguelike Completed, CloseDown Completed\n"
+        );
+
+        let filter = Filter {
+            task_type: Some(TaskType::Roguelike),
+            ..Default::default()
+        };
+        assert_eq!(
+            list(&filter.select(&records, time(7, 0).date_naive()), &filter),
+            "2024-01-07 16:00:00 02:00:00 succeeded default daily: Roguelike Completed\n"
+        );
+    }
+
+    #[test]
+    fn aggregate() {
+        let records = records();
+        let selected: Vec<_> = records.iter().collect();
+
+        let stats = Stats::collect(&selected, &Filter::default());
+        assert_eq!(stats.runs, 3);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.stages["1-7"], FightStats {
+            times: 7,
+            sanity: 42,
+            medicine: 1,
+            expiring_medicine: 1,
+            stone: 0,
+            total_drops: [("固源岩".to_owned(), 13), ("源岩".to_owned(), 1)].into(),
+        });
+        assert_eq!(
+            stats.to_string(),
+            "Runs: 3 (1 failed)\n\
+             Tasks: CloseDown 1, Fight 3, Recruit 1, Roguelike 1\n\
+             Fight:\n  \
+             1-7: 7 times, 42 sanity, 1 medicine (1 expiring), drops: 固源岩 × 13, 源岩 × 1\n  \
+             CE-6: 1 times, 36 sanity, drops: 龙门币 × 10000\n  \
+             Total: 8 times, 78 sanity, 1 medicine (1 expiring), \
+             drops: 固源岩 × 13, 源岩 × 1, 龙门币 × 10000\n\
+             Recruit: recruited 4 times, refreshed 1 times\n\
+             Roguelike: 2 explorations (Failed 1, Passed 1), \
+             invested 10 originium ingots, gained 250 exp\n"
+        );
+
+        let filter = Filter {
+            task_type: Some(TaskType::Fight),
+            profile: Some("default".to_owned()),
+            ..Default::default()
+        };
+        let selected = filter.select(&records, time(7, 0).date_naive());
+        assert_eq!(
+            Stats::collect(&selected, &filter).to_string(),
+            "Runs: 2 (0 failed)\n\
+             Tasks: Fight 2\n\
+             Fight:\n  \
+             1-7: 7 times, 42 sanity, 1 medicine (1 expiring), drops: 固源岩 × 13, 源岩 × 1\n"
+        );
+
+        assert_eq!(
+            Stats::collect(&[], &Filter::default()).to_string(),
+            "Runs: 0 (0 failed)\n"
+        );
+    }
+}
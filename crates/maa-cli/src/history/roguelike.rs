@@ -0,0 +1,649 @@
+//! Explorations of roguelike tasks, stored separately to compare strategies across runs
+
+use std::{collections::BTreeMap as Map, fmt::Write};
+
+use anyhow::Result;
+use chrono::{DateTime, Days, Local, NaiveDate};
+use maa_types::TaskType;
+use serde::{Deserialize, Serialize};
+
+use super::{JsonLines, Record};
+
+/// Strategy of roguelike explorations, set by params of the roguelike task
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Strategy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub squad: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roles: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub core_char: Option<String>,
+}
+
+impl std::fmt::Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mode = self.mode.map(|mode| mode.to_string());
+        let items = [
+            ("theme", self.theme.as_deref()),
+            ("mode", mode.as_deref()),
+            ("squad", self.squad.as_deref()),
+            ("roles", self.roles.as_deref()),
+            ("core operator", self.core_char.as_deref()),
+        ];
+        let mut items = items
+            .iter()
+            .filter_map(|(key, value)| Some((key, (*value)?)));
+        match items.next() {
+            Some((key, value)) => write!(f, "{key} {value}")?,
+            None => write!(f, "default strategy")?,
+        }
+        for (key, value) in items {
+            write!(f, ", {key} {value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An exploration, the same as those in the summary of a roguelike task
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize)]
+pub struct Exploration {
+    pub state: String,
+    #[serde(default)]
+    pub invest: Option<i64>,
+    #[serde(default)]
+    pub exp: Option<i64>,
+    #[serde(default)]
+    pub floor: Option<i64>,
+    #[serde(default)]
+    pub battles: i64,
+    /// Originium ingots in the bank after the last investment
+    #[serde(default)]
+    pub deposit: Option<i64>,
+    pub start: DateTime<Local>,
+    #[serde(default)]
+    pub end: Option<DateTime<Local>>,
+}
+
+/// A finished exploration with the strategy used
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize)]
+pub struct ExplorationRecord {
+    /// Name of the profile, `None` for the default profile
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(flatten)]
+    pub strategy: Strategy,
+    #[serde(flatten)]
+    pub exploration: Exploration,
+}
+
+fn store() -> JsonLines<ExplorationRecord> {
+    JsonLines::new(join!(crate::state::dir(), "history", "roguelike.jsonl"))
+}
+
+/// Append explorations of roguelike tasks in a finished run
+pub(super) fn append(record: &Record) -> Result<()> {
+    let store = store();
+    for exploration in explorations(record) {
+        store.append(&exploration)?;
+    }
+    Ok(())
+}
+
+fn explorations(record: &Record) -> Vec<ExplorationRecord> {
+    #[derive(Deserialize)]
+    struct Detail {
+        #[serde(flatten)]
+        strategy: Strategy,
+        explorations: Vec<Exploration>,
+    }
+
+    record
+        .tasks
+        .iter()
+        .filter(|task| task.task_type == TaskType::Roguelike)
+        // Details of tasks stopped before started may be missing, skip them
+        .filter_map(|task| serde_json::from_value::<Detail>(task.detail.clone()).ok())
+        .flat_map(|detail| {
+            let strategy = detail.strategy;
+            detail
+                .explorations
+                .into_iter()
+                .map(move |exploration| ExplorationRecord {
+                    profile: record.profile.clone(),
+                    strategy: strategy.clone(),
+                    exploration,
+                })
+        })
+        .collect()
+}
+
+/// Progress towards an investment goal, estimated from recorded explorations
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Default)]
+pub struct InvestmentProgress {
+    /// Latest originium ingots in the bank, `None` if never recorded
+    pub deposit: Option<i64>,
+    /// Average originium ingots invested in a finished exploration with the same mode
+    pub average: Option<f64>,
+}
+
+/// Progress of investment of given theme in given profile (`None` for the default profile),
+/// explorations of other modes are not used to estimate
+pub fn investment_progress(
+    profile: Option<&str>,
+    theme: &str,
+    mode: i32,
+) -> Result<InvestmentProgress> {
+    Ok(progress_in(&store().records()?, profile, theme, mode))
+}
+
+fn progress_in(
+    records: &[ExplorationRecord],
+    profile: Option<&str>,
+    theme: &str,
+    mode: i32,
+) -> InvestmentProgress {
+    let profile = profile.unwrap_or("default");
+    let explorations: Vec<_> = records
+        .iter()
+        .filter(|record| record.profile.as_deref().unwrap_or("default") == profile)
+        .filter(|record| record.strategy.theme.as_deref() == Some(theme))
+        .map(|record| (record.strategy.mode, &record.exploration))
+        .collect();
+
+    let deposit = explorations
+        .iter()
+        .filter_map(|(_, exploration)| Some((exploration.start, exploration.deposit?)))
+        .max_by_key(|(start, _)| *start)
+        .map(|(_, deposit)| deposit);
+
+    let (invest, count) = explorations
+        .iter()
+        .filter(|(m, exploration)| *m == Some(mode) && exploration.end.is_some())
+        .fold((0, 0), |(invest, count), (_, exploration)| {
+            (invest + exploration.invest.unwrap_or_default(), count + 1)
+        });
+    let average = (count > 0).then(|| invest as f64 / count as f64);
+
+    InvestmentProgress { deposit, average }
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(clap::Args, Default)]
+pub struct Filter {
+    /// Only include explorations started on or after given date, e.g. 2024-01-01
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+    /// Only include explorations started on or before given date
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+    /// Only include explorations started in the last given number of days, including today
+    #[arg(long, conflicts_with = "since")]
+    pub days: Option<u64>,
+    /// Only include explorations with given profile
+    #[arg(short, long)]
+    pub profile: Option<String>,
+    /// Only include explorations of given theme, e.g. Sarkaz
+    #[arg(long)]
+    pub theme: Option<String>,
+}
+
+impl Filter {
+    fn matches(&self, record: &ExplorationRecord, today: NaiveDate) -> bool {
+        let date = record.exploration.start.date_naive();
+        let since = match self.days {
+            Some(days) => today.checked_sub_days(Days::new(days.saturating_sub(1))),
+            None => self.since,
+        };
+        since.is_none_or(|since| date >= since)
+            && self.until.is_none_or(|until| date <= until)
+            && self
+                .profile
+                .as_deref()
+                .is_none_or(|profile| record.profile.as_deref().unwrap_or("default") == profile)
+            && self
+                .theme
+                .as_deref()
+                .is_none_or(|theme| record.strategy.theme.as_deref() == Some(theme))
+    }
+}
+
+/// Show statistics of explorations grouped by strategy
+pub(super) fn compare(filter: &Filter) -> Result<()> {
+    let records = store().records()?;
+    let today = Local::now().date_naive();
+    let selected: Vec<_> = records
+        .iter()
+        .filter(|record| filter.matches(record, today))
+        .collect();
+    print!("{}", report(&selected));
+    Ok(())
+}
+
+/// Statistics of explorations with the same strategy
+#[derive(Default)]
+struct StrategyStats {
+    explorations: usize,
+    states: Map<String, usize>,
+    battles: i64,
+    invest: i64,
+    exp: i64,
+    /// Sum and count of floors reached, explorations without a floor are not counted
+    floors: (i64, usize),
+    /// Sum and count of durations in seconds, unfinished explorations are not counted
+    durations: (i64, usize),
+}
+
+impl StrategyStats {
+    fn add(&mut self, exploration: &Exploration) {
+        self.explorations += 1;
+        *self.states.entry(exploration.state.clone()).or_default() += 1;
+        self.battles += exploration.battles;
+        self.invest += exploration.invest.unwrap_or_default();
+        self.exp += exploration.exp.unwrap_or_default();
+        if let Some(floor) = exploration.floor {
+            self.floors.0 += floor;
+            self.floors.1 += 1;
+        }
+        if let Some(end) = exploration.end {
+            self.durations.0 += (end - exploration.start).num_seconds();
+            self.durations.1 += 1;
+        }
+    }
+}
+
+impl std::fmt::Display for StrategyStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.explorations as f64;
+        let passed = self.states.get("Passed").copied().unwrap_or_default();
+
+        write!(f, "{} explorations (", self.explorations)?;
+        for (i, (state, count)) in self.states.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ", " };
+            write!(f, "{sep}{state} {count}")?;
+        }
+        writeln!(f, "), passed {:.1}%", passed as f64 * 100.0 / count)?;
+
+        write!(f, "average")?;
+        if self.floors.1 > 0 {
+            write!(
+                f,
+                " floor {:.1},",
+                self.floors.0 as f64 / self.floors.1 as f64
+            )?;
+        }
+        write!(
+            f,
+            " {:.1} battles, invested {:.1} originium ingots, gained {:.1} exp",
+            self.battles as f64 / count,
+            self.invest as f64 / count,
+            self.exp as f64 / count,
+        )?;
+        if self.durations.1 > 0 {
+            let seconds = self.durations.0 / self.durations.1 as i64;
+            write!(
+                f,
+                ", took {:02}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn report(records: &[&ExplorationRecord]) -> String {
+    let mut stats: Map<&Strategy, StrategyStats> = Map::new();
+    for record in records {
+        stats
+            .entry(&record.strategy)
+            .or_default()
+            .add(&record.exploration);
+    }
+
+    let mut output = String::new();
+    for (strategy, stats) in stats {
+        let _ = writeln!(output, "{strategy}:");
+        for line in stats.to_string().lines() {
+            let _ = writeln!(output, "  {line}");
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+    use crate::history::{Status, TaskRecord};
+
+    fn time(day: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, day, 4, minute, 0).unwrap()
+    }
+
+    fn record() -> Record {
+        Record {
+            start: time(1, 0),
+            end: time(1, 50),
+            profile: Some("alt".to_owned()),
+            task: Some("roguelike".to_owned()),
+            job: None,
+            status: Status::Succeeded,
+            error: None,
+            tasks: vec![
+                TaskRecord {
+                    id: 1,
+                    name: None,
+                    task_type: TaskType::Roguelike,
+                    start_time: Some(time(1, 0)),
+                    end_time: Some(time(1, 50)),
+                    reason: "Completed".to_owned(),
+                    detail: json!({
+                        "theme": "Sarkaz",
+                        "mode": 0,
+                        "squad": "点刺成锭分队",
+                        "explorations": [
+                            {
+                                "state": "Passed",
+                                "invest": 20,
+                                "exp": 300,
+                                "floor": 5,
+                                "battles": 8,
+                                "deposit": 120,
+                                "start": time(1, 0),
+                                "end": time(1, 40),
+                            },
+                            {
+                                "state": "Unknown",
+                                "invest": null,
+                                "exp": null,
+                                "floor": null,
+                                "battles": 1,
+                                "start": time(1, 40),
+                                "end": null,
+                            },
+                        ],
+                    }),
+                },
+                TaskRecord {
+                    id: 2,
+                    name: None,
+                    task_type: TaskType::Roguelike,
+                    start_time: None,
+                    end_time: None,
+                    reason: "Unfinished".to_owned(),
+                    detail: json!(null),
+                },
+            ],
+        }
+    }
+
+    fn exploration(day: u32, state: &str, floor: i64, minutes: u32) -> Exploration {
+        Exploration {
+            state: state.to_owned(),
+            invest: Some(10),
+            exp: Some(100),
+            floor: Some(floor),
+            battles: 4,
+            deposit: None,
+            start: time(day, 0),
+            end: Some(time(day, minutes)),
+        }
+    }
+
+    #[test]
+    fn extract_explorations() {
+        let explorations = explorations(&record());
+        assert_eq!(explorations.len(), 2);
+        assert_eq!(explorations[0].profile.as_deref(), Some("alt"));
+        assert_eq!(explorations[1].strategy, Strategy {
+            theme: Some("Sarkaz".to_owned()),
+            mode: Some(0),
+            squad: Some("点刺成锭分队".to_owned()),
+            ..Default::default()
+        });
+        assert_eq!(explorations[0].exploration, Exploration {
+            state: "Passed".to_owned(),
+            invest: Some(20),
+            exp: Some(300),
+            floor: Some(5),
+            battles: 8,
+            deposit: Some(120),
+            start: time(1, 0),
+            end: Some(time(1, 40)),
+        });
+
+        let line = serde_json::to_string(&explorations[0]).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ExplorationRecord>(&line).unwrap(),
+            explorations[0]
+        );
+    }
+
+    #[test]
+    fn filter() {
+        let sarkaz = Strategy {
+            theme: Some("Sarkaz".to_owned()),
+            ..Default::default()
+        };
+        let records = [
+            ExplorationRecord {
+                profile: None,
+                strategy: sarkaz.clone(),
+                exploration: exploration(1, "Passed", 5, 30),
+            },
+            ExplorationRecord {
+                profile: Some("alt".to_owned()),
+                strategy: Strategy::default(),
+                exploration: exploration(5, "Failed", 2, 10),
+            },
+        ];
+        let today = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let select = |filter: Filter| {
+            records
+                .iter()
+                .filter(|record| filter.matches(record, today))
+                .map(|record| record.exploration.start)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(select(Filter::default()), [time(1, 0), time(5, 0)]);
+        assert_eq!(
+            select(Filter {
+                days: Some(3),
+                ..Default::default()
+            }),
+            [time(5, 0)]
+        );
+        assert_eq!(
+            select(Filter {
+                until: NaiveDate::from_ymd_opt(2024, 1, 4),
+                ..Default::default()
+            }),
+            [time(1, 0)]
+        );
+        assert_eq!(
+            select(Filter {
+                profile: Some("default".to_owned()),
+                ..Default::default()
+            }),
+            [time(1, 0)]
+        );
+        assert_eq!(
+            select(Filter {
+                theme: Some("Sarkaz".to_owned()),
+                ..Default::default()
+            }),
+            [time(1, 0)]
+        );
+    }
+
+    #[test]
+    fn progress() {
+        let strategy = |mode| Strategy {
+            theme: Some("Sarkaz".to_owned()),
+            mode: Some(mode),
+            ..Default::default()
+        };
+        let records = [
+            ExplorationRecord {
+                profile: None,
+                strategy: strategy(1),
+                exploration: Exploration {
+                    invest: Some(30),
+                    deposit: Some(80),
+                    ..exploration(1, "Failed", 3, 30)
+                },
+            },
+            ExplorationRecord {
+                profile: None,
+                strategy: strategy(0),
+                exploration: Exploration {
+                    deposit: Some(90),
+                    ..exploration(2, "Failed", 3, 30)
+                },
+            },
+            ExplorationRecord {
+                profile: None,
+                strategy: strategy(1),
+                exploration: Exploration {
+                    invest: None,
+                    ..exploration(3, "Failed", 1, 5)
+                },
+            },
+            // unfinished explorations are not used to estimate
+            ExplorationRecord {
+                profile: None,
+                strategy: strategy(1),
+                exploration: Exploration {
+                    end: None,
+                    ..exploration(4, "Unknown", 1, 0)
+                },
+            },
+        ];
+
+        assert_eq!(
+            progress_in(&records, None, "Sarkaz", 1),
+            InvestmentProgress {
+                deposit: Some(90),
+                average: Some(15.0),
+            }
+        );
+        assert_eq!(
+            progress_in(&records, None, "Sarkaz", 5),
+            InvestmentProgress {
+                deposit: Some(90),
+                average: None,
+            }
+        );
+        assert_eq!(
+            progress_in(&records, None, "Sami", 1),
+            InvestmentProgress::default()
+        );
+    }
+
+    #[test]
+    fn progress_per_profile() {
+        let record = |profile: Option<&str>, start, invest, deposit| ExplorationRecord {
+            profile: profile.map(ToOwned::to_owned),
+            strategy: Strategy {
+                theme: Some("Sarkaz".to_owned()),
+                mode: Some(1),
+                ..Default::default()
+            },
+            exploration: Exploration {
+                invest: Some(invest),
+                deposit: Some(deposit),
+                ..exploration(start, "Failed", 3, 30)
+            },
+        };
+        let records = [
+            record(None, 1, 10, 100),
+            record(Some("alt"), 2, 40, 300),
+            record(Some("default"), 3, 20, 120),
+        ];
+
+        assert_eq!(
+            progress_in(&records, None, "Sarkaz", 1),
+            InvestmentProgress {
+                deposit: Some(120),
+                average: Some(15.0),
+            }
+        );
+        assert_eq!(
+            progress_in(&records, Some("alt"), "Sarkaz", 1),
+            InvestmentProgress {
+                deposit: Some(300),
+                average: Some(40.0),
+            }
+        );
+        assert_eq!(
+            progress_in(&records, Some("other"), "Sarkaz", 1),
+            InvestmentProgress::default()
+        );
+    }
+
+    #[test]
+    fn compare_strategies() {
+        let strategy = |squad: &str| Strategy {
+            theme: Some("Sarkaz".to_owned()),
+            mode: Some(0),
+            squad: Some(squad.to_owned()),
+            ..Default::default()
+        };
+        let records = [
+            ExplorationRecord {
+                profile: None,
+                strategy: strategy("点刺成锭分队"),
+                exploration: exploration(1, "Passed", 5, 40),
+            },
+            ExplorationRecord {
+                profile: None,
+                strategy: strategy("指挥分队"),
+                exploration: exploration(2, "Failed", 2, 10),
+            },
+            ExplorationRecord {
+                profile: None,
+                strategy: strategy("点刺成锭分队"),
+                exploration: Exploration {
+                    floor: None,
+                    end: None,
+                    ..exploration(3, "Failed", 0, 0)
+                },
+            },
+            ExplorationRecord {
+                profile: None,
+                strategy: Strategy::default(),
+                exploration: exploration(4, "Passed", 4, 35),
+            },
+        ];
+        let selected: Vec<_> = records.iter().collect();
+
+        assert_eq!(
+            report(&selected),
+            "default strategy:\n  \
+             1 explorations (Passed 1), passed 100.0%\n  \
+             average floor 4.0, 4.0 battles, invested 10.0 originium ingots, \
+             gained 100.0 exp, took 00:35:00\n\
+             theme Sarkaz, mode 0, squad 指挥分队:\n  \
+             1 explorations (Failed 1), passed 0.0%\n  \
+             average floor 2.0, 4.0 battles, invested 10.0 originium ingots, \
+             gained 100.0 exp, took 00:10:00\n\
+             theme Sarkaz, mode 0, squad 点刺成锭分队:\n  \
+             2 explorations (Failed 1, Passed 1), passed 50.0%\n  \
+             average floor 5.0, 4.0 battles, invested 10.0 originium ingots, \
+             gained 100.0 exp, took 00:40:00\n"
+        );
+    }
+}
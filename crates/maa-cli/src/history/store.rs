@@ -0,0 +1,75 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    marker::PhantomData,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use log::warn;
+use maa_dirs::Ensure;
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Append-only store of records, one JSON object per line
+pub struct JsonLines<T> {
+    path: PathBuf,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonLines<T> {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize> JsonLines<T> {
+    pub fn append(&self, record: &T) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            parent.ensure()?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> JsonLines<T> {
+    /// Load all records, broken lines are skipped with a warning
+    pub fn records(&self) -> Result<Vec<T>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = File::open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        let mut records = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(e) => warn!(
+                    "Skipping invalid record at {}:{}: {e}",
+                    self.path.display(),
+                    index + 1
+                ),
+            }
+        }
+
+        Ok(records)
+    }
+}
@@ -123,6 +123,12 @@ pub fn update(args: &CommonArgs) -> Result<()> {
         self_replace::self_replace(&tmp_exe).with_desc("Failed to replace maa-cli binary")
     });
 
+    let installer = if let Some((key, required)) = super::signature_key()? {
+        installer.with_signature_key(key, required)
+    } else {
+        installer
+    };
+
     installer
         .exec(
             maa_dirs::cache().ensure()?,
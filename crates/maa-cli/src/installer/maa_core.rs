@@ -8,11 +8,13 @@ use std::{
 use anyhow::{Context, Result, bail};
 use maa_dirs::{self, Ensure, MAA_CORE_LIB};
 use maa_installer::{
+    error::WithDesc,
     manifest::{Asset, Manifest},
-    verify::SizeVerifier,
+    verify::{SizeVerifier, digest::DigestVerifier},
 };
 use maa_version::{VersionManifest, core};
 use semver::Version;
+use sha2::Sha256;
 
 // use super::reporter::StepReporter;
 use crate::{
@@ -39,6 +41,24 @@ impl CoreManifest {
         Ok(CoreManifest(manifest))
     }
 
+    /// Read the manifest and check that it describes the pinned version, if any
+    fn from_reader_pinned(
+        file: std::fs::File,
+        pinned: Option<&Version>,
+    ) -> maa_installer::error::Result<Self> {
+        use maa_installer::error::{Error, ErrorKind};
+        let manifest = Self::from_reader(file)?;
+        if let Some(pinned) = pinned
+            && manifest.version() != pinned
+        {
+            return Err(Error::new(ErrorKind::Other).with_desc(format!(
+                "Version manifest describes v{} instead of pinned v{pinned}",
+                manifest.version()
+            )));
+        }
+        Ok(manifest)
+    }
+
     fn get_asset(&self, os: &str, arch: &str) -> Option<CoreAsset<'_>> {
         let asset_name = asset_name(self.version(), os, arch);
         self.0
@@ -158,20 +178,87 @@ fn extract_mapper(
     None
 }
 
+/// Where to install MaaCore from
+#[cfg_attr(test, derive(Debug, PartialEq))]
+enum Source {
+    /// Fetch the version manifest from the api and download the package
+    Remote,
+    /// A local version manifest with the package next to it
+    Manifest(PathBuf),
+    /// A local package
+    Archive(PathBuf),
+}
+
+impl Source {
+    /// Resolve the source from `--manifest` and `--from`
+    ///
+    /// A directory given by `--from` is a local mirror, where the manifest is named like the api.
+    fn resolve(args: &CommonArgs, manifest_file: &str) -> Result<Self> {
+        if let Some(manifest) = &args.manifest {
+            let path = match manifest.split_once("://") {
+                Some(("file", path)) => path,
+                Some(_) => bail!("Only local manifests are supported, use `--api-url` instead"),
+                None => manifest,
+            };
+            return Ok(Self::Manifest(PathBuf::from(path)));
+        }
+
+        match &args.from {
+            None => Ok(Self::Remote),
+            Some(from) if from.is_dir() => Ok(Self::Manifest(from.join(manifest_file))),
+            Some(from) if from.is_file() => Ok(Self::Archive(from.clone())),
+            Some(from) => bail!("{} not found", from.display()),
+        }
+    }
+}
+
+/// Verifier from the checksum file next to the archive, e.g. `MAA-v5.2.0-win-x64.zip.sha256`
+///
+/// The checksum file is in the format of `sha256sum`, only the first hash is used.
+fn checksum_verifier(
+    archive: &Path,
+) -> maa_installer::error::Result<Option<DigestVerifier<Sha256>>> {
+    let mut checksum_file = archive.as_os_str().to_owned();
+    checksum_file.push(".sha256");
+    let checksum_file = PathBuf::from(checksum_file);
+    if !checksum_file.is_file() {
+        log::warn!(
+            "No checksum file found for {}, skip checksum verification",
+            archive.display()
+        );
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&checksum_file)
+        .then_with_desc(|| format!("Failed to read {}", checksum_file.display()))?;
+    let hash = content.split_whitespace().next().unwrap_or_default();
+    DigestVerifier::from_hex_str(hash).map(Some)
+}
+
 fn create_and_exec_installer(args: &CommonArgs, current_version: Option<&Version>) -> Result<()> {
     let config = CLI_CONFIG.core_config().apply_args(args);
     let lib_dir = maa_dirs::library();
     let resource_dir = maa_dirs::resource();
     let components = config.components();
+    let pinned = config.version();
+    if let Some(version) = pinned {
+        log::info!("MaaCore is pinned to v{version}");
+    }
+
+    // Whether installed components have been moved into a snapshot by the pre-install hook
+    let snapshotted = std::cell::Cell::new(false);
+    let snapshotted = &snapshotted;
 
     let installer = maa_installer::installer::Installer::new(
         crate::state::AGENT.clone(),
         config.api_url(),
-        CoreManifest::from_reader,
+        |file| CoreManifest::from_reader_pinned(file, pinned),
         |src| extract_mapper(src, lib_dir, resource_dir, components),
     )
     .with_test_duration(config.test_time())
+    .with_connections(config.connections())
     .with_pre_install_hook(move || {
+        snapshotted.set(super::rollback::snapshot(components));
         if components.library {
             lib_dir.ensure_clean()?;
         }
@@ -186,13 +273,35 @@ fn create_and_exec_installer(args: &CommonArgs, current_version: Option<&Version
     } else {
         installer
     };
+    let installer = if let Some((key, required)) = super::signature_key()? {
+        installer.with_signature_key(key, required)
+    } else {
+        installer
+    };
 
-    installer
-        .exec(
-            maa_dirs::cache().ensure()?,
-            &format!("core-manifest-{}.json", config.channel()),
-        )
-        .context("Failed to install MaaCore")?;
+    let result = match Source::resolve(args, &config.manifest_file())? {
+        Source::Remote => installer.exec(maa_dirs::cache().ensure()?, &config.manifest_name()),
+        Source::Manifest(path) => installer.exec_offline(&path, checksum_verifier),
+        Source::Archive(path) => {
+            if let Some(version) = pinned
+                && path.file_name() != Some(this_asset_name(version).as_ref())
+            {
+                bail!(
+                    "{} is not the package of pinned v{version}, expected {}",
+                    path.display(),
+                    this_asset_name(version)
+                );
+            }
+            checksum_verifier(&path).and_then(|verifier| installer.exec_archive(&path, verifier))
+        }
+    };
+
+    // Components were removed by the pre-install hook, bring them back
+    if result.is_err() && snapshotted.get() {
+        super::rollback::restore_snapshot(components);
+    }
+
+    result.context("Failed to install MaaCore")?;
 
     Ok(())
 }
@@ -278,6 +387,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn resolve_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("MAA-v5.2.0-linux-x86_64.tar.gz");
+        std::fs::write(&archive, "").unwrap();
+
+        let resolve = |from: Option<&Path>, manifest: Option<&str>| {
+            Source::resolve(
+                &CommonArgs {
+                    from: from.map(Path::to_path_buf),
+                    manifest: manifest.map(str::to_owned),
+                    ..Default::default()
+                },
+                "stable.json",
+            )
+        };
+
+        assert_eq!(resolve(None, None).unwrap(), Source::Remote);
+        assert_eq!(
+            resolve(Some(tmp.path()), None).unwrap(),
+            Source::Manifest(tmp.path().join("stable.json"))
+        );
+        assert_eq!(
+            resolve(Some(&archive), None).unwrap(),
+            Source::Archive(archive.clone())
+        );
+        assert!(resolve(Some(&tmp.path().join("missing")), None).is_err());
+
+        assert_eq!(
+            resolve(None, Some("file:///mnt/maa/v5.2.0.json")).unwrap(),
+            Source::Manifest(PathBuf::from("/mnt/maa/v5.2.0.json"))
+        );
+        assert_eq!(
+            resolve(None, Some("mirror/stable.json")).unwrap(),
+            Source::Manifest(PathBuf::from("mirror/stable.json"))
+        );
+        assert!(resolve(None, Some("https://example.com/stable.json")).is_err());
+    }
+
+    #[test]
+    fn test_checksum_verifier() {
+        use maa_installer::verify::Verifier;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("MAA-v5.2.0-linux-x86_64.tar.gz");
+        std::fs::write(&archive, "hello").unwrap();
+
+        assert!(checksum_verifier(&archive).unwrap().is_none());
+
+        let checksum_file = tmp.path().join("MAA-v5.2.0-linux-x86_64.tar.gz.sha256");
+        std::fs::write(
+            &checksum_file,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  \
+             MAA-v5.2.0-linux-x86_64.tar.gz\n",
+        )
+        .unwrap();
+        let mut verifier = checksum_verifier(&archive).unwrap().unwrap();
+        verifier.verify_file(&archive).unwrap();
+
+        std::fs::write(&archive, "world").unwrap();
+        let mut verifier = checksum_verifier(&archive).unwrap().unwrap();
+        assert!(verifier.verify_file(&archive).is_err());
+
+        std::fs::write(&checksum_file, "not a hash").unwrap();
+        assert!(checksum_verifier(&archive).is_err());
+    }
+
     mod asset_name_tests {
         use super::*;
 
@@ -375,6 +551,21 @@ mod tests {
         assert_eq!(MANIFEST.0.details.assets.len(), 5);
     }
 
+    #[test]
+    fn test_pinned_manifest() {
+        let open = || {
+            std::fs::File::open(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/fixtures/version/core_version.json"
+            ))
+            .unwrap()
+        };
+
+        assert!(CoreManifest::from_reader_pinned(open(), None).is_ok());
+        assert!(CoreManifest::from_reader_pinned(open(), Some(&Version::new(4, 26, 1))).is_ok());
+        assert!(CoreManifest::from_reader_pinned(open(), Some(&Version::new(4, 26, 0))).is_err());
+    }
+
     mod core_asset {
         use super::*;
 
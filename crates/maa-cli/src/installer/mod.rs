@@ -2,6 +2,27 @@
 pub mod maa_cli;
 #[cfg(feature = "core_installer")]
 pub mod maa_core;
+#[cfg(feature = "core_installer")]
+pub mod rollback;
 
 pub mod hot_update;
 pub mod resource;
+
+/// Trusted public key to verify signatures of packages, and whether signatures are required
+#[cfg(feature = "__installer")]
+fn signature_key() -> anyhow::Result<Option<(maa_installer::verify::signature::PublicKey, bool)>> {
+    use anyhow::{Context, bail};
+
+    let config = crate::config::cli::CLI_CONFIG.signature_config();
+    match config.public_key() {
+        Some(key) => {
+            let key = maa_installer::verify::signature::PublicKey::from_base64(key)
+                .context("Invalid public key to verify signatures")?;
+            Ok(Some((key, config.required())))
+        }
+        None if config.required() => {
+            bail!("Signatures of packages are required but no public key is configured")
+        }
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,361 @@
+//! Snapshots of MaaCore replaced by installation, used to roll back to previous versions.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use maa_dirs::{self, Ensure};
+use semver::Version;
+
+use crate::{config::cli::maa_core::Components, state::CORE_VERSION};
+
+/// Number of snapshots to keep, older versions are removed
+const KEEP_SNAPSHOTS: usize = 3;
+
+/// Names of directories in a snapshot, the same as the installed ones
+const LIBRARY: &str = "lib";
+const RESOURCE: &str = "resource";
+
+/// Snapshots of MaaCore, each in a directory named by its version
+struct Snapshots {
+    root: PathBuf,
+}
+
+impl Snapshots {
+    fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Versions of snapshots, newest first
+    fn versions(&self) -> Result<Vec<Version>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if let Some(version) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| Version::parse(name).ok())
+            {
+                versions.push(version);
+            }
+        }
+        versions.sort_unstable_by(|a, b| b.cmp(a));
+
+        Ok(versions)
+    }
+
+    /// Move given directories into the snapshot of given version
+    ///
+    /// An existing snapshot of the same version is replaced. If a directory can not be moved,
+    /// directories already moved are moved back, so they are either all kept or all installed.
+    fn take(&self, version: &Version, dirs: &[(&str, &Path)]) -> Result<()> {
+        let dir = self.root.join(version.to_string());
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        dir.as_path().ensure()?;
+
+        let mut moved: Vec<(PathBuf, &Path)> = Vec::new();
+        for (name, src) in dirs {
+            if !src.exists() {
+                continue;
+            }
+            let dest = dir.join(name);
+            if let Err(e) = fs::rename(src, &dest) {
+                for (dest, src) in moved.into_iter().rev() {
+                    if let Err(e) = fs::rename(&dest, src) {
+                        warn!("Failed to move {} back: {e}", src.display());
+                    }
+                }
+                let _ = fs::remove_dir(&dir);
+                return Err(e)
+                    .with_context(|| format!("Failed to move {} to snapshot", src.display()));
+            }
+            moved.push((dest, *src));
+        }
+
+        Ok(())
+    }
+
+    /// Move directories in the snapshot of given version back, the snapshot is removed
+    ///
+    /// Directories not in the snapshot are left untouched.
+    fn restore(&self, version: &Version, dirs: &[(&str, &Path)]) -> Result<()> {
+        let dir = self.root.join(version.to_string());
+        if !dir.exists() {
+            bail!("No snapshot of MaaCore v{version}");
+        }
+
+        for (name, dest) in dirs {
+            let src = dir.join(name);
+            if !src.exists() {
+                continue;
+            }
+            if dest.exists() {
+                fs::remove_dir_all(dest)?;
+            }
+            fs::rename(&src, dest)
+                .with_context(|| format!("Failed to restore {}", dest.display()))?;
+        }
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    /// Remove snapshots except the newest ones
+    fn prune(&self, keep: usize) -> Result<()> {
+        for version in self.versions()?.iter().skip(keep) {
+            fs::remove_dir_all(self.root.join(version.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+fn snapshots() -> Snapshots {
+    Snapshots::new(join!(maa_dirs::data(), "snapshots", "core"))
+}
+
+fn installed_dirs(components: &Components) -> Vec<(&'static str, &'static Path)> {
+    let mut dirs = Vec::new();
+    if components.library {
+        dirs.push((LIBRARY, maa_dirs::library()));
+    }
+    if components.resource {
+        dirs.push((RESOURCE, maa_dirs::resource()));
+    }
+    dirs
+}
+
+/// Versions of MaaCore that can be rolled back to, newest first
+pub fn versions() -> Result<Vec<Version>> {
+    snapshots().versions()
+}
+
+/// Keep components of the installed MaaCore before they are replaced, returning whether kept
+///
+/// Failures are only logged, since they should not block the installation.
+pub fn snapshot(components: &Components) -> bool {
+    let Some(version) = CORE_VERSION.as_ref() else {
+        return false;
+    };
+
+    let snapshots = snapshots();
+    if let Err(e) = snapshots.take(version, &installed_dirs(components)) {
+        warn!("Failed to keep MaaCore v{version} for rollback: {e:#}");
+        return false;
+    }
+    info!("Kept MaaCore v{version}, use `maa rollback core` to restore it");
+
+    // The snapshot is kept anyway, old ones are removed next time
+    if let Err(e) = snapshots.prune(KEEP_SNAPSHOTS) {
+        warn!("Failed to remove old snapshots of MaaCore: {e:#}");
+    }
+
+    true
+}
+
+/// Restore components kept by [`snapshot`] after the installation failed
+///
+/// Partially installed components are replaced, failures are only logged.
+pub fn restore_snapshot(components: &Components) {
+    let Some(version) = CORE_VERSION.as_ref() else {
+        return;
+    };
+
+    match snapshots().restore(version, &installed_dirs(components)) {
+        Ok(()) => info!("Restored MaaCore v{version}"),
+        Err(e) => warn!(
+            "Failed to restore MaaCore v{version}: {e:#}, use `maa rollback core {version}` to retry"
+        ),
+    }
+}
+
+/// Pick the version to roll back to, the newest version older than the current one by default
+fn select(
+    versions: &[Version],
+    current: Option<&Version>,
+    target: Option<&Version>,
+) -> Result<Version> {
+    if let Some(target) = target {
+        return versions
+            .iter()
+            .find(|v| *v == target)
+            .cloned()
+            .with_context(|| format!("No snapshot of MaaCore v{target}"));
+    }
+
+    versions
+        .iter()
+        .find(|v| current.is_none_or(|current| *v < current))
+        .cloned()
+        .context("No previous version of MaaCore to roll back to")
+}
+
+/// Restore MaaCore of given version or the previous version
+///
+/// The current version is kept as a snapshot, so it can be restored in the same way.
+pub fn rollback(target: Option<&Version>) -> Result<()> {
+    let lib_dir = maa_dirs::library();
+    if let Some(dir) = maa_dirs::find_library()
+        && dir != lib_dir
+    {
+        bail!(
+            "MaaCore found at {} but not installed by maa, aborting",
+            dir.display()
+        )
+    }
+
+    let snapshots = snapshots();
+    let current = CORE_VERSION.as_ref();
+    let version = select(&snapshots.versions()?, current, target)?;
+    if current == Some(&version) {
+        bail!("MaaCore v{version} is already installed");
+    }
+
+    let dirs = installed_dirs(&Components::default());
+    if let Some(current) = current {
+        // Only components in the snapshot are replaced, others are left installed
+        let root = snapshots.root.join(version.to_string());
+        let kept: Vec<_> = dirs
+            .iter()
+            .copied()
+            .filter(|(name, _)| root.join(name).exists())
+            .collect();
+        snapshots.take(current, &kept)?;
+    }
+    snapshots.restore(&version, &dirs)?;
+    snapshots.prune(KEEP_SNAPSHOTS)?;
+
+    info!("Rolled back MaaCore to v{version}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+
+    fn version(s: &str) -> Version {
+        Version::parse(s).unwrap()
+    }
+
+    #[test]
+    fn select_version() {
+        let versions = [version("5.2.0"), version("5.1.0"), version("5.0.0")];
+
+        assert_eq!(
+            select(&versions, Some(&version("5.3.0")), None).unwrap(),
+            version("5.2.0")
+        );
+        assert_eq!(
+            select(&versions, Some(&version("5.1.0")), None).unwrap(),
+            version("5.0.0")
+        );
+        assert_eq!(select(&versions, None, None).unwrap(), version("5.2.0"));
+        assert!(select(&versions, Some(&version("5.0.0")), None).is_err());
+
+        assert_eq!(
+            select(&versions, Some(&version("5.1.0")), Some(&version("5.2.0"))).unwrap(),
+            version("5.2.0")
+        );
+        assert!(select(&versions, None, Some(&version("4.0.0"))).is_err());
+    }
+
+    #[test]
+    fn take_and_restore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let lib_dir = tmp.path().join("lib");
+        let resource_dir = tmp.path().join("resource");
+        let snapshots = Snapshots::new(tmp.path().join("snapshots"));
+
+        let install = |tag: &str| {
+            lib_dir.as_path().ensure_clean().unwrap();
+            resource_dir.as_path().ensure_clean().unwrap();
+            fs::write(lib_dir.join("libMaaCore.so"), tag).unwrap();
+            fs::write(resource_dir.join("config.json"), tag).unwrap();
+        };
+        let installed = || {
+            (
+                fs::read_to_string(lib_dir.join("libMaaCore.so")).unwrap(),
+                fs::read_to_string(resource_dir.join("config.json")).unwrap(),
+            )
+        };
+        let dirs = [
+            (LIBRARY, lib_dir.as_path()),
+            (RESOURCE, resource_dir.as_path()),
+        ];
+
+        assert!(snapshots.versions().unwrap().is_empty());
+
+        for minor in 0..5 {
+            install(&format!("5.{minor}.0"));
+            snapshots.take(&Version::new(5, minor, 0), &dirs).unwrap();
+            snapshots.prune(KEEP_SNAPSHOTS).unwrap();
+            assert!(!lib_dir.exists());
+        }
+        // only the newest snapshots are kept
+        assert_eq!(snapshots.versions().unwrap(), [
+            version("5.4.0"),
+            version("5.3.0"),
+            version("5.2.0")
+        ]);
+
+        install("5.5.0");
+        snapshots.restore(&version("5.3.0"), &dirs).unwrap();
+        assert_eq!(installed(), ("5.3.0".to_owned(), "5.3.0".to_owned()));
+        assert_eq!(snapshots.versions().unwrap(), [
+            version("5.4.0"),
+            version("5.2.0")
+        ]);
+        assert!(snapshots.restore(&version("5.3.0"), &dirs).is_err());
+
+        // snapshots with only some of the components
+        snapshots
+            .take(&version("5.3.0"), &[(RESOURCE, resource_dir.as_path())])
+            .unwrap();
+        assert!(lib_dir.exists());
+        install("5.5.0");
+        snapshots.restore(&version("5.3.0"), &dirs).unwrap();
+        assert_eq!(installed(), ("5.5.0".to_owned(), "5.3.0".to_owned()));
+
+        // a failed installation leaves partial components, which are replaced by the snapshot
+        snapshots.take(&version("5.5.0"), &dirs).unwrap();
+        lib_dir.as_path().ensure_clean().unwrap();
+        fs::write(lib_dir.join("partial"), "").unwrap();
+        snapshots.restore(&version("5.5.0"), &dirs).unwrap();
+        assert_eq!(installed(), ("5.5.0".to_owned(), "5.3.0".to_owned()));
+        assert!(!lib_dir.join("partial").exists());
+    }
+
+    #[test]
+    fn take_moves_back_on_failure() {
+        let tmp = tempfile::tempdir().unwrap();
+        let lib_dir = tmp.path().join("lib");
+        let other_dir = tmp.path().join("other");
+        let snapshots = Snapshots::new(tmp.path().join("snapshots"));
+
+        for dir in [&lib_dir, &other_dir] {
+            dir.as_path().ensure_clean().unwrap();
+            fs::write(dir.join("file"), "").unwrap();
+        }
+
+        // the second directory can not be moved onto the non-empty first one
+        let dirs = [(LIBRARY, lib_dir.as_path()), (LIBRARY, other_dir.as_path())];
+        assert!(snapshots.take(&version("5.0.0"), &dirs).is_err());
+        assert!(lib_dir.join("file").exists());
+        assert!(other_dir.join("file").exists());
+        assert!(snapshots.versions().unwrap().is_empty());
+    }
+}
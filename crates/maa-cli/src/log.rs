@@ -1,4 +1,34 @@
-use std::{io::Write, path::PathBuf};
+use std::{cell::RefCell, io::Write, path::PathBuf, sync::Arc};
+
+thread_local! {
+    /// Name of the instance that the current thread works for, see [`with_instance`]
+    static INSTANCE: RefCell<Option<Arc<str>>> = const { RefCell::new(None) };
+}
+
+/// Run a closure with all logs of current thread prefixed by the name of given instance
+///
+/// This is used to distinguish logs of instances running in parallel.
+/// The previous instance name of current thread is restored after the closure returns.
+pub fn with_instance<T>(name: Option<&Arc<str>>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Arc<str>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            INSTANCE.set(self.0.take());
+        }
+    }
+
+    let _restore = Restore(INSTANCE.replace(name.cloned()));
+    f()
+}
+
+fn instance_prefix() -> String {
+    INSTANCE.with_borrow(|name| {
+        name.as_deref()
+            .map(|name| format!("[{name}] "))
+            .unwrap_or_default()
+    })
+}
 
 #[derive(clap::Args)]
 pub struct Args {
@@ -155,16 +185,17 @@ impl LogFormatter {
             LogFormatter::Prefixed => {
                 writeln!(
                     buf,
-                    "[{} {}{:<5}{}] {}",
+                    "[{} {}{:<5}{}] {}{}",
                     chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
                     buf.default_level_style(record.level()),
                     record.level(),
                     env_logger::fmt::style::Reset,
+                    instance_prefix(),
                     record.args()
                 )
             }
             LogFormatter::Plain => {
-                writeln!(buf, "{}", record.args())
+                writeln!(buf, "{}{}", instance_prefix(), record.args())
             }
         }
     }
@@ -303,4 +334,25 @@ mod tests {
             assert_eq!(LogPrefix::Auto.formatter(false), LogFormatter::Plain);
         }
     }
+
+    #[test]
+    fn instance() {
+        assert_eq!(instance_prefix(), "");
+
+        let outer: Arc<str> = Arc::from("emu1");
+        let inner: Arc<str> = Arc::from("emu2");
+        with_instance(Some(&outer), || {
+            assert_eq!(instance_prefix(), "[emu1] ");
+            with_instance(Some(&inner), || assert_eq!(instance_prefix(), "[emu2] "));
+            with_instance(None, || assert_eq!(instance_prefix(), ""));
+            assert_eq!(instance_prefix(), "[emu1] ");
+        });
+
+        assert_eq!(instance_prefix(), "");
+        std::thread::spawn(move || {
+            with_instance(Some(&outer), || assert_eq!(instance_prefix(), "[emu1] "))
+        })
+        .join()
+        .unwrap();
+    }
 }
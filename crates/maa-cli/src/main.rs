@@ -12,7 +12,11 @@ mod activity;
 mod cleanup;
 mod command;
 mod config;
+mod depot;
+mod history;
 mod installer;
+mod notify;
+mod operbox;
 mod run;
 
 use anyhow::{Context, Result};
@@ -42,6 +46,10 @@ fn main() -> Result<()> {
             installer::hot_update::update()?;
             installer::resource::update(false)?;
         }
+        #[cfg(feature = "core_installer")]
+        Command::Rollback(command::RollbackCommand::Core { version }) => {
+            installer::rollback::rollback(version.as_ref())?
+        }
         #[cfg(feature = "cli_installer")]
         Command::SelfC(self_c) => match self_c {
             command::SelfCommand::Update { common } => installer::maa_cli::update(&common)?,
@@ -70,6 +78,7 @@ fn main() -> Result<()> {
             Dir::Config => println!("{}", dirs::config().display()),
             Dir::Cache => println!("{}", dirs::cache().display()),
             Dir::Log => println!("{}", dirs::log().display()),
+            Dir::State => println!("{}", dirs::state().display()),
         },
         Command::Version { component } => {
             match component {
@@ -79,21 +88,72 @@ fn main() -> Result<()> {
                 _ => {}
             }
             match component {
-                Component::All | Component::MaaCore => println!(
-                    "MaaCore {}",
-                    state::CORE_VERSION_STR
-                        .as_deref()
-                        .context("Failed to get MaaCore version")?
-                ),
+                Component::All | Component::MaaCore => {
+                    let version = state::CORE_VERSION_STR.as_deref();
+                    if let Some(version) = version {
+                        println!("MaaCore {version}");
+                    }
+                    #[cfg(feature = "core_installer")]
+                    {
+                        let versions = installer::rollback::versions()?;
+                        if !versions.is_empty() {
+                            let versions: Vec<_> =
+                                versions.iter().map(|v| format!("v{v}")).collect();
+                            println!("MaaCore rollback available: {}", versions.join(", "));
+                        }
+                    }
+                    version.context("Failed to get MaaCore version")?;
+                }
                 _ => {}
             }
         }
-        Command::Run { task, common } => run::run_custom(task, common)?,
+        Command::Run {
+            task,
+            fleet,
+            common,
+        } => match task {
+            Some(task) if fleet.is_empty() => run::run_custom(&task, common)?,
+            task => run::run_fleet(fleet, task.as_deref(), common)?,
+        },
+        Command::Daemon { schedule, common } => run::daemon(schedule.as_deref(), common)?,
+        #[cfg(feature = "server")]
+        Command::Serve {
+            listen,
+            client,
+            common,
+        } => run::serve(&listen, client, common)?,
         Command::StartUp { params, common } => run::run_preset(params, common)?,
         Command::CloseDown { params, common } => run::run_preset(params, common)?,
         Command::Fight { params, common } => run::run_preset(params, common)?,
-        Command::Roguelike { params, common } => run::run_preset(params, common)?,
-        Command::Copilot { params, common } => run::run_preset(params, common)?,
+        Command::Recruit { params, common } => run::run_preset(params, common)?,
+        Command::Infrast { params, common } => run::run_preset(params, common)?,
+        Command::Daily { params, common } => run::run_daily(params, common)?,
+        Command::Mall { params, common } => run::run_preset(params, common)?,
+        Command::Award { params, common } => run::run_preset(params, common)?,
+        Command::Depot { params, common } => {
+            let export = params.export(common.profile.clone());
+            run::run_preset_and_export(params, common, export)?
+        }
+        Command::OperBox { params, common } => {
+            let export = params.export(common.profile.clone());
+            run::run_preset_and_export(params, common, export)?
+        }
+        Command::Roguelike { params, common } => {
+            let params = params.with_profile(common.profile.clone());
+            run::run_preset(params, common)?
+        }
+        Command::Copilot {
+            command: Some(command),
+            ..
+        } => run::preset::browse_copilot(command)?,
+        Command::Copilot {
+            command: None,
+            params,
+            common,
+        } => {
+            let params = params.with_profile(common.profile.clone());
+            run::run_preset(params, common)?
+        }
         Command::SSSCopilot { params, common } => run::run_preset(params, common)?,
         Command::ParadoxCopilot { params, common } => run::run_preset(params, common)?,
         Command::Reclamation { params, common } => run::run_preset(params, common)?,
@@ -113,7 +173,22 @@ fn main() -> Result<()> {
                 )
             );
         }
+        Command::Next {
+            cron,
+            calendar,
+            count,
+            timezone,
+        } => print!(
+            "{}",
+            config::task::describe_next(
+                cron.as_ref(),
+                calendar.as_deref(),
+                count,
+                timezone.unwrap_or_default()
+            )?
+        ),
         Command::Cleanup { targets } => cleanup::cleanup(&targets)?,
+        Command::History(command) => history::history(command)?,
         Command::List => {
             let task_dir = dirs::config().join("tasks");
             if !task_dir.exists() {
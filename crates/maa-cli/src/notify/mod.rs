@@ -0,0 +1,392 @@
+//! Notifications sent when a run is finished
+
+mod smtp;
+
+use std::{
+    io::Write,
+    process::{self, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{debug, warn};
+use maa_types::TaskType;
+use serde::Serialize;
+
+use crate::{
+    config::cli::{
+        CLI_CONFIG,
+        notify::{Command, Config, Event, Webhook},
+    },
+    history::{Record, Status},
+    state::AGENT,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Payload of a notification, which is the history record with the triggered events
+#[derive(Serialize)]
+struct Notification<'a> {
+    events: &'a [Event],
+    #[serde(flatten)]
+    record: &'a Record,
+}
+
+/// Send notifications of a finished run to all sinks interested in its events
+///
+/// Failures are only logged, so that a broken sink does not affect the run.
+pub fn notify(record: &Record, offline: bool) {
+    send_all(CLI_CONFIG.notify_config(), record, offline);
+}
+
+fn send_all(config: &Config, record: &Record, offline: bool) {
+    if config.is_empty() {
+        return;
+    }
+
+    let events = events(record, offline);
+    let notification = Notification {
+        events: &events,
+        record,
+    };
+    let json = match serde_json::to_string_pretty(&notification) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Failed to serialize notification: {e}");
+            return;
+        }
+    };
+
+    for sink in &config.webhook {
+        if sink.events.accepts_any(&events)
+            && let Err(e) = webhook(sink, &json)
+        {
+            warn!("Failed to send notification to {}: {e:#}", sink.url);
+        }
+    }
+
+    for sink in &config.command {
+        if sink.events.accepts_any(&events)
+            && let Err(e) = command(sink, &events, &json)
+        {
+            warn!(
+                "Failed to run notification command {:?}: {e:#}",
+                sink.command
+            );
+        }
+    }
+
+    for sink in &config.smtp {
+        if sink.events.accepts_any(&events)
+            && let Err(e) = smtp::send(
+                sink,
+                &subject(record, &events),
+                &text(record, &events, &json),
+            )
+        {
+            warn!("Failed to send notification email by {}: {e:#}", sink.host);
+        }
+    }
+}
+
+fn events(record: &Record, offline: bool) -> Vec<Event> {
+    let mut events = vec![match record.status {
+        _ if offline => Event::Offline,
+        Status::Succeeded => Event::Complete,
+        Status::Failed => Event::Error,
+    }];
+
+    let recruit_details = || {
+        record
+            .tasks
+            .iter()
+            .filter(|task| task.task_type == TaskType::Recruit)
+            .map(|task| &task.detail)
+    };
+
+    let recruit_6star = recruit_details()
+        .filter_map(|detail| detail.get("records")?.as_array())
+        .flatten()
+        .any(|r| r.get("level").and_then(|l| l.as_u64()) >= Some(6));
+    if recruit_6star {
+        events.push(Event::Recruit6Star);
+    }
+
+    let recruit_rare_tag = recruit_details()
+        .filter_map(|detail| detail.get("rare_tags")?.as_array())
+        .any(|tags| !tags.is_empty());
+    if recruit_rare_tag {
+        events.push(Event::RecruitRareTag);
+    }
+
+    events
+}
+
+fn webhook(sink: &Webhook, json: &str) -> Result<()> {
+    let mut request = AGENT
+        .post(&sink.url)
+        .config()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .header("Content-Type", "application/json");
+    for (name, value) in &sink.headers {
+        if let Some(value) = value.get_with_desc("webhook header")? {
+            request = request.header(name, value.as_ref());
+        }
+    }
+
+    request.send(json)?;
+    debug!("Notification sent to {}", sink.url);
+
+    Ok(())
+}
+
+fn command(sink: &Command, events: &[Event], json: &str) -> Result<()> {
+    let Some((program, args)) = sink.command.split_first() else {
+        bail!("command is empty");
+    };
+
+    let mut child = process::Command::new(program)
+        .args(args)
+        .env("MAA_NOTIFY_EVENTS", join_events(events))
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn command")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may exit without reading stdin, which is fine
+        match stdin.write_all(json.as_bytes()) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => {}
+        }
+    }
+
+    // Poll the command, since a hanging command should not block the run
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() > TIMEOUT {
+            child.kill()?;
+            child.wait()?;
+            bail!("command timed out after {}s", TIMEOUT.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    if !status.success() {
+        bail!("command exited with {status}");
+    }
+
+    Ok(())
+}
+
+fn join_events(events: &[Event]) -> String {
+    events
+        .iter()
+        .map(|event| event.to_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn subject(record: &Record, events: &[Event]) -> String {
+    format!(
+        "maa-cli: {} {}",
+        record.task.as_deref().unwrap_or("tasks"),
+        join_events(events)
+    )
+}
+
+fn text(record: &Record, events: &[Event], json: &str) -> String {
+    let mut text = format!(
+        "Events: {}\nTask: {}\nProfile: {}\nStart: {}\nEnd: {}\n",
+        join_events(events),
+        record.task.as_deref().unwrap_or("-"),
+        record.profile.as_deref().unwrap_or("default"),
+        record.start.format("%Y-%m-%d %H:%M:%S"),
+        record.end.format("%Y-%m-%d %H:%M:%S"),
+    );
+    if let Some(error) = record.error.as_deref() {
+        text.push_str(&format!("Error: {error}\n"));
+    }
+    text.push('\n');
+    text.push_str(json);
+    text
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use chrono::{Local, TimeZone};
+    use serde_json::json;
+
+    use super::*;
+    use crate::history::TaskRecord;
+
+    fn record(status: Status, recruit_level: u64) -> Record {
+        Record {
+            start: Local.with_ymd_and_hms(2024, 1, 1, 4, 0, 0).unwrap(),
+            end: Local.with_ymd_and_hms(2024, 1, 1, 5, 0, 0).unwrap(),
+            profile: None,
+            task: Some("daily".to_owned()),
+            job: None,
+            status,
+            error: matches!(status, Status::Failed).then(|| "Interrupted by user!".to_owned()),
+            tasks: vec![TaskRecord {
+                id: 1,
+                name: None,
+                task_type: TaskType::Recruit,
+                start_time: None,
+                end_time: None,
+                reason: "Completed".to_owned(),
+                detail: json!({
+                    "refresh_times": null,
+                    "recruit_times": 1,
+                    "records": [{ "level": recruit_level, "tags": ["高级资深干员"], "state": null }],
+                }),
+            }],
+        }
+    }
+
+    #[test]
+    fn events_of_record() {
+        use Event::*;
+
+        assert_eq!(events(&record(Status::Succeeded, 4), false), [Complete]);
+        assert_eq!(events(&record(Status::Failed, 4), false), [Error]);
+        assert_eq!(events(&record(Status::Succeeded, 4), true), [Offline]);
+        assert_eq!(events(&record(Status::Succeeded, 6), false), [
+            Complete,
+            Recruit6Star
+        ]);
+
+        let mut rare = record(Status::Succeeded, 4);
+        rare.tasks[0].detail["rare_tags"] = json!(["资深干员"]);
+        assert_eq!(events(&rare, false), [Complete, RecruitRareTag]);
+    }
+
+    #[test]
+    fn format() {
+        let record = record(Status::Failed, 6);
+        let events = events(&record, false);
+        assert_eq!(join_events(&events), "error,recruit_6star");
+        assert_eq!(
+            subject(&record, &events),
+            "maa-cli: daily error,recruit_6star"
+        );
+        assert_eq!(
+            text(&record, &events, "{}"),
+            "Events: error,recruit_6star\nTask: daily\nProfile: default\n\
+             Start: 2024-01-01 04:00:00\nEnd: 2024-01-01 05:00:00\n\
+             Error: Interrupted by user!\n\n{}"
+        );
+
+        let value = serde_json::to_value(Notification {
+            events: &events,
+            record: &record,
+        })
+        .unwrap();
+        assert_eq!(value["events"], json!(["error", "recruit_6star"]));
+        assert_eq!(value["task"], "daily");
+        assert_eq!(value["status"], "failed");
+        assert_eq!(value["tasks"][0]["type"], "Recruit");
+    }
+
+    #[test]
+    fn webhook_sink() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+
+        let config: Config = serde_json::from_value(json!({
+            "webhook": [
+                { "url": url, "headers": { "Authorization": "Bearer token" } },
+                { "url": url, "on_complete": false },
+            ],
+        }))
+        .unwrap();
+
+        let handle = std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let auth = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("Authorization"))
+                .map(|h| h.value.to_string());
+            request.respond(tiny_http::Response::empty(204)).unwrap();
+            // the second sink is not interested in complete event
+            assert!(
+                server
+                    .recv_timeout(Duration::from_millis(200))
+                    .unwrap()
+                    .is_none()
+            );
+            (auth, body)
+        });
+
+        send_all(&config, &record(Status::Succeeded, 4), false);
+
+        let (auth, body) = handle.join().unwrap();
+        assert_eq!(auth.as_deref(), Some("Bearer token"));
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["events"], json!(["complete"]));
+        assert_eq!(body["task"], "daily");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_sink() {
+        use maa_dirs::Ensure;
+
+        let dir = std::env::temp_dir().join("maa-test-notify-command");
+        dir.ensure_clean().unwrap();
+        let output = dir.join("output.json");
+        let events_file = dir.join("events");
+
+        let config: Config = serde_json::from_value(json!({
+            "command": [{
+                "command": [
+                    "sh",
+                    "-c",
+                    format!(
+                        "cat > {} && printf %s \"$MAA_NOTIFY_EVENTS\" > {}",
+                        output.display(),
+                        events_file.display()
+                    ),
+                ],
+            }],
+        }))
+        .unwrap();
+
+        send_all(&config, &record(Status::Failed, 6), false);
+
+        let body: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(body["events"], json!(["error", "recruit_6star"]));
+        assert_eq!(body["error"], "Interrupted by user!");
+        assert_eq!(
+            std::fs::read_to_string(&events_file).unwrap(),
+            "error,recruit_6star"
+        );
+
+        assert!(
+            command(
+                &serde_json::from_value(json!({ "command": ["false"] })).unwrap(),
+                &[Event::Complete],
+                "{}"
+            )
+            .is_err()
+        );
+        assert!(
+            command(
+                &serde_json::from_value(json!({ "command": [] })).unwrap(),
+                &[Event::Complete],
+                "{}"
+            )
+            .is_err()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
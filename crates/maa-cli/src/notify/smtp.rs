@@ -0,0 +1,324 @@
+//! A minimal SMTP client to send plain text emails
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use log::debug;
+use rustls::{ClientConfig, ClientConnection, StreamOwned, pki_types::ServerName};
+use rustls_platform_verifier::ConfigVerifierExt;
+
+use super::TIMEOUT;
+use crate::config::cli::notify::{Security, Smtp};
+
+/// Send an email with given subject and plain text body
+pub fn send(config: &Smtp, subject: &str, body: &str) -> Result<()> {
+    // Credentials must never be sent in plain text
+    if config.username.is_some() && matches!(config.security, Security::None) {
+        bail!(
+            "SMTP credentials of {} can only be sent over TLS, set `security` to \"tls\" or \"starttls\"",
+            config.host
+        );
+    }
+
+    let port = config.port();
+    let tcp = connect(&config.host, port)
+        .with_context(|| format!("Failed to connect to {}:{port}", config.host))?;
+    tcp.set_read_timeout(Some(TIMEOUT))?;
+    tcp.set_write_timeout(Some(TIMEOUT))?;
+
+    match config.security {
+        Security::Tls => {
+            let mut conn = Connection::new(tls(tcp, &config.host)?);
+            conn.expect(220)?;
+            deliver(conn, config, subject, body)
+        }
+        Security::StartTls => {
+            let mut conn = Connection::new(tcp);
+            conn.expect(220)?;
+            conn.ehlo()?;
+            conn.command("STARTTLS", 220)?;
+            deliver(
+                Connection::new(tls(conn.into_inner(), &config.host)?),
+                config,
+                subject,
+                body,
+            )
+        }
+        Security::None => {
+            let mut conn = Connection::new(tcp);
+            conn.expect(220)?;
+            deliver(conn, config, subject, body)
+        }
+    }
+}
+
+/// Connect to the first reachable address of the host, each attempt is limited by the timeout
+fn connect(host: &str, port: u16) -> Result<TcpStream> {
+    let mut last_error = None;
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(tcp) => return Ok(tcp),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    match last_error {
+        Some(e) => Err(e.into()),
+        None => bail!("No address of {host} is resolved"),
+    }
+}
+
+fn tls(tcp: TcpStream, host: &str) -> Result<StreamOwned<ClientConnection, TcpStream>> {
+    let config = ClientConfig::with_platform_verifier().context("Failed to setup TLS")?;
+    let name = ServerName::try_from(host.to_owned())
+        .with_context(|| format!("Invalid server name {host}"))?;
+    let conn = ClientConnection::new(Arc::new(config), name)?;
+    Ok(StreamOwned::new(conn, tcp))
+}
+
+/// Run a mail transaction on a greeted connection
+fn deliver<S: Read + Write>(
+    mut conn: Connection<S>,
+    config: &Smtp,
+    subject: &str,
+    body: &str,
+) -> Result<()> {
+    conn.ehlo()?;
+
+    if let Some(username) = config.username.as_deref() {
+        let password = config
+            .password
+            .get_with_desc("SMTP password")?
+            .unwrap_or_default();
+        let credentials = BASE64.encode(format!("\0{username}\0{password}"));
+        conn.command(&format!("AUTH PLAIN {credentials}"), 235)
+            .context("Failed to login")?;
+    }
+
+    conn.command(&format!("MAIL FROM:<{}>", config.from), 250)?;
+    for to in &config.to {
+        conn.command(&format!("RCPT TO:<{to}>"), 250)?;
+    }
+    conn.command("DATA", 354)?;
+    conn.send(&message(config, subject, body))?;
+    conn.expect(250)?;
+    conn.command("QUIT", 221)?;
+
+    Ok(())
+}
+
+/// Format the message with headers, ending with the terminating line of `DATA`
+fn message(config: &Smtp, subject: &str, body: &str) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        config.from,
+        config.to.join(", "),
+        encode_header(subject),
+        chrono::Local::now().to_rfc2822(),
+    );
+    for line in body.lines() {
+        // lines starting with a dot must be escaped, otherwise `.` alone ends the message
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push('.');
+    message
+}
+
+/// Encode non-ASCII header values as described in RFC 2047
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_owned()
+    } else {
+        format!("=?utf-8?B?{}?=", BASE64.encode(value))
+    }
+}
+
+struct Connection<S> {
+    reader: BufReader<S>,
+}
+
+impl<S: Read + Write> Connection<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> S {
+        self.reader.into_inner()
+    }
+
+    fn send(&mut self, line: &str) -> Result<()> {
+        let stream = self.reader.get_mut();
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Read a reply and check its code, the text of all lines is returned
+    fn expect(&mut self, code: u16) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                bail!("Connection closed by SMTP server");
+            }
+            let line = line.trim_end();
+            debug!("SMTP: {line}");
+
+            let (reply_code, rest) = line.split_at_checked(3).unwrap_or((line, ""));
+            if reply_code != code.to_string() {
+                bail!("Unexpected reply from SMTP server: {line}");
+            }
+            text.push_str(rest.get(1..).unwrap_or_default());
+            text.push('\n');
+
+            // `250-...` is followed by more lines, while `250 ...` is the last line
+            if !rest.starts_with('-') {
+                return Ok(text);
+            }
+        }
+    }
+
+    fn command(&mut self, command: &str, code: u16) -> Result<String> {
+        self.send(command)?;
+        self.expect(code)
+    }
+
+    fn ehlo(&mut self) -> Result<String> {
+        self.command("EHLO localhost", 250)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::config::cli::secret::Secret;
+
+    #[test]
+    fn encode() {
+        assert_eq!(encode_header("maa-cli: error"), "maa-cli: error");
+        assert_eq!(encode_header("公招"), "=?utf-8?B?5YWs5oub?=");
+    }
+
+    #[test]
+    fn send_mail() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // A fake SMTP server records all received lines
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut received = Vec::new();
+            let mut in_data = false;
+
+            writer.write_all(b"220 localhost ready\r\n").unwrap();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end_matches("\r\n").to_owned();
+                let reply: &[u8] = if in_data {
+                    if line == "." {
+                        in_data = false;
+                        b"250 queued\r\n"
+                    } else {
+                        b""
+                    }
+                } else if line.starts_with("EHLO") {
+                    b"250-localhost\r\n250 AUTH PLAIN\r\n"
+                } else if line.starts_with("AUTH") {
+                    b"235 ok\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    received.push(line);
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).unwrap();
+                received.push(line);
+            }
+            received
+        });
+
+        let config: Smtp = serde_json::from_value(serde_json::json!({
+            "host": "127.0.0.1",
+            "port": port,
+            "security": "none",
+            "username": "user",
+            "password": "pass",
+            "from": "maa@example.com",
+            "to": ["a@example.com", "b@example.com"],
+        }))
+        .unwrap();
+        assert_eq!(config.password, Secret::Plain("pass".to_owned()));
+
+        // Credentials are rejected without TLS, so deliver on the plain connection directly
+        let mut conn = Connection::new(TcpStream::connect(("127.0.0.1", port)).unwrap());
+        conn.expect(220).unwrap();
+        deliver(conn, &config, "maa-cli: complete", "line 1\n.line 2").unwrap();
+
+        let received = server.join().unwrap();
+        let position = |prefix: &str| received.iter().position(|l| l.starts_with(prefix));
+
+        assert_eq!(received[0], "EHLO localhost");
+        assert_eq!(
+            received[1],
+            format!("AUTH PLAIN {}", BASE64.encode("\0user\0pass"))
+        );
+        assert_eq!(received[2], "MAIL FROM:<maa@example.com>");
+        assert_eq!(received[3], "RCPT TO:<a@example.com>");
+        assert_eq!(received[4], "RCPT TO:<b@example.com>");
+        assert_eq!(received[5], "DATA");
+        assert!(position("Subject: maa-cli: complete").is_some());
+        assert!(position("To: a@example.com, b@example.com").is_some());
+        let body = position("line 1").unwrap();
+        assert_eq!(received[body + 1], "..line 2");
+        assert_eq!(received[body + 2], ".");
+        assert_eq!(received.last().unwrap(), "QUIT");
+    }
+
+    #[test]
+    fn reject_credentials_without_tls() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        listener.set_nonblocking(true).unwrap();
+
+        let config: Smtp = serde_json::from_value(serde_json::json!({
+            "host": "127.0.0.1",
+            "port": port,
+            "security": "none",
+            "username": "user",
+            "password": "pass",
+            "from": "maa@example.com",
+            "to": ["a@example.com"],
+        }))
+        .unwrap();
+
+        let err = send(&config, "maa-cli: complete", "body").unwrap_err();
+        assert!(err.to_string().contains("only be sent over TLS"));
+        // Nothing is sent, not even a connection is made
+        assert!(listener.accept().is_err());
+    }
+}
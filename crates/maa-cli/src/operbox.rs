@@ -0,0 +1,145 @@
+//! Operators recognized by the last OperBox task of each profile, saved to be exported and used
+//! to check copilot formations
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use maa_dirs::Ensure;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize)]
+pub struct OperBox {
+    /// When the operators were recognized
+    pub time: DateTime<Local>,
+    /// Owned operators
+    pub operators: Vec<Operator>,
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Serialize, Deserialize)]
+pub struct Operator {
+    pub id: String,
+    pub name: String,
+    /// Number of stars of the operator
+    pub rarity: i64,
+    pub elite: i64,
+    pub level: i64,
+    pub potential: i64,
+}
+
+/// Path of the operators saved for given profile, `None` for the default profile
+pub fn path(profile: Option<&str>) -> PathBuf {
+    crate::state::dir()
+        .join("operbox")
+        .join(format!("{}.json", profile.unwrap_or("default")))
+}
+
+impl OperBox {
+    /// Parse the details of a `OperBoxInfo` message, `None` if the recognition is not done
+    pub fn from_details(details: &Value) -> Option<Self> {
+        if !details.get("done")?.as_bool()? {
+            return None;
+        }
+
+        let operators = details
+            .get("own_opers")?
+            .as_array()?
+            .iter()
+            .filter_map(|oper| {
+                Some(Operator {
+                    id: oper.get("id")?.as_str()?.to_owned(),
+                    name: oper.get("name")?.as_str()?.to_owned(),
+                    rarity: oper.get("rarity")?.as_i64()?,
+                    elite: oper.get("elite")?.as_i64()?,
+                    level: oper.get("level")?.as_i64()?,
+                    potential: oper.get("potential")?.as_i64()?,
+                })
+            })
+            .collect();
+
+        Some(Self {
+            time: Local::now(),
+            operators,
+        })
+    }
+
+    /// Load the last saved operators of given profile, `None` if the operators have never been
+    /// recognized
+    pub fn load(profile: Option<&str>) -> Result<Option<Self>> {
+        let path = path(profile);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = std::fs::File::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let operbox = serde_json::from_reader(file)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        Ok(Some(operbox))
+    }
+
+    pub fn save(&self, profile: Option<&str>) -> Result<()> {
+        let path = path(profile);
+        if let Some(parent) = path.parent() {
+            parent.ensure()?;
+        }
+
+        crate::atomic_fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn from_details() {
+        let details = json!({
+            "done": true,
+            "all_oper": [
+                { "id": "char_002_amiya", "name": "阿米娅", "own": true, "rarity": 5 },
+                { "id": "char_003_kalts", "name": "凯尔希", "own": false, "rarity": 6 },
+            ],
+            "own_opers": [
+                {
+                    "id": "char_002_amiya",
+                    "name": "阿米娅",
+                    "own": true,
+                    "elite": 2,
+                    "level": 50,
+                    "potential": 6,
+                    "rarity": 5,
+                },
+                { "id": "broken" },
+            ],
+        });
+
+        let operbox = OperBox::from_details(&details).unwrap();
+        assert_eq!(operbox.operators, [Operator {
+            id: "char_002_amiya".to_owned(),
+            name: "阿米娅".to_owned(),
+            rarity: 5,
+            elite: 2,
+            level: 50,
+            potential: 6,
+        }]);
+
+        assert!(OperBox::from_details(&json!({ "done": false })).is_none());
+        assert!(OperBox::from_details(&json!({ "done": true })).is_none());
+    }
+
+    #[test]
+    fn path_per_profile() {
+        let dir = crate::state::dir().join("operbox");
+        assert_eq!(path(None), dir.join("default.json"));
+        assert_eq!(path(Some("alt")), dir.join("alt.json"));
+    }
+}
@@ -8,29 +8,63 @@ use log::{debug, error, info, trace, warn};
 use maa_core::Callback;
 use maa_types::{MessageKind, primitive::AsstTaskId};
 use serde_json::{Map, Value};
-use summary::{Facility, edit_current_task_detail, end_current_task, start_task};
-
-use crate::state::AGENT;
-
-pub static MAA_CORE_ERRORED: AtomicBool = AtomicBool::new(false);
+use summary::{Facility, SharedSummary};
+
+use crate::{depot::Depot, operbox::OperBox, state::AGENT};
+
+/// State of an assistant instance, shared between its callback and the runner
+#[derive(Default)]
+pub struct InstanceState {
+    pub summary: SharedSummary,
+    /// Set when any task chain failed
+    pub errored: AtomicBool,
+    /// Set when tasks should be stopped because the game went offline
+    /// and auto reconnect is disabled
+    pub offline_stop: AtomicBool,
+}
 
 fn json_pretty(value: &impl serde::Serialize) -> String {
     serde_json::to_string_pretty(value).unwrap_or_else(|e| format!("<serialization error: {e}>"))
 }
 
+/// A function called with every parsed callback message before it is processed
+pub type Observer = Box<dyn Fn(MessageKind, &Value) + Send + Sync>;
+
 pub struct MaaCallback {
     auto_reconnect: bool,
-    offline_stop_requested: Arc<AtomicBool>,
+    state: Arc<InstanceState>,
+    observer: Option<Observer>,
+    name: Option<Arc<str>>,
+    profile: Option<Arc<str>>,
 }
 
 impl MaaCallback {
-    pub fn new(auto_reconnect: bool) -> (Self, Arc<AtomicBool>) {
-        let offline_stop_requested = Arc::new(AtomicBool::new(false));
-        let cb = Self {
+    pub fn new(auto_reconnect: bool, state: Arc<InstanceState>) -> Self {
+        Self {
             auto_reconnect,
-            offline_stop_requested: Arc::clone(&offline_stop_requested),
-        };
-        (cb, offline_stop_requested)
+            state,
+            observer: None,
+            name: None,
+            profile: None,
+        }
+    }
+
+    #[cfg(feature = "server")]
+    pub fn with_observer(mut self, observer: Option<Observer>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Prefix all logs of this instance with given name
+    pub fn with_name(mut self, name: Option<Arc<str>>) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Save recognized items to the depot of given profile
+    pub fn with_profile(mut self, profile: Option<&str>) -> Self {
+        self.profile = profile.map(Arc::from);
+        self
     }
 }
 
@@ -44,7 +78,10 @@ impl Callback for MaaCallback {
             log::warn!("Failed to parse message for {kind:?}: {message}");
             return;
         };
-        self.process_message(kind, message);
+        if let Some(observer) = &self.observer {
+            observer(kind, &message);
+        }
+        crate::log::with_instance(self.name.as_ref(), || self.process_message(kind, message));
     }
 }
 
@@ -161,20 +198,28 @@ impl MaaCallback {
         match kind {
             TaskChainStart => {
                 info!("{} {}", taskchain, "Start");
-                start_task(message.get("taskid")?.as_i64()? as AsstTaskId);
+                self.state
+                    .summary
+                    .start_task(message.get("taskid")?.as_i64()? as AsstTaskId);
             }
             TaskChainCompleted => {
                 info!("{} {}", taskchain, "Completed");
-                end_current_task(summary::Reason::Completed);
+                self.state
+                    .summary
+                    .end_current_task(summary::Reason::Completed);
             }
             TaskChainStopped => {
                 warn!("{} {}", taskchain, "Stopped");
-                end_current_task(summary::Reason::Stopped);
+                self.state
+                    .summary
+                    .end_current_task(summary::Reason::Stopped);
             }
             TaskChainError => {
                 error!("{} {}", taskchain, "Error");
-                end_current_task(summary::Reason::Error);
-                MAA_CORE_ERRORED.store(true, std::sync::atomic::Ordering::Relaxed);
+                self.state.summary.end_current_task(summary::Reason::Error);
+                self.state
+                    .errored
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
             }
             TaskChainExtraInfo => {}
 
@@ -212,7 +257,7 @@ impl MaaCallback {
 
             match task {
                 "StartButton2" | "AnnihilationConfirm" => {
-                    edit_current_task_detail(|detail| {
+                    self.state.summary.edit_current_task_detail(|detail| {
                         if let Some(detail) = detail.as_fight_mut()
                             && let Some((series, sanity)) = detail.get_series()
                         {
@@ -226,7 +271,7 @@ impl MaaCallback {
                 // Fight
                 "StoneConfirm" => {
                     let exec_times = details.get("exec_times")?.as_i64()?;
-                    edit_current_task_detail(|detail| {
+                    self.state.summary.edit_current_task_detail(|detail| {
                         if let Some(detail) = detail.as_fight_mut() {
                             detail.set_stone(exec_times)
                         }
@@ -236,7 +281,7 @@ impl MaaCallback {
                 "AbandonAction" => warn!("{}", "PRTS error"),
                 // Recruit
                 "RecruitRefreshConfirm" => {
-                    edit_current_task_detail(|detail| {
+                    self.state.summary.edit_current_task_detail(|detail| {
                         if let Some(detail) = detail.as_recruit_mut() {
                             detail.refresh()
                         }
@@ -244,7 +289,7 @@ impl MaaCallback {
                     info!("{}", "Refresh Tags")
                 }
                 "RecruitConfirm" => {
-                    edit_current_task_detail(|detail| {
+                    self.state.summary.edit_current_task_detail(|detail| {
                         if let Some(detail) = detail.as_recruit_mut() {
                             detail.recruit()
                         }
@@ -256,7 +301,7 @@ impl MaaCallback {
                 // RogueLike
                 "StartExplore" => {
                     let exec_times = details.get("exec_times")?.as_i64()?;
-                    edit_current_task_detail(|detail| {
+                    self.state.summary.edit_current_task_detail(|detail| {
                         if let Some(detail) = detail.as_roguelike_mut() {
                             detail.start_exploration()
                         }
@@ -264,7 +309,7 @@ impl MaaCallback {
                     info!("Start exploration {exec_times} times")
                 }
                 "ExitThenAbandon" => {
-                    edit_current_task_detail(|detail| {
+                    self.state.summary.edit_current_task_detail(|detail| {
                         if let Some(detail) = detail.as_roguelike_mut() {
                             detail.set_state(summary::ExplorationState::Abandoned)
                         }
@@ -272,12 +317,21 @@ impl MaaCallback {
                     info!("Exploration Abandoned")
                 }
                 "ExitThenConfirm" => info!("{}", "ExplorationConfirmed"),
-                "MissionCompletedFlag" => info!("{}", "MissionCompleted"),
+                "MissionCompletedFlag" => {
+                    if message.get("taskchain")?.as_str()? == "Roguelike" {
+                        self.state.summary.edit_current_task_detail(|detail| {
+                            if let Some(detail) = detail.as_roguelike_mut() {
+                                detail.complete_battle()
+                            }
+                        });
+                    }
+                    info!("{}", "MissionCompleted")
+                }
                 "MissionFailedFlag" => {
                     // In some cases a failed mission doesn't mean failed exploration;
                     // if the exploration was not failed, its state would be overwritten later
                     if message.get("taskchain")?.as_str()? == "Roguelike" {
-                        edit_current_task_detail(|detail| {
+                        self.state.summary.edit_current_task_detail(|detail| {
                             if let Some(detail) = detail.as_roguelike_mut() {
                                 detail.set_state(summary::ExplorationState::Failed)
                             }
@@ -291,12 +345,20 @@ impl MaaCallback {
                 "StageEmergencyOps" | "StageEmergencyDps" => info!("{}", "EmergencyOpsEnter"),
                 "StageDreadfulFoe" | "StageDreadfulFoe-5Enter" => info!("{}", "DreadfulFoe"),
                 "StageTraderInvestSystemFull" => warn!("{}", "TraderInvestSystemFull"),
-                "GamePass" => info!("{}", "RoguelikeGamePass"),
+                "GamePass" => {
+                    self.state.summary.edit_current_task_detail(|detail| {
+                        if let Some(detail) = detail.as_roguelike_mut() {
+                            detail.set_state(summary::ExplorationState::Passed)
+                        }
+                    });
+                    info!("{}", "RoguelikeGamePass")
+                }
                 "OfflineConfirm" => {
                     warn!("{}", "GameOffline");
                     if !self.auto_reconnect {
                         warn!("Auto reconnect disabled, stopping");
-                        self.offline_stop_requested
+                        self.state
+                            .offline_stop
                             .store(true, std::sync::atomic::Ordering::Relaxed);
                     }
                 }
@@ -319,8 +381,28 @@ impl MaaCallback {
         let taskchain = message.get("taskchain")?.as_str()?;
 
         match taskchain {
-            "Depot" => info!("{}: {}", "Depot", json_pretty(message)),
-            "OperBox" => info!("{}: {}", "OperBox", json_pretty(message)),
+            "Depot" => match message.get("details").and_then(Depot::from_details) {
+                Some(depot) => match depot.save(self.profile.as_deref()) {
+                    Ok(()) => info!(
+                        "Recognized {} items in depot, saved to {}",
+                        depot.items.len(),
+                        crate::depot::path(self.profile.as_deref()).display()
+                    ),
+                    Err(e) => warn!("Failed to save depot: {e:#}"),
+                },
+                None => trace!("{}: {}", "Depot", json_pretty(message)),
+            },
+            "OperBox" => match message.get("details").and_then(OperBox::from_details) {
+                Some(operbox) => match operbox.save(self.profile.as_deref()) {
+                    Ok(()) => info!(
+                        "Recognized {} operators, saved to {}",
+                        operbox.operators.len(),
+                        crate::operbox::path(self.profile.as_deref()).display()
+                    ),
+                    Err(e) => warn!("Failed to save operators: {e:#}"),
+                },
+                None => trace!("{}: {}", "OperBox", json_pretty(message)),
+            },
             _ => {}
         }
 
@@ -331,7 +413,7 @@ impl MaaCallback {
             "FightTimes" => {
                 let series = details.get("series")?.as_i64()?;
                 let sanity_cost = details.get("sanity_cost")?.as_i64()?;
-                edit_current_task_detail(|detail| {
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_fight_mut() {
                         detail.set_series(series, sanity_cost);
                     }
@@ -357,14 +439,14 @@ impl MaaCallback {
                         .unwrap_or_else(|| "none".to_owned())
                 );
 
-                edit_current_task_detail(|detail| {
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_fight_mut() {
                         detail.push_drop(all_drops);
                     }
                 });
 
                 let stage = details.get("stage")?.get("stageCode")?.as_str()?;
-                edit_current_task_detail(|detail| {
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_fight_mut() {
                         detail.set_stage(stage);
                     }
@@ -380,7 +462,7 @@ impl MaaCallback {
             "UseMedicine" => {
                 let count = details.get("count")?.as_i64()?;
                 let is_expiring = details.get("is_expiring")?.as_bool()?;
-                edit_current_task_detail(|detail| {
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_fight_mut() {
                         detail.use_medicine(count, is_expiring);
                     }
@@ -409,7 +491,7 @@ impl MaaCallback {
                 let index = details.get("index")?.as_i64()?;
                 let product = details.get("product")?.as_str()?;
 
-                edit_current_task_detail(|detail| {
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_infrast_mut() {
                         detail.set_product(
                             facility.parse().unwrap_or(Facility::Unknown),
@@ -427,7 +509,7 @@ impl MaaCallback {
                 let operators = details.get("names")?.as_array()?;
                 let candidates = details.get("candidates")?.as_array()?;
 
-                edit_current_task_detail(|detail| {
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_infrast_mut() {
                         detail.set_operators(
                             facility.parse().unwrap_or(Facility::Unknown),
@@ -459,14 +541,23 @@ impl MaaCallback {
 
             // Recruit
             "RecruitTagsDetected" => (), // this info is contained in RecruitResult, so ignore it
-            "RecruitSpecialTag" | "RecruitRobotTag" => {
-                info!("{}: {}", "RecruitingTips", details.get("tag")?.as_str()?)
+            "RecruitSpecialTag" => {
+                let tag = details.get("tag")?.as_str()?;
+
+                self.state.summary.edit_current_task_detail(|detail| {
+                    if let Some(detail) = detail.as_recruit_mut() {
+                        detail.push_rare_tag(tag);
+                    }
+                });
+
+                info!("{}: {}", "RecruitingTips", tag)
             }
+            "RecruitRobotTag" => info!("{}: {}", "RecruitingTips", details.get("tag")?.as_str()?),
             "RecruitResult" => {
                 let level = details.get("level")?.as_u64()?;
                 let tags = details.get("tags")?.as_array()?;
 
-                edit_current_task_detail(|detail| {
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_recruit_mut() {
                         detail.push_recruit(
                             level,
@@ -506,9 +597,10 @@ impl MaaCallback {
                 let total = details.get("total")?.as_i64()?;
                 let deposit = details.get("deposit")?.as_i64()?;
 
-                edit_current_task_detail(|detail| {
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_roguelike_mut() {
                         detail.invest(count);
+                        detail.set_deposit(deposit);
                     }
                 });
 
@@ -516,9 +608,13 @@ impl MaaCallback {
             }
             "RoguelikeSettlement" => {
                 let exp = details.get("exp")?.as_i64()?;
-                edit_current_task_detail(|detail| {
+                let floor = details.get("floor").and_then(Value::as_i64);
+                self.state.summary.edit_current_task_detail(|detail| {
                     if let Some(detail) = detail.as_roguelike_mut() {
-                        detail.set_exp(exp)
+                        detail.set_exp(exp);
+                        if let Some(floor) = floor {
+                            detail.set_floor(floor);
+                        }
                     }
                 });
                 info!("Gain {exp} exp during this exploration");
@@ -629,15 +725,76 @@ mod tests {
 
     #[test]
     fn offline_confirm_stops_when_auto_reconnect_disabled() {
-        let (cb, offline_stop) = MaaCallback::new(false);
+        let state = Arc::new(InstanceState::default());
+        let cb = MaaCallback::new(false, Arc::clone(&state));
         cb.on_message(MessageKind::SubTaskStart, Some(offline_confirm_msg()));
-        assert!(offline_stop.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(
+            state
+                .offline_stop
+                .load(std::sync::atomic::Ordering::Relaxed)
+        );
     }
 
     #[test]
     fn offline_confirm_does_not_stop_when_auto_reconnect_enabled() {
-        let (cb, offline_stop) = MaaCallback::new(true);
+        let state = Arc::new(InstanceState::default());
+        let cb = MaaCallback::new(true, Arc::clone(&state));
         cb.on_message(MessageKind::SubTaskStart, Some(offline_confirm_msg()));
-        assert!(!offline_stop.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(
+            !state
+                .offline_stop
+                .load(std::sync::atomic::Ordering::Relaxed)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn observer_receives_parsed_messages() {
+        use std::sync::Mutex;
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let observer: Observer = {
+            let received = Arc::clone(&received);
+            Box::new(move |kind, message| received.lock().unwrap().push((kind, message.clone())))
+        };
+        let cb = MaaCallback::new(true, Arc::default()).with_observer(Some(observer));
+
+        cb.on_message(MessageKind::SubTaskStart, Some(offline_confirm_msg()));
+        cb.on_message(MessageKind::AllTasksCompleted, Some("not json"));
+        cb.on_message(MessageKind::AllTasksCompleted, None);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0, MessageKind::SubTaskStart);
+        assert_eq!(received[0].1["details"]["task"], "OfflineConfirm");
+    }
+
+    #[test]
+    fn summary_of_instance() {
+        use maa_types::TaskType;
+
+        let state = Arc::new(InstanceState::default());
+        let cb = MaaCallback::new(true, Arc::clone(&state));
+        let mut summary = summary::Summary::new();
+        summary.insert(1, None, TaskType::StartUp);
+        state.summary.init(summary);
+
+        cb.on_message(
+            MessageKind::TaskChainStart,
+            Some(r#"{"taskchain":"StartUp","taskid":1}"#),
+        );
+        cb.on_message(
+            MessageKind::TaskChainError,
+            Some(r#"{"taskchain":"StartUp","taskid":1}"#),
+        );
+
+        assert!(state.errored.load(std::sync::atomic::Ordering::Relaxed));
+        let tasks: Vec<Value> = state.summary.tasks().unwrap();
+        assert_eq!(tasks[0]["reason"], "Error");
+
+        // Another instance is not affected
+        let other = InstanceState::default();
+        assert!(!other.errored.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(other.summary.tasks::<Value>().unwrap().is_empty());
     }
 }
@@ -1,44 +1,130 @@
 pub use std::collections::BTreeMap as Map;
-use std::sync::Mutex;
+use std::{path::Path, sync::Mutex};
 
+use anyhow::{Context, Result};
 use chrono;
+use log::debug;
 use maa_types::{TaskType, primitive::AsstTaskId};
+use maa_value::prelude::*;
+use serde::{Serialize, Serializer, de::DeserializeOwned, ser::SerializeStruct};
 
 use super::IterJoin;
+use crate::{config::task::Task, history::roguelike::Strategy};
 
-static SUMMARY: Mutex<Option<Summary>> = Mutex::new(None);
+/// Summary of tasks of an assistant instance, shared between its callback and the runner
+///
+/// The summary is absent until tasks are appended to the instance.
+#[derive(Default)]
+pub struct SharedSummary(Mutex<Option<Summary>>);
 
 // It's safe to unwarp the mutex all there, because lock() returns a error only when
 // another thread failed inside the lock, which is impossible in this case, because
 // there is no function that can panic inside the lock, unless the print!, which is
 // not a problem.
 
-pub(crate) fn init(summary: Summary) {
-    *SUMMARY.lock().unwrap() = Some(summary);
-}
+impl SharedSummary {
+    pub(crate) fn init(&self, summary: Summary) {
+        *self.0.lock().unwrap() = Some(summary);
+    }
 
-fn with_summary<T>(f: impl FnOnce(&Summary) -> T) -> Option<T> {
-    SUMMARY.lock().unwrap().as_ref().map(f)
-}
+    pub(crate) fn clear(&self) {
+        *self.0.lock().unwrap() = None;
+    }
 
-fn with_summary_mut<T>(f: impl FnOnce(&mut Summary) -> T) -> Option<T> {
-    SUMMARY.lock().unwrap().as_mut().map(f)
-}
+    fn with<T>(&self, f: impl FnOnce(&Summary) -> T) -> Option<T> {
+        self.0.lock().unwrap().as_ref().map(f)
+    }
 
-pub(crate) fn display() -> Option<()> {
-    with_summary(|summary| print!("{summary}"))
-}
+    fn with_mut<T>(&self, f: impl FnOnce(&mut Summary) -> T) -> Option<T> {
+        self.0.lock().unwrap().as_mut().map(f)
+    }
+
+    /// Render the summary in given format, `None` if the summary is not initialized
+    pub(crate) fn render(&self, format: SummaryFormat) -> Result<Option<String>> {
+        self.with(|summary| summary.render(format)).transpose()
+    }
+
+    /// Print the summary in given format, or write it to given file
+    ///
+    /// Nothing is printed or written if the summary is not initialized.
+    pub(crate) fn report(&self, format: SummaryFormat, file: Option<&Path>) -> Result<()> {
+        match self.render(format)? {
+            Some(content) => output(&content, file),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the serialized summary of each task, converted to given type
+    ///
+    /// Returns an empty list if the summary is not initialized.
+    pub(crate) fn tasks<T: DeserializeOwned>(&self) -> serde_json::Result<Vec<T>> {
+        match self
+            .with(|summary| serde_json::to_value(summary))
+            .transpose()?
+        {
+            Some(mut value) => serde_json::from_value(value["tasks"].take()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub(crate) fn insert(&self, id: AsstTaskId, name: Option<String>, task: &Task) {
+        self.with_mut(|summary| summary.insert_task(id, name, task));
+    }
+
+    /// Reason why given task ended, `None` if the task is not found or not ended yet
+    pub(crate) fn reason(&self, id: AsstTaskId) -> Option<Reason> {
+        self.with(|summary| summary.task_summarys.get(&id).map(|task| task.reason))
+            .flatten()
+            .filter(|reason| !matches!(reason, Reason::Unstarted | Reason::Unfinished))
+    }
+
+    /// Serialized detail of given task, `None` if the task is not found
+    pub(crate) fn detail(&self, id: AsstTaskId) -> Option<serde_json::Value> {
+        self.with(|summary| {
+            let task = summary.task_summarys.get(&id)?;
+            serde_json::to_value(&task.detail).ok()
+        })
+        .flatten()
+    }
+
+    pub(super) fn start_task(&self, id: AsstTaskId) -> Option<()> {
+        self.with_mut(|summary| summary.start_task(id)).flatten()
+    }
+
+    pub(super) fn end_current_task(&self, reason: Reason) -> Option<()> {
+        self.with_mut(|summary| summary.end_current_task(reason))
+            .flatten()
+    }
 
-pub(super) fn start_task(id: AsstTaskId) -> Option<()> {
-    with_summary_mut(|summary| summary.start_task(id)).flatten()
+    pub(super) fn edit_current_task_detail(&self, f: impl FnOnce(&mut Detail)) -> Option<()> {
+        self.with_mut(|summary| summary.edit_current_task_detail(f))
+            .flatten()
+    }
 }
 
-pub(super) fn end_current_task(reason: Reason) -> Option<()> {
-    with_summary_mut(|summary| summary.end_current_task(reason)).flatten()
+/// Format of the task summary
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum SummaryFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    #[clap(alias = "j")]
+    Json,
+    #[clap(alias = "y")]
+    Yaml,
 }
 
-pub(super) fn edit_current_task_detail(f: impl FnOnce(&mut Detail)) -> Option<()> {
-    with_summary_mut(|summary| summary.edit_current_task_detail(f)).flatten()
+/// Print rendered summary, or write it to given file
+pub(crate) fn output(content: &str, file: Option<&Path>) -> Result<()> {
+    match file {
+        Some(file) => crate::atomic_fs::write(file, content)
+            .with_context(|| format!("Failed to write summary to {}", file.display())),
+        None => {
+            print!("{content}");
+            Ok(())
+        }
+    }
 }
 
 pub struct Summary {
@@ -54,11 +140,29 @@ impl Summary {
         }
     }
 
+    #[cfg(test)]
     pub fn insert(&mut self, id: AsstTaskId, name: Option<String>, task: impl Into<TaskType>) {
         self.task_summarys
             .insert(id, TaskSummary::new(name, task.into()));
     }
 
+    /// Insert a task to be run, with details known from its params
+    pub fn insert_task(&mut self, id: AsstTaskId, name: Option<String>, task: &Task) {
+        let mut summary = TaskSummary::new(name, task.task_type);
+        if let Some(detail) = summary.detail.as_infrast_mut() {
+            detail.set_plan_from_params(&task.params);
+        }
+        if let Some(detail) = summary.detail.as_roguelike_mut() {
+            detail.set_strategy_from_params(&task.params);
+        }
+        if let Some(detail) = summary.detail.as_fight_mut()
+            && let Some(note) = &task.note
+        {
+            detail.set_auto_stage(task.params.get_or("stage", ""), note);
+        }
+        self.task_summarys.insert(id, summary);
+    }
+
     fn current_mut(&mut self) -> Option<&mut TaskSummary> {
         self.current_task
             .and_then(|id| self.task_summarys.get_mut(&id))
@@ -80,6 +184,35 @@ impl Summary {
     fn edit_current_task_detail(&mut self, f: impl FnOnce(&mut Detail)) -> Option<()> {
         self.current_mut().map(|summary| summary.edit_detail(f))
     }
+
+    fn render(&self, format: SummaryFormat) -> Result<String> {
+        Ok(match format {
+            SummaryFormat::Text => self.to_string(),
+            SummaryFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+            SummaryFormat::Yaml => serde_yaml::to_string(self)?,
+        })
+    }
+}
+
+impl Serialize for Summary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            id: AsstTaskId,
+            #[serde(flatten)]
+            summary: &'a TaskSummary,
+        }
+
+        let tasks: Vec<_> = self
+            .task_summarys
+            .iter()
+            .map(|(&id, summary)| Entry { id, summary })
+            .collect();
+
+        let mut state = serializer.serialize_struct("Summary", 1)?;
+        state.serialize_field("tasks", &tasks)?;
+        state.end()
+    }
 }
 
 const LINE_SEP: &str = "----------------------------------------";
@@ -98,13 +231,15 @@ impl std::fmt::Display for Summary {
     }
 }
 
+#[derive(Serialize)]
 pub struct TaskSummary {
     name: Option<String>,
+    #[serde(rename = "type")]
     task: TaskType,
-    detail: Detail,
     start_time: Option<chrono::DateTime<chrono::Local>>,
     end_time: Option<chrono::DateTime<chrono::Local>>,
     reason: Reason,
+    detail: Detail,
 }
 
 impl TaskSummary {
@@ -137,6 +272,9 @@ impl TaskSummary {
     fn end(&mut self, reason: Reason) {
         self.end_time = Some(chrono::Local::now());
         self.reason = reason;
+        if let Some(detail) = self.detail.as_roguelike_mut() {
+            detail.finish();
+        }
     }
 
     fn edit_detail(&mut self, f: impl FnOnce(&mut Detail)) {
@@ -183,7 +321,9 @@ impl std::fmt::Display for TaskSummary {
     }
 }
 
-pub(super) enum Reason {
+#[cfg_attr(test, derive(Debug))]
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub(crate) enum Reason {
     Completed,
     Stopped,
     Error,
@@ -237,6 +377,8 @@ impl std::fmt::Display for FormattedDuration {
     }
 }
 
+#[derive(Serialize)]
+#[serde(untagged)]
 pub enum Detail {
     None,
     Infrast(InfrastDetail),
@@ -293,8 +435,24 @@ impl std::fmt::Display for Detail {
     }
 }
 
-pub struct InfrastDetail(Map<Facility, Map<i64, InfrastRoomInfo>>);
+#[derive(Serialize)]
+pub struct InfrastDetail {
+    /// Only serialized for custom plans
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan: Option<InfrastPlan>,
+    rooms: Map<Facility, Map<i64, InfrastRoomInfo>>,
+}
+
+/// Plan used by a custom Infrast task
+#[derive(Serialize)]
+struct InfrastPlan {
+    index: i32,
+    name: Option<String>,
+    /// Time ranges of the plan, e.g. `22:00-23:59`
+    period: Vec<String>,
+}
 
+#[derive(Serialize)]
 struct InfrastRoomInfo {
     product: Option<String>,
     operators: Vec<String>,
@@ -303,14 +461,55 @@ struct InfrastRoomInfo {
 
 impl InfrastDetail {
     pub fn new() -> Self {
-        Self(Map::new())
+        Self {
+            plan: None,
+            rooms: Map::new(),
+        }
+    }
+
+    /// Set the plan from the parameters of a custom Infrast task
+    ///
+    /// The plan file has been checked before the task is appended,
+    /// so failures to load it are ignored here.
+    fn set_plan_from_params(&mut self, params: &MAAValue) {
+        use crate::config::infrast::{CUSTOM_MODE, PlanFile};
+
+        if params.get_or("mode", 0) != CUSTOM_MODE {
+            return;
+        }
+        let Some(filename) = params.get_typed::<&str>("filename") else {
+            return;
+        };
+        let index = params.get_or("plan_index", 0);
+        let plan_file = match PlanFile::load(std::path::Path::new(filename)) {
+            Ok(plan_file) => plan_file,
+            Err(e) => {
+                debug!("Failed to load plan file for summary: {e:#}");
+                return;
+            }
+        };
+
+        if let Some(plan) = usize::try_from(index)
+            .ok()
+            .and_then(|i| plan_file.plans.get(i))
+        {
+            self.plan = Some(InfrastPlan {
+                index,
+                name: plan.name.clone(),
+                period: plan
+                    .period
+                    .iter()
+                    .map(|[start, end]| format!("{start}-{end}"))
+                    .collect(),
+            });
+        }
     }
 
     pub(super) fn set_product(&mut self, facility: Facility, id: i64, info: &str) {
         use Facility::*;
         // only the product of Mfg and Trade is useful
         if matches!(facility, Mfg | Trade) {
-            self.0
+            self.rooms
                 .entry(facility)
                 .or_default()
                 .entry(id)
@@ -326,7 +525,7 @@ impl InfrastDetail {
         operators: Vec<String>,
         candidates: Vec<String>,
     ) {
-        let map = self.0.entry(facility).or_default();
+        let map = self.rooms.entry(facility).or_default();
 
         if let Some(room_info) = map.get_mut(&id) {
             room_info.set_operators(operators, candidates);
@@ -341,7 +540,17 @@ impl InfrastDetail {
 
 impl std::fmt::Display for InfrastDetail {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (facility, map) in &self.0 {
+        if let Some(plan) = &self.plan {
+            write!(f, "Plan {}", plan.index)?;
+            if let Some(name) = &plan.name {
+                write!(f, " {name}")?;
+            }
+            if !plan.period.is_empty() {
+                write!(f, " ({})", plan.period.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        for (facility, map) in &self.rooms {
             for room_info in map.values() {
                 writeln!(f, "{facility}{room_info}")?;
             }
@@ -352,7 +561,7 @@ impl std::fmt::Display for InfrastDetail {
 }
 
 #[cfg_attr(test, derive(Debug))]
-#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Serialize)]
 pub(super) enum Facility {
     Control,
     Mfg,
@@ -465,8 +674,12 @@ impl std::fmt::Display for InfrastRoomInfo {
 pub struct FightDetail {
     // stage name to fight
     stage: Option<String>,
+    // why the stage is selected, if selected automatically
+    stage_reason: Option<String>,
     // Total times
     times: i64,
+    // Total sanity cost
+    sanity: i64,
     // series and sanity cost of last fight
     series: (i64, i64),
     // used medicine / expiring medicine
@@ -483,7 +696,9 @@ impl FightDetail {
     pub fn new() -> Self {
         Self {
             stage: None,
+            stage_reason: None,
             times: 0,
+            sanity: 0,
             series: (0, 0),
             medicine: (0, 0),
             stone: 0,
@@ -501,6 +716,7 @@ impl FightDetail {
 
     pub fn start(&mut self) {
         self.times += self.series.0;
+        self.sanity += self.series.1;
     }
 
     pub fn set_stage(&mut self, stage: &str) {
@@ -510,6 +726,11 @@ impl FightDetail {
         self.stage = Some(stage.to_owned());
     }
 
+    fn set_auto_stage(&mut self, stage: &str, reason: &str) {
+        self.stage = Some(stage.to_owned());
+        self.stage_reason = Some(reason.to_owned());
+    }
+
     pub fn set_series(&mut self, series: i64, sanity: i64) {
         self.series = (series, sanity);
     }
@@ -530,6 +751,34 @@ impl FightDetail {
     pub fn push_drop(&mut self, drop: Map<String, i64>) {
         self.drops.push(drop);
     }
+
+    fn total_drops(&self) -> Map<String, i64> {
+        let mut total_drops = Map::new();
+        for (item, count) in self.drops.iter().flatten() {
+            insert_or_add_by_ref(&mut total_drops, item, *count);
+        }
+        total_drops
+    }
+}
+
+impl Serialize for FightDetail {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (medicine, expiring_medicine) = self.medicine;
+        let mut state = serializer.serialize_struct("FightDetail", 9)?;
+        state.serialize_field("stage", &self.stage)?;
+        match &self.stage_reason {
+            Some(reason) => state.serialize_field("stage_reason", reason)?,
+            None => state.skip_field("stage_reason")?,
+        }
+        state.serialize_field("times", &self.times)?;
+        state.serialize_field("sanity", &self.sanity)?;
+        state.serialize_field("medicine", &medicine)?;
+        state.serialize_field("expiring_medicine", &expiring_medicine)?;
+        state.serialize_field("stone", &self.stone)?;
+        state.serialize_field("drops", &self.drops)?;
+        state.serialize_field("total_drops", &self.total_drops())?;
+        state.end()
+    }
 }
 
 impl std::fmt::Display for FightDetail {
@@ -539,6 +788,9 @@ impl std::fmt::Display for FightDetail {
         } else {
             return Ok(());
         }
+        if let Some(reason) = self.stage_reason.as_ref() {
+            write!(f, " (auto selected, {reason})")?;
+        }
 
         if self.times != 0 {
             write!(f, " {} times", self.times)?;
@@ -555,22 +807,20 @@ impl std::fmt::Display for FightDetail {
         }
         if !self.drops.is_empty() {
             writeln!(f, ", drops:")?;
-            let mut total_drop = Map::new();
             for (i, drop) in self.drops.iter().enumerate() {
                 write!(f, "{}.", i + 1)?;
                 let mut iter = drop.iter();
                 if let Some((item, count)) = iter.next() {
                     write!(f, " {item} × {count}")?;
-                    insert_or_add_by_ref(&mut total_drop, item, *count);
                 }
                 for (item, count) in iter {
                     write!(f, ", {item} × {count}")?;
-                    insert_or_add_by_ref(&mut total_drop, item, *count);
                 }
                 writeln!(f)?;
             }
             write!(f, "total drops:")?;
-            let mut iter = total_drop.iter();
+            let total_drops = self.total_drops();
+            let mut iter = total_drops.iter();
             if let Some((item, count)) = iter.next() {
                 write!(f, " {item} × {count}")?;
             }
@@ -588,8 +838,11 @@ pub struct RecruitDetail {
     recruit_times: Option<i64>,
     // [(tags, level, state), ...]
     record: Vec<(u64, Vec<String>, RecruitState)>,
+    /// Rare tags detected, e.g. Top Operator, which are not recruited automatically
+    rare_tags: Vec<String>,
 }
 
+#[derive(Serialize)]
 enum RecruitState {
     Refreshed,
     Recruited,
@@ -602,6 +855,7 @@ impl RecruitDetail {
             refresh_times: None,
             recruit_times: None,
             record: Vec::new(),
+            rare_tags: Vec::new(),
         }
     }
 
@@ -623,6 +877,45 @@ impl RecruitDetail {
         self.record
             .push((level, tags.into_iter().collect(), RecruitState::None));
     }
+
+    pub(super) fn push_rare_tag(&mut self, tag: &str) {
+        if !self.rare_tags.iter().any(|t| t == tag) {
+            self.rare_tags.push(tag.to_owned());
+        }
+    }
+}
+
+impl Serialize for RecruitDetail {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            level: u64,
+            tags: &'a [String],
+            /// `None` if the tags are neither refreshed nor recruited
+            state: Option<&'a RecruitState>,
+        }
+
+        let records: Vec<_> = self
+            .record
+            .iter()
+            .map(|(level, tags, state)| Record {
+                level: *level,
+                tags,
+                state: (!matches!(state, RecruitState::None)).then_some(state),
+            })
+            .collect();
+
+        let mut state = serializer.serialize_struct("RecruitDetail", 4)?;
+        state.serialize_field("refresh_times", &self.refresh_times)?;
+        state.serialize_field("recruit_times", &self.recruit_times)?;
+        state.serialize_field("records", &records)?;
+        if self.rare_tags.is_empty() {
+            state.skip_field("rare_tags")?;
+        } else {
+            state.serialize_field("rare_tags", &self.rare_tags)?;
+        }
+        state.end()
+    }
 }
 
 impl std::fmt::Display for RecruitDetail {
@@ -653,22 +946,42 @@ impl std::fmt::Display for RecruitDetail {
                 writeln!(f, "Refreshed {times} times")?;
             }
         }
+        if !self.rare_tags.is_empty() {
+            writeln!(f, "Rare tags: {}", self.rare_tags.join(", "))?;
+        }
         Ok(())
     }
 }
 
+#[derive(Serialize)]
 pub struct RoguelikeDetail {
+    #[serde(flatten)]
+    strategy: Strategy,
     explorations: Vec<ExplorationDetail>,
 }
 
 impl RoguelikeDetail {
     fn new() -> Self {
         Self {
+            strategy: Strategy::default(),
             explorations: Vec::new(),
         }
     }
 
+    /// Set the strategy of explorations from params of the task
+    fn set_strategy_from_params(&mut self, params: &MAAValue) {
+        let get = |key| params.get_typed::<&str>(key).map(ToOwned::to_owned);
+        self.strategy = Strategy {
+            theme: get("theme"),
+            mode: params.get_typed("mode"),
+            squad: get("squad"),
+            roles: get("roles"),
+            core_char: get("core_char"),
+        };
+    }
+
     pub(super) fn start_exploration(&mut self) {
+        self.finish();
         self.explorations.push(ExplorationDetail::new());
     }
 
@@ -676,6 +989,13 @@ impl RoguelikeDetail {
         self.explorations.last_mut()
     }
 
+    /// End the current exploration if not ended yet, e.g. when the task is stopped
+    fn finish(&mut self) {
+        if let Some(exploration) = self.get_current_exploration() {
+            exploration.finish();
+        }
+    }
+
     pub(super) fn set_state(&mut self, state: ExplorationState) {
         if let Some(exploration) = self.get_current_exploration() {
             exploration.set_state(state);
@@ -688,24 +1008,54 @@ impl RoguelikeDetail {
         }
     }
 
+    pub(super) fn set_deposit(&mut self, deposit: i64) {
+        if let Some(exploration) = self.get_current_exploration() {
+            exploration.deposit = Some(deposit);
+        }
+    }
+
     pub(super) fn set_exp(&mut self, exp: i64) {
         if let Some(exploration) = self.get_current_exploration() {
             exploration.set_exp(exp);
         }
     }
+
+    pub(super) fn set_floor(&mut self, floor: i64) {
+        if let Some(exploration) = self.get_current_exploration() {
+            exploration.floor = Some(floor);
+        }
+    }
+
+    pub(super) fn complete_battle(&mut self) {
+        if let Some(exploration) = self.get_current_exploration() {
+            exploration.battles += 1;
+        }
+    }
 }
 
 impl std::fmt::Display for RoguelikeDetail {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.strategy != Strategy::default() {
+            writeln!(f, "Strategy: {}", self.strategy)?;
+        }
+
         if !self.explorations.is_empty() {
             let mut total_invest = 0;
             let mut total_exp = 0;
+            let mut deepest_floor = None;
+            let mut total_duration = chrono::Duration::zero();
+            let mut ended = 0;
             let mut state_count = [0; ExplorationState::total_type()];
             writeln!(f, "Explorations:")?;
             for (i, exploration) in self.explorations.iter().enumerate() {
                 state_count[exploration.state.to_index()] += 1;
                 total_exp += exploration.exp.unwrap_or_default();
                 total_invest += exploration.invest.unwrap_or_default();
+                deepest_floor = deepest_floor.max(exploration.floor);
+                if let Some(duration) = exploration.duration() {
+                    total_duration += duration;
+                    ended += 1;
+                }
                 writeln!(f, "{}. {};", i + 1, exploration)?;
             }
             writeln!(f, "Summary:")?;
@@ -720,13 +1070,26 @@ impl std::fmt::Display for RoguelikeDetail {
             writeln!(f)?;
             writeln!(f, "Total invest {total_invest} originium ingotes")?;
             writeln!(f, "Total gained {total_exp} exp")?;
+            if let Some(deposit) = self.explorations.iter().rev().find_map(|e| e.deposit) {
+                writeln!(f, "Deposit {deposit} originium ingotes")?;
+            }
+            if let Some(floor) = deepest_floor {
+                writeln!(f, "Deepest floor {floor}")?;
+            }
+            if ended > 0 {
+                writeln!(
+                    f,
+                    "Average duration {}",
+                    FormattedDuration::from(total_duration / ended)
+                )?;
+            }
         }
         Ok(())
     }
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize)]
 pub(super) enum ExplorationState {
     Passed = 0,
     Failed,
@@ -773,6 +1136,7 @@ impl std::fmt::Display for ExplorationState {
     }
 }
 
+#[derive(Serialize)]
 struct ExplorationDetail {
     /// current state of this exploration
     state: ExplorationState,
@@ -780,6 +1144,14 @@ struct ExplorationDetail {
     invest: Option<i64>,
     /// total exp gained of this exploration
     exp: Option<i64>,
+    /// the deepest floor reached in this exploration
+    floor: Option<i64>,
+    /// number of battles completed in this exploration
+    battles: i64,
+    /// originium ingots in the bank after the last investment of this exploration
+    deposit: Option<i64>,
+    start: chrono::DateTime<chrono::Local>,
+    end: Option<chrono::DateTime<chrono::Local>>,
 }
 
 impl ExplorationDetail {
@@ -788,11 +1160,27 @@ impl ExplorationDetail {
             state: ExplorationState::Unknown,
             invest: None,
             exp: None,
+            floor: None,
+            battles: 0,
+            deposit: None,
+            start: chrono::Local::now(),
+            end: None,
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.end.is_none() {
+            self.end = Some(chrono::Local::now());
         }
     }
 
+    fn duration(&self) -> Option<chrono::Duration> {
+        self.end.map(|end| end - self.start)
+    }
+
     pub(super) fn set_state(&mut self, state: ExplorationState) {
         self.state = state;
+        self.end = Some(chrono::Local::now());
     }
 
     pub(super) fn invest(&mut self, invest: i64) {
@@ -803,19 +1191,29 @@ impl ExplorationDetail {
     }
 
     pub(super) fn set_exp(&mut self, exp: i64) {
-        self.exp = Some(exp)
+        self.exp = Some(exp);
+        self.end = Some(chrono::Local::now());
     }
 }
 
 impl std::fmt::Display for ExplorationDetail {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.state)?;
+        if let Some(floor) = self.floor {
+            write!(f, ", floor {floor}")?;
+        }
+        if self.battles > 0 {
+            write!(f, ", {} battles", self.battles)?;
+        }
         if let Some(invest) = self.invest {
             write!(f, ", invest {invest} originium ingotes")?;
         }
         if let Some(exp) = self.exp {
             write!(f, ", gained {exp} exp")?;
         }
+        if let Some(duration) = self.duration() {
+            write!(f, ", {}", FormattedDuration::from(duration))?;
+        }
         Ok(())
     }
 }
@@ -947,6 +1345,105 @@ mod tests {
 
             assert!(re.is_match(&summary.to_string()));
         }
+
+        #[test]
+        fn serialize() {
+            use TaskType::*;
+            use serde_json::json;
+
+            let mut summary = Summary::new();
+            summary.insert(1, Some("Fight TS".to_owned()), Fight);
+            summary.insert(2, None, Recruit);
+            summary.insert(3, None, CloseDown);
+
+            summary.start_task(1);
+            summary.edit_current_task_detail(|detail| {
+                let detail = detail.as_fight_mut().unwrap();
+                detail.set_stage("TS-9");
+                detail.set_series(1, 18);
+                detail.use_medicine(1, false);
+                detail.start();
+                detail.push_drop([("A".to_owned(), 1)].into_iter().collect());
+            });
+            summary.end_current_task(Reason::Completed);
+
+            summary.start_task(2);
+            summary.edit_current_task_detail(|detail| {
+                let detail = detail.as_recruit_mut().unwrap();
+                detail.push_recruit(4, ["A", "B"].into_iter().map(|s| s.to_owned()));
+                detail.recruit();
+                detail.push_recruit(3, ["C"].into_iter().map(|s| s.to_owned()));
+            });
+            summary.end_current_task(Reason::Stopped);
+
+            let value = serde_json::to_value(&summary).unwrap();
+            let tasks = value["tasks"].as_array().unwrap();
+            assert_eq!(tasks.len(), 3);
+
+            assert_eq!(tasks[0]["id"], 1);
+            assert_eq!(tasks[0]["name"], "Fight TS");
+            assert_eq!(tasks[0]["type"], "Fight");
+            assert_eq!(tasks[0]["reason"], "Completed");
+            assert!(tasks[0]["start_time"].is_string());
+            assert!(tasks[0]["end_time"].is_string());
+            assert_eq!(
+                tasks[0]["detail"],
+                json!({
+                    "stage": "TS-9",
+                    "times": 1,
+                    "sanity": 18,
+                    "medicine": 1,
+                    "expiring_medicine": 0,
+                    "stone": 0,
+                    "drops": [{ "A": 1 }],
+                    "total_drops": { "A": 1 },
+                })
+            );
+
+            assert_eq!(tasks[1]["id"], 2);
+            assert_eq!(tasks[1]["name"], json!(null));
+            assert_eq!(tasks[1]["type"], "Recruit");
+            assert_eq!(tasks[1]["reason"], "Stopped");
+            assert_eq!(
+                tasks[1]["detail"],
+                json!({
+                    "refresh_times": null,
+                    "recruit_times": 1,
+                    "records": [
+                        { "level": 4, "tags": ["A", "B"], "state": "Recruited" },
+                        { "level": 3, "tags": ["C"], "state": null },
+                    ],
+                })
+            );
+
+            assert_eq!(
+                tasks[2],
+                json!({
+                    "id": 3,
+                    "name": null,
+                    "type": "CloseDown",
+                    "start_time": null,
+                    "end_time": null,
+                    "reason": "Unstarted",
+                    "detail": null,
+                })
+            );
+
+            let yaml = summary.render(SummaryFormat::Yaml).unwrap();
+            assert_eq!(
+                serde_yaml::from_str::<serde_json::Value>(&yaml).unwrap(),
+                value
+            );
+            let json = summary.render(SummaryFormat::Json).unwrap();
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+                value
+            );
+            assert_eq!(
+                summary.render(SummaryFormat::Text).unwrap(),
+                summary.to_string()
+            );
+        }
     }
 
     mod detail {
@@ -1030,6 +1527,66 @@ mod tests {
                 "Mfg(Product) with operators: A, B, [C, D]\n\
                  Office with operators: unknown\n",
             );
+            assert_eq!(
+                serde_json::to_value(&detail).unwrap(),
+                serde_json::json!({
+                    "rooms": {
+                        "Mfg": {
+                            "1": { "product": "Product", "operators": ["A", "B"], "candidates": ["C", "D"] },
+                        },
+                        "Office": {
+                            "1": { "product": null, "operators": [], "candidates": [] },
+                        },
+                    },
+                })
+            );
+        }
+
+        #[test]
+        fn infrast_plan() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("plan.json");
+            std::fs::write(
+                &path,
+                r#"{ "plans": [{}, { "name": "Night", "period": [["22:00", "05:59"]] }] }"#,
+            )
+            .unwrap();
+            let path = path.to_str().unwrap();
+
+            let mut summary = Summary::new();
+            summary.insert_task(
+                1,
+                None,
+                &Task::new(
+                    TaskType::Infrast,
+                    object!("mode" => 10000, "filename" => path, "plan_index" => 1),
+                ),
+            );
+            summary.insert_task(
+                2,
+                None,
+                &Task::new(TaskType::Infrast, object!("mode" => 0, "filename" => path)),
+            );
+            summary.insert_task(
+                3,
+                None,
+                &Task::new(
+                    TaskType::Infrast,
+                    object!("mode" => 10000, "filename" => path, "plan_index" => 2),
+                ),
+            );
+
+            let detail = &summary.task_summarys[&1].detail;
+            assert_eq!(detail.to_string(), "Plan 1 Night (22:00-05:59)\n");
+            assert_eq!(
+                serde_json::to_value(detail).unwrap(),
+                serde_json::json!({
+                    "plan": { "index": 1, "name": "Night", "period": ["22:00-05:59"] },
+                    "rooms": {},
+                })
+            );
+            assert_eq!(summary.task_summarys[&2].detail.to_string(), "");
+            assert_eq!(summary.task_summarys[&3].detail.to_string(), "");
         }
 
         #[test]
@@ -1074,6 +1631,34 @@ mod tests {
 
             let detail = FightDetail::new();
             assert_eq!(detail.to_string(), "");
+
+            let mut summary = Summary::new();
+            let mut task = Task::new(TaskType::Fight, object!("stage" => "CE-6"));
+            task.note = Some("first given stage, open now".to_owned());
+            summary.insert_task(1, None, &task);
+            let detail = &summary.task_summarys[&1].detail;
+            assert_eq!(
+                detail.to_string(),
+                "Fight CE-6 (auto selected, first given stage, open now)\n"
+            );
+            assert_eq!(
+                serde_json::to_value(detail).unwrap()["stage_reason"],
+                "first given stage, open now"
+            );
+
+            summary.insert_task(
+                2,
+                None,
+                &Task::new(TaskType::Fight, object!("stage" => "1-7")),
+            );
+            let detail = &summary.task_summarys[&2].detail;
+            assert_eq!(detail.to_string(), "");
+            assert!(
+                serde_json::to_value(detail)
+                    .unwrap()
+                    .get("stage_reason")
+                    .is_none()
+            );
         }
 
         #[test]
@@ -1120,6 +1705,21 @@ mod tests {
                  2. ★★★★ C, D\n\
                  Refreshed 1 times\n",
             );
+
+            let mut detail = RecruitDetail::new();
+            detail.push_recruit(6, ["高级资深干员"].into_iter().map(|s| s.to_owned()));
+            detail.push_rare_tag("高级资深干员");
+            detail.push_rare_tag("高级资深干员");
+            assert_eq!(
+                detail.to_string(),
+                "Detected tags:\n\
+                 1. ★★★★★★ 高级资深干员\n\
+                 Rare tags: 高级资深干员\n",
+            );
+            assert_eq!(
+                serde_json::to_value(&detail).unwrap()["rare_tags"],
+                serde_json::json!(["高级资深干员"])
+            );
         }
 
         #[test]
@@ -1144,28 +1744,101 @@ mod tests {
 
         #[test]
         fn roguelike() {
+            use chrono::TimeZone;
+
+            let time = |minute, second| {
+                chrono::Local
+                    .with_ymd_and_hms(2024, 1, 1, 4, minute, second)
+                    .unwrap()
+            };
+
             let mut detail = RoguelikeDetail::new();
+            detail.set_strategy_from_params(&object!(
+                "theme" => "Sarkaz",
+                "mode" => 0,
+                "squad" => "点刺成锭分队",
+                "core_char" => "维什戴尔",
+            ));
             detail.start_exploration();
             detail.invest(10);
+            detail.complete_battle();
             detail.set_state(ExplorationState::Failed);
             detail.set_exp(100);
             detail.start_exploration();
             detail.invest(17);
             detail.invest(1);
+            detail.set_deposit(28);
+            detail.complete_battle();
+            detail.complete_battle();
+            detail.set_floor(3);
             detail.set_state(ExplorationState::Passed);
             detail.set_exp(200);
             detail.start_exploration();
+            detail.explorations[0].start = time(0, 0);
+            detail.explorations[0].end = Some(time(10, 30));
+            detail.explorations[1].start = time(10, 30);
+            detail.explorations[1].end = Some(time(40, 0));
+            detail.explorations[2].start = time(40, 0);
             assert_eq!(
                 detail.to_string(),
-                "Explorations:\n\
-                1. Failed, invest 10 originium ingotes, gained 100 exp;\n\
-                2. Passed, invest 18 originium ingotes, gained 200 exp;\n\
+                "Strategy: theme Sarkaz, mode 0, squad 点刺成锭分队, core operator 维什戴尔\n\
+                Explorations:\n\
+                1. Failed, 1 battles, invest 10 originium ingotes, gained 100 exp, 10m 30s;\n\
+                2. Passed, floor 3, 2 battles, invest 18 originium ingotes, gained 200 exp, 29m 30s;\n\
                 3. Unknown;\n\
                 Summary:\n\
                 Passed 1, Failed 1, Unknown 1\n\
                 Total invest 28 originium ingotes\n\
-                Total gained 300 exp\n",
+                Total gained 300 exp\n\
+                Deposit 28 originium ingotes\n\
+                Deepest floor 3\n\
+                Average duration 20m\n",
             );
+            assert_eq!(
+                serde_json::to_value(&detail).unwrap(),
+                serde_json::json!({
+                    "theme": "Sarkaz",
+                    "mode": 0,
+                    "squad": "点刺成锭分队",
+                    "core_char": "维什戴尔",
+                    "explorations": [
+                        {
+                            "state": "Failed",
+                            "invest": 10,
+                            "exp": 100,
+                            "floor": null,
+                            "battles": 1,
+                            "deposit": null,
+                            "start": time(0, 0),
+                            "end": time(10, 30),
+                        },
+                        {
+                            "state": "Passed",
+                            "invest": 18,
+                            "exp": 200,
+                            "floor": 3,
+                            "battles": 2,
+                            "deposit": 28,
+                            "start": time(10, 30),
+                            "end": time(40, 0),
+                        },
+                        {
+                            "state": "Unknown",
+                            "invest": null,
+                            "exp": null,
+                            "floor": null,
+                            "battles": 0,
+                            "deposit": null,
+                            "start": time(40, 0),
+                            "end": null,
+                        },
+                    ],
+                })
+            );
+
+            // the current exploration ends with the task
+            detail.finish();
+            assert!(detail.explorations[2].end.is_some());
         }
     }
 }
@@ -0,0 +1,326 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{self, AtomicBool},
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, Utc};
+use log::{debug, error, info, warn};
+use maa_core::Assistant;
+use maa_dirs as dirs;
+
+use super::{
+    CommonArgs, InstanceState, create_assistant, execute, finish_run, load_asst_config, load_core,
+    load_task_file, new_callback, register_stop_signal, setup_core,
+};
+use crate::{
+    config::{
+        FindFile, FromFile,
+        asst::AsstConfig,
+        schedule::{Job, ScheduleConfig, Trigger},
+    },
+    history,
+};
+
+/// Number of upcoming runs displayed for each job in dry run mode
+const DRY_RUN_PREVIEW: usize = 3;
+
+/// Run task files on schedule until interrupted
+pub fn daemon(schedule: Option<&Path>, args: CommonArgs) -> Result<()> {
+    let schedule = load_schedule(schedule)?;
+
+    if args.dry_run {
+        return preview(&schedule, args.profile.as_deref());
+    }
+
+    let mut daemon = Daemon {
+        asst_config: load_asst_config(&args)?,
+        stop_bool: register_stop_signal()?,
+        args,
+        state: Arc::default(),
+        asst: None,
+        loaded_resource: None,
+    };
+
+    let ret = daemon.run(&schedule.jobs);
+
+    // TODO: Better ways to restore signal handlers?
+    daemon.stop_bool.store(true, atomic::Ordering::Relaxed);
+
+    ret
+}
+
+fn load_schedule(path: Option<&Path>) -> Result<ScheduleConfig> {
+    let schedule = match path {
+        Some(path) => ScheduleConfig::from_file(path),
+        None => ScheduleConfig::find_file(join!(dirs::config(), "schedule")),
+    }
+    .context("Failed to load schedule!")?;
+
+    schedule.validate()?;
+
+    Ok(schedule)
+}
+
+/// Check all task files and print upcoming runs of each job
+fn preview(schedule: &ScheduleConfig, profile: Option<&str>) -> Result<()> {
+    let now = Utc::now();
+    for job in &schedule.jobs {
+        load_task_file(&job.task, profile).with_context(|| {
+            format!("Failed to load task `{}` of job `{}`", job.task, job.name())
+        })?;
+
+        println!("{} ({}):", job.name(), job.task);
+        let upcoming =
+            std::iter::successors(first_run(job, now, None), |t| job.trigger.next_after(*t))
+                .take(DRY_RUN_PREVIEW);
+        for time in upcoming {
+            println!(
+                "  {}",
+                time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Time of the first run of a job after the daemon started
+///
+/// Cron jobs are run at the next matched time, runs missed while the daemon is not running are
+/// not made up. Interval jobs are run one interval after the last recorded run, or immediately if
+/// they are overdue or have never been run.
+fn first_run(
+    job: &Job,
+    now: DateTime<Utc>,
+    last_start: Option<DateTime<Local>>,
+) -> Option<DateTime<Utc>> {
+    match job.trigger {
+        Trigger::Cron { .. } => job.trigger.next_after(now),
+        Trigger::Interval(_) => match last_start {
+            Some(last) => job
+                .trigger
+                .next_after(last.with_timezone(&Utc))
+                .map(|next| next.max(now)),
+            None => Some(now),
+        },
+    }
+}
+
+struct Daemon {
+    asst_config: AsstConfig,
+    args: CommonArgs,
+    stop_bool: Arc<AtomicBool>,
+    /// State shared by all runs, the summary is cleared before each run
+    state: Arc<InstanceState>,
+    /// Assistant kept alive between runs, recreated when resources are reloaded
+    asst: Option<Assistant>,
+    /// Resource directories loaded into MaaCore, `None` if MaaCore is not set up yet
+    loaded_resource: Option<Vec<PathBuf>>,
+}
+
+impl Daemon {
+    fn run(&mut self, jobs: &[Job]) -> Result<()> {
+        load_core().context("Failed to load MaaCore!")?;
+
+        let now = Utc::now();
+        let mut next_runs = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            let last_start = history::last_job_start(self.args.profile.as_deref(), job.name())
+                .unwrap_or_else(|e| {
+                    warn!("Failed to read run history: {e:#}");
+                    None
+                });
+            let next = first_run(job, now, last_start);
+            log_next_run(job, next);
+            next_runs.push(next);
+        }
+
+        info!("Daemon started with {} job(s)", jobs.len());
+
+        loop {
+            if self.stop_bool.load(atomic::Ordering::Relaxed) {
+                info!("Daemon stopped by user");
+                return Ok(());
+            }
+
+            let now = Utc::now();
+            let due = next_runs
+                .iter()
+                .enumerate()
+                .filter_map(|(i, next)| next.filter(|t| *t <= now).map(|t| (i, t)))
+                .min_by_key(|(_, t)| *t);
+
+            let Some((index, _)) = due else {
+                if next_runs.iter().all(Option::is_none) {
+                    info!("No more jobs to run, exiting daemon");
+                    return Ok(());
+                }
+                std::thread::sleep(Duration::from_secs(1));
+                continue;
+            };
+
+            let job = &jobs[index];
+            let start = Local::now();
+            if job.condition.is_active(self.args.profile.as_deref()) {
+                self.run_job(job, start);
+            } else {
+                info!("Skipping job `{}`: condition not met", job.name());
+            }
+
+            let now = Utc::now();
+            let next = match job.trigger {
+                Trigger::Cron { .. } => job.trigger.next_after(now),
+                Trigger::Interval(_) => job
+                    .trigger
+                    .next_after(start.with_timezone(&Utc))
+                    .map(|next| next.max(now)),
+            };
+            log_next_run(job, next);
+            next_runs[index] = next;
+        }
+    }
+
+    fn run_job(&mut self, job: &Job, start: DateTime<Local>) {
+        info!("Running job `{}` with task `{}`", job.name(), job.task);
+
+        // Do not report the summary of last run if this run fails before tasks are appended
+        self.state.summary.clear();
+        self.state.errored.store(false, atomic::Ordering::Relaxed);
+        let ret = self.run_task(&job.task);
+
+        if let Err(e) = self.args.report_summary(&self.state.summary) {
+            warn!("Failed to report summary: {e:#}");
+        }
+
+        let error = match &ret {
+            Ok(()) => {
+                info!("Job `{}` finished", job.name());
+                None
+            }
+            Err(e) => {
+                error!("Job `{}` failed: {e:#}", job.name());
+                Some(format!("{e:#}"))
+            }
+        };
+
+        finish_run(
+            &self.state,
+            start,
+            Some(&job.task),
+            Some(job.name()),
+            self.args.profile.as_deref(),
+            error,
+        );
+    }
+
+    fn run_task(&mut self, task: &str) -> Result<()> {
+        let mut task_config = load_task_file(task, self.args.profile.as_deref())?;
+
+        let mut asst_config = self.asst_config.clone();
+        if let Some(resource) = task_config.client_type.resource() {
+            asst_config.resource.use_global_resource(resource);
+        }
+
+        let stop_bool = Arc::clone(&self.stop_bool);
+        let state = Arc::clone(&self.state);
+        let asst = self.prepare_assistant(&asst_config)?;
+
+        let tasks = std::mem::take(&mut task_config.tasks);
+        let ret = execute(asst, &asst_config, &task_config, tasks, &state, &stop_bool);
+
+        // Make sure no task is left in the queue for the next run
+        if ret.is_err()
+            && let Err(e) = asst.stop()
+        {
+            debug!("Failed to stop MaaCore: {e}");
+        }
+
+        ret?;
+
+        if state.errored.load(atomic::Ordering::Relaxed) {
+            bail!("Some error occurred during running task!");
+        }
+
+        Ok(())
+    }
+
+    /// Get the assistant, loading resources and recreating it if resources changed
+    fn prepare_assistant(&mut self, asst_config: &AsstConfig) -> Result<&Assistant> {
+        let resource = asst_config.resource.resource_dirs();
+
+        match self.loaded_resource.as_ref() {
+            None => setup_core(asst_config)?,
+            Some(loaded) if *loaded != resource => {
+                debug!("Resources changed, reloading resources");
+                // The old assistant must be destroyed before loading new resources
+                self.asst = None;
+                asst_config.resource.load()?;
+            }
+            Some(_) => {}
+        }
+        self.loaded_resource = Some(resource);
+
+        let asst = match self.asst.take() {
+            Some(asst) => asst,
+            None => {
+                let callback = new_callback(
+                    asst_config,
+                    self.args.no_auto_reconnect,
+                    Arc::clone(&self.state),
+                )
+                .with_profile(self.args.profile.as_deref());
+                create_assistant(asst_config, callback)?
+            }
+        };
+
+        Ok(self.asst.insert(asst))
+    }
+}
+
+fn log_next_run(job: &Job, next: Option<DateTime<Utc>>) {
+    match next {
+        Some(next) => info!(
+            "Next run of job `{}` at {}",
+            job.name(),
+            next.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+        ),
+        None => warn!("Job `{}` will never be run again", job.name()),
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn first_run_of_job() {
+        let job = |json: &str| serde_json::from_str::<Job>(json).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let local = |h, m| {
+            Utc.with_ymd_and_hms(2024, 1, 1, h, m, 0)
+                .unwrap()
+                .with_timezone(&Local)
+        };
+
+        let interval = job(r#"{ "task": "a", "interval": "1h" }"#);
+        assert_eq!(first_run(&interval, now, None), Some(now));
+        assert_eq!(
+            first_run(&interval, now, Some(local(11, 30))),
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 12, 30, 0).unwrap())
+        );
+        assert_eq!(first_run(&interval, now, Some(local(9, 0))), Some(now));
+
+        let cron = job(r#"{ "task": "a", "cron": "0 4 * * *", "timezone": 0 }"#);
+        let next = Some(Utc.with_ymd_and_hms(2024, 1, 2, 4, 0, 0).unwrap());
+        assert_eq!(first_run(&cron, now, None), next);
+        assert_eq!(first_run(&cron, now, Some(local(4, 0))), next);
+    }
+}
@@ -0,0 +1,388 @@
+//! Run custom tasks on multiple devices in parallel, each with its own assistant.
+//!
+//! MaaCore is loaded and set up only once, so all instances share the same static options
+//! (from the profile of the first instance) and must use the same resources.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{self, AtomicBool},
+    },
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use clap::Args;
+use log::{error, info};
+use maa_dirs as dirs;
+use serde::Serialize;
+
+use super::{
+    CommonArgs, InstanceState, SummaryFormat, append_tasks, create_assistant, execute,
+    find_profile, finish_run, load_core, load_task_file, new_callback, register_stop_signal,
+    setup_core, summary, update_resources,
+};
+use crate::{
+    config::{
+        FindFile,
+        asst::AsstConfig,
+        fleet::{FleetConfig, Instance},
+        task::TaskConfig,
+    },
+    history::Status,
+};
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Args, Default)]
+pub struct FleetArgs {
+    /// Run the task on given instance, repeat to run on multiple instances in parallel
+    ///
+    /// Each instance is given as `PROFILE[@ADDR]`, where `PROFILE` is the profile name
+    /// (empty for the default profile) and `ADDR` is the address of the device
+    /// (default to the address in the profile).
+    /// For example, `--instance emu1@127.0.0.1:5555 --instance emu2@127.0.0.1:5557`.
+    #[arg(
+        long = "instance",
+        value_name = "PROFILE[@ADDR]",
+        conflicts_with_all = ["profile", "addr", "fleet"],
+        verbatim_doc_comment
+    )]
+    pub instances: Vec<Instance>,
+    /// Run the instances listed in given fleet file in parallel
+    ///
+    /// The fleet file lists instances with their profile, address and task file.
+    /// Relative paths are resolved against the `fleets` directory in the config directory,
+    /// and the extension can be omitted like task files.
+    /// Instances without a task file run the task given in command line.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["profile", "addr"],
+        verbatim_doc_comment
+    )]
+    pub fleet: Option<PathBuf>,
+}
+
+impl FleetArgs {
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty() && self.fleet.is_none()
+    }
+
+    fn load(self) -> Result<FleetConfig> {
+        let fleet = match self.fleet {
+            Some(path) => match dirs::abs_config(&path, Some("fleets")) {
+                Some(abs_path) => FleetConfig::find_file(abs_path),
+                None => FleetConfig::find_file(&path),
+            }
+            .context("Failed to load fleet file!")?,
+            None => FleetConfig {
+                instances: self.instances,
+            },
+        };
+
+        fleet.validate()?;
+
+        Ok(fleet)
+    }
+}
+
+/// An instance ready to run, with its profile and task file loaded
+struct Prepared {
+    name: Arc<str>,
+    instance: Instance,
+    task: String,
+    asst_config: AsstConfig,
+    task_config: TaskConfig,
+}
+
+/// Result of an instance
+struct Outcome {
+    name: Arc<str>,
+    instance: Instance,
+    task: String,
+    state: Arc<InstanceState>,
+    error: Option<String>,
+}
+
+/// Run instances in parallel and report a combined summary
+pub fn run_fleet(fleet: FleetArgs, default_task: Option<&str>, args: CommonArgs) -> Result<()> {
+    let fleet = fleet.load()?;
+
+    update_resources()?;
+    let prepared = fleet
+        .instances
+        .into_iter()
+        .map(|instance| prepare(instance, default_task, &args))
+        .collect::<Result<Vec<_>>>()?;
+    check_resources(&prepared)?;
+
+    load_core().context("Failed to load MaaCore!")?;
+    setup_core(&prepared[0].asst_config)?;
+
+    let stop_bool = register_stop_signal()?;
+
+    info!("Running {} instances in parallel", prepared.len());
+    let outcomes = std::thread::scope(|s| {
+        let handles: Vec<_> = prepared
+            .into_iter()
+            .map(|prepared| s.spawn(|| run_instance(prepared, &args, &stop_bool)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // TODO: Better ways to restore signal handlers?
+    stop_bool.store(true, atomic::Ordering::Relaxed);
+
+    let reported = if args.no_summary {
+        Ok(())
+    } else {
+        render(&outcomes, args.summary_format)
+            .and_then(|content| summary::output(&content, args.summary_file.as_deref()))
+    };
+
+    let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+    if failed > 0 {
+        bail!("{failed} of {} instances failed!", outcomes.len());
+    }
+
+    reported
+}
+
+fn prepare(instance: Instance, default_task: Option<&str>, args: &CommonArgs) -> Result<Prepared> {
+    let name = instance.name();
+    let Some(task) = instance.task.as_deref().or(default_task) else {
+        bail!("No task given for instance `{name}`!");
+    };
+
+    let mut asst_config = find_profile(dirs::config(), instance.profile.as_deref())
+        .with_context(|| format!("Failed to load profile of instance `{name}`"))?;
+    args.apply_to(&mut asst_config);
+    if let Some(addr) = instance.addr.as_deref() {
+        asst_config.connection.set_address(addr);
+    }
+
+    let task_config = load_task_file(task, instance.profile.as_deref())
+        .with_context(|| format!("Failed to load task `{task}` of instance `{name}`"))?;
+    if let Some(resource) = task_config.client_type.resource() {
+        asst_config.resource.use_global_resource(resource);
+    }
+
+    Ok(Prepared {
+        name: Arc::from(name),
+        task: task.to_owned(),
+        instance,
+        asst_config,
+        task_config,
+    })
+}
+
+/// Resources are loaded into MaaCore globally, so all instances must use the same resources
+fn check_resources(prepared: &[Prepared]) -> Result<()> {
+    let Some((first, rest)) = prepared.split_first() else {
+        bail!("No instance to run!");
+    };
+
+    let resource = first.asst_config.resource.resource_dirs();
+    for other in rest {
+        if other.asst_config.resource.resource_dirs() != resource {
+            bail!(
+                "Instances `{}` and `{}` use different resources, \
+                 which can not be loaded at the same time!",
+                first.name,
+                other.name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_instance(prepared: Prepared, args: &CommonArgs, stop_bool: &AtomicBool) -> Outcome {
+    let Prepared {
+        name,
+        instance,
+        task,
+        asst_config,
+        mut task_config,
+    } = prepared;
+
+    let prefix = Arc::clone(&name);
+    crate::log::with_instance(Some(&prefix), || {
+        let start = Local::now();
+        let state = Arc::new(InstanceState::default());
+
+        let ret = (|| {
+            let callback = new_callback(&asst_config, args.no_auto_reconnect, Arc::clone(&state))
+                .with_name(Some(Arc::clone(&name)))
+                .with_profile(instance.profile.as_deref());
+            let asst = create_assistant(&asst_config, callback)?;
+
+            let tasks = std::mem::take(&mut task_config.tasks);
+            if args.dry_run {
+                append_tasks(&asst, &state.summary, tasks)?;
+            } else {
+                execute(&asst, &asst_config, &task_config, tasks, &state, stop_bool)?;
+            }
+
+            if state.errored.load(atomic::Ordering::Relaxed) {
+                bail!("Some error occurred during running task!");
+            }
+
+            Ok(())
+        })();
+
+        let error = ret.err().map(|e| format!("{e:#}"));
+        match &error {
+            Some(e) => error!("Instance failed: {e}"),
+            None => info!("Instance finished"),
+        }
+
+        if !args.dry_run {
+            finish_run(
+                &state,
+                start,
+                Some(&task),
+                None,
+                instance.profile.as_deref(),
+                error.clone(),
+            );
+        }
+
+        Outcome {
+            name,
+            instance,
+            task,
+            state,
+            error,
+        }
+    })
+}
+
+/// Render the summaries of all instances as one report
+fn render(outcomes: &[Outcome], format: SummaryFormat) -> Result<String> {
+    #[derive(Serialize)]
+    struct Report<'a> {
+        instances: Vec<InstanceReport<'a>>,
+    }
+
+    #[derive(Serialize)]
+    struct InstanceReport<'a> {
+        name: &'a str,
+        profile: Option<&'a str>,
+        addr: Option<&'a str>,
+        task: &'a str,
+        status: Status,
+        error: Option<&'a str>,
+        tasks: Vec<serde_json::Value>,
+    }
+
+    if let SummaryFormat::Text = format {
+        let mut text = String::new();
+        for outcome in outcomes {
+            text.push_str(&format!(
+                "[{}] {}: {}",
+                outcome.name,
+                outcome.task,
+                status(outcome).to_str()
+            ));
+            if let Some(error) = outcome.error.as_deref() {
+                text.push_str(&format!(" ({error})"));
+            }
+            text.push('\n');
+            text.push_str(&outcome.state.summary.render(format)?.unwrap_or_default());
+        }
+        return Ok(text);
+    }
+
+    let report = Report {
+        instances: outcomes
+            .iter()
+            .map(|outcome| {
+                Ok(InstanceReport {
+                    name: &outcome.name,
+                    profile: outcome.instance.profile.as_deref(),
+                    addr: outcome.instance.addr.as_deref(),
+                    task: &outcome.task,
+                    status: status(outcome),
+                    error: outcome.error.as_deref(),
+                    tasks: outcome.state.summary.tasks()?,
+                })
+            })
+            .collect::<serde_json::Result<_>>()?,
+    };
+
+    Ok(match format {
+        SummaryFormat::Yaml => serde_yaml::to_string(&report)?,
+        _ => serde_json::to_string_pretty(&report)? + "\n",
+    })
+}
+
+fn status(outcome: &Outcome) -> Status {
+    if outcome.error.is_none() {
+        Status::Succeeded
+    } else {
+        Status::Failed
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use maa_types::TaskType;
+    use serde_json::json;
+
+    use super::*;
+
+    fn outcome(name: &str, error: Option<&str>) -> Outcome {
+        let state = Arc::new(InstanceState::default());
+        let mut task_summary = summary::Summary::new();
+        task_summary.insert(1, None, TaskType::StartUp);
+        state.summary.init(task_summary);
+
+        Outcome {
+            name: Arc::from(name),
+            instance: format!("{name}@127.0.0.1:5555").parse().unwrap(),
+            task: "daily".to_owned(),
+            state,
+            error: error.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn render_report() {
+        let outcomes = [
+            outcome("emu1", None),
+            outcome("emu2", Some("Interrupted by user!")),
+        ];
+
+        let value: serde_json::Value =
+            serde_json::from_str(&render(&outcomes, SummaryFormat::Json).unwrap()).unwrap();
+        let instances = value["instances"].as_array().unwrap();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0]["name"], "emu1");
+        assert_eq!(instances[0]["profile"], "emu1");
+        assert_eq!(instances[0]["addr"], "127.0.0.1:5555");
+        assert_eq!(instances[0]["task"], "daily");
+        assert_eq!(instances[0]["status"], "succeeded");
+        assert_eq!(instances[0]["error"], json!(null));
+        assert_eq!(instances[0]["tasks"][0]["type"], "StartUp");
+        assert_eq!(instances[1]["status"], "failed");
+        assert_eq!(instances[1]["error"], "Interrupted by user!");
+
+        let yaml: serde_json::Value =
+            serde_yaml::from_str(&render(&outcomes, SummaryFormat::Yaml).unwrap()).unwrap();
+        assert_eq!(yaml, value);
+
+        let text = render(&outcomes, SummaryFormat::Text).unwrap();
+        assert!(text.starts_with("[emu1] daily: succeeded\nSummary\n"));
+        assert!(text.contains("[emu2] daily: failed (Interrupted by user!)\nSummary\n"));
+    }
+}
@@ -1,16 +1,34 @@
 mod callback;
-use callback::summary;
+pub use callback::summary::SummaryFormat;
+use callback::{InstanceState, MaaCallback, summary};
+
+mod daemon;
+pub use daemon::daemon;
 
 mod external;
 
+mod fleet;
+pub use fleet::{FleetArgs, run_fleet};
+
+mod queue;
+
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "server")]
+pub use server::serve;
+
 pub mod preset;
 
 use std::{
-    path::Path,
-    sync::{Arc, atomic},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{self, AtomicBool},
+    },
 };
 
 use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
 use clap::Args;
 use log::{debug, warn};
 use maa_core::Assistant;
@@ -21,9 +39,9 @@ use crate::{
     config::{
         FindFile,
         asst::AsstConfig,
-        task::{TaskConfig, TaskConfigTemplate},
+        task::{Task, TaskConfig, TaskConfigTemplate},
     },
-    installer,
+    history, installer, notify,
 };
 
 #[cfg_attr(test, derive(Debug, PartialEq))]
@@ -82,6 +100,25 @@ pub struct CommonArgs {
     /// If you want to disable this behavior, you can use this option.
     #[arg(long, verbatim_doc_comment)]
     pub no_summary: bool,
+    /// Format of task summary
+    ///
+    /// The summary can be displayed as human readable text (default),
+    /// or serialized to JSON or YAML for other programs to consume.
+    /// The serialized summary contains the id, name, type, start time,
+    /// end time, end reason and details of each task.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        conflicts_with = "no_summary",
+        verbatim_doc_comment
+    )]
+    pub summary_format: SummaryFormat,
+    /// Write task summary to given file instead of stdout
+    ///
+    /// The file will be overwritten if it already exists.
+    #[arg(long, conflicts_with = "no_summary", verbatim_doc_comment)]
+    pub summary_file: Option<PathBuf>,
     /// Do not reconnect when game loses connection to server
     ///
     /// By default, maa will automatically reconnect when the game client
@@ -92,6 +129,14 @@ pub struct CommonArgs {
 }
 
 impl CommonArgs {
+    /// Display or write the task summary as requested
+    fn report_summary(&self, summary: &summary::SharedSummary) -> Result<()> {
+        if self.no_summary {
+            return Ok(());
+        }
+        summary.report(self.summary_format, self.summary_file.as_deref())
+    }
+
     pub fn apply_to(&self, config: &mut AsstConfig) {
         if let Some(addr) = self.addr.as_ref() {
             config.connection.set_address(addr);
@@ -121,20 +166,13 @@ fn find_profile(root: impl AsRef<Path>, profile: Option<&str>) -> Result<AsstCon
     }
 }
 
-fn run_core<F>(f: F, args: CommonArgs) -> Result<()>
+fn run_core<F>(f: F, args: &CommonArgs, state: &Arc<InstanceState>) -> Result<()>
 where
     F: FnOnce(&AsstConfig) -> Result<TaskConfig>,
 {
-    // Auto update hot update resource
-    installer::hot_update::update()?;
-    installer::resource::update(true)?;
-
-    // Load asst config
-    let mut asst_config = find_profile(dirs::config(), args.profile.as_deref())?;
-
-    args.apply_to(&mut asst_config);
+    let mut asst_config = load_asst_config(args)?;
 
-    let task_config = f(&asst_config)?;
+    let mut task_config = f(&asst_config)?;
     if let Some(resource) = task_config.client_type.resource() {
         asst_config.resource.use_global_resource(resource);
     }
@@ -143,8 +181,49 @@ where
     load_core().context("Failed to load MaaCore!")?;
     setup_core(&asst_config)?;
 
-    // Register signal handlers
-    let stop_bool = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let stop_bool = register_stop_signal()?;
+
+    let callback = new_callback(&asst_config, args.no_auto_reconnect, Arc::clone(state))
+        .with_profile(args.profile.as_deref());
+    let asst = create_assistant(&asst_config, callback)?;
+
+    let tasks = std::mem::take(&mut task_config.tasks);
+    if args.dry_run {
+        append_tasks(&asst, &state.summary, tasks)?;
+    } else {
+        execute(&asst, &asst_config, &task_config, tasks, state, &stop_bool)?;
+    }
+
+    // TODO: Better ways to restore signal handlers?
+    stop_bool.store(true, atomic::Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Update resources and load the profile with command line arguments applied
+fn load_asst_config(args: &CommonArgs) -> Result<AsstConfig> {
+    update_resources()?;
+
+    // Load asst config
+    let mut asst_config = find_profile(dirs::config(), args.profile.as_deref())?;
+
+    args.apply_to(&mut asst_config);
+
+    Ok(asst_config)
+}
+
+/// Auto update hot update resource
+fn update_resources() -> Result<()> {
+    installer::hot_update::update()?;
+    installer::resource::update(true)?;
+    Ok(())
+}
+
+/// Register signal handlers, the returned flag will be set when a termination signal is received
+///
+/// The first signal only sets the flag, and the second one terminates the process.
+fn register_stop_signal() -> Result<Arc<AtomicBool>> {
+    let stop_bool = Arc::new(AtomicBool::new(false));
     for sig in TERM_SIGNALS {
         signal_hook::flag::register_conditional_default(*sig, Arc::clone(&stop_bool))
             .context("Failed to register signal handler!")?;
@@ -152,16 +231,36 @@ where
             .context("Failed to register signal handler!")?;
     }
 
-    // Create and setup Assistant
-    let auto_reconnect = asst_config.behavior.auto_reconnect && !args.no_auto_reconnect;
-    let (maa_callback, offline_stop) = callback::MaaCallback::new(auto_reconnect);
-    let asst = Assistant::new_with_callback(maa_callback)
+    Ok(stop_bool)
+}
+
+/// Create the callback of an assistant, which records messages into given state
+fn new_callback(
+    asst_config: &AsstConfig,
+    no_auto_reconnect: bool,
+    state: Arc<InstanceState>,
+) -> MaaCallback {
+    let auto_reconnect = asst_config.behavior.auto_reconnect && !no_auto_reconnect;
+    MaaCallback::new(auto_reconnect, state)
+}
+
+/// Create and setup Assistant with given callback
+fn create_assistant(asst_config: &AsstConfig, callback: MaaCallback) -> Result<Assistant> {
+    let asst = Assistant::new_with_callback(callback)
         .context("Failed to create Assistant: resources may not be loaded")?;
     asst_config.instance_options.apply_to(&asst)?;
 
-    // Register tasks to Assistant and prepare summary
-    let mut task_summary = (!args.no_summary).then(summary::Summary::new);
-    for task in task_config.tasks {
+    Ok(asst)
+}
+
+/// Register all tasks to Assistant without running them, used to check tasks in dry run
+fn append_tasks(
+    asst: &Assistant,
+    summary: &summary::SharedSummary,
+    tasks: Vec<Task>,
+) -> Result<()> {
+    let mut task_summary = summary::Summary::new();
+    for task in tasks {
         let task_type = task.task_type;
         let params = serde_json::to_string_pretty(&task.params)?;
         debug!(
@@ -177,111 +276,191 @@ where
                 )
             })?;
 
-        if let Some(s) = task_summary.as_mut() {
-            s.insert(id, task.name, task_type);
-        }
-    }
-    if let Some(s) = task_summary {
-        summary::init(s);
+        task_summary.insert_task(id, task.name.clone(), &task);
     }
+    summary.init(task_summary);
 
-    if !args.dry_run {
-        // Prepare connection
-        let (adb_path, address, config) = asst_config.connection.connect_args();
-
-        // Launch external apps
-        let app: Option<Box<dyn external::ExternalApp>> = match asst_config.connection.preset() {
-            #[cfg(target_os = "macos")]
-            crate::config::asst::Preset::PlayCover => Some(Box::new(external::PlayCoverApp::new(
-                task_config.client_type,
-                address.as_ref(),
-            ))),
-            #[cfg(target_os = "linux")]
-            crate::config::asst::Preset::Waydroid => Some(Box::new(external::WaydroidApp::new())),
-            _ => None,
-        };
-
-        // Startup external app or query its runtime address if available
-        let runtime_address = app
-            .as_deref()
-            .map(|app| app.open(task_config.start_app))
-            .transpose()?
-            .flatten();
-
-        let address = runtime_address.as_deref().unwrap_or(&address);
-
-        // Connect to game or emulator
-        asst.async_connect(adb_path, address, config, true)?;
-
-        debug!("Starting MAA...");
-        asst.start()?;
-
-        while asst.running() {
-            if stop_bool.load(atomic::Ordering::Relaxed) {
-                bail!("Interrupted by user!");
-            }
-            if offline_stop.load(atomic::Ordering::Relaxed) {
-                break;
-            }
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        }
-
-        debug!("Stopping MAA...");
-        asst.stop()?;
+    Ok(())
+}
 
-        // Close external app
-        if let (Some(app), true) = (app.as_deref(), task_config.close_app) {
-            debug!("Closing external app...");
-            app.close().context("Failed to close external app")?;
-        }
+/// Connect to the game and run tasks one by one until they are finished
+///
+/// The summary is always collected to be recorded in the history, even if it is not displayed.
+fn execute(
+    asst: &Assistant,
+    asst_config: &AsstConfig,
+    task_config: &TaskConfig,
+    tasks: Vec<Task>,
+    state: &InstanceState,
+    stop_bool: &AtomicBool,
+) -> Result<()> {
+    // Prepare connection
+    let (adb_path, address, config) = asst_config.connection.connect_args();
+
+    // Launch external apps
+    let app: Option<Box<dyn external::ExternalApp>> = match asst_config.connection.preset() {
+        #[cfg(target_os = "macos")]
+        crate::config::asst::Preset::PlayCover => Some(Box::new(external::PlayCoverApp::new(
+            task_config.client_type,
+            address.as_ref(),
+        ))),
+        #[cfg(target_os = "linux")]
+        crate::config::asst::Preset::Waydroid => Some(Box::new(external::WaydroidApp::new())),
+        _ => None,
+    };
+
+    // Startup external app or query its runtime address if available
+    let runtime_address = app
+        .as_deref()
+        .map(|app| app.open(task_config.start_app))
+        .transpose()?
+        .flatten();
+
+    let address = runtime_address.as_deref().unwrap_or(&address);
+
+    // Connect to game or emulator
+    asst.async_connect(adb_path, address, config, true)?;
+
+    debug!("Starting MAA...");
+    queue::run_tasks(asst, tasks, state, stop_bool)?;
+
+    debug!("Stopping MAA...");
+    asst.stop()?;
+
+    // Close external app
+    if let (Some(app), true) = (app.as_deref(), task_config.close_app) {
+        debug!("Closing external app...");
+        app.close().context("Failed to close external app")?;
     }
 
-    // TODO: Better ways to restore signal handlers?
-    stop_bool.store(true, atomic::Ordering::Relaxed);
-
     Ok(())
 }
 
 // Wrapper for run_core, always try to display summary even if error occurred
 // It's safe to display summary even if summary is not initialized
-pub fn run<F>(f: F, args: CommonArgs) -> Result<()>
+pub fn run<F>(f: F, task: Option<&str>, args: CommonArgs) -> Result<()>
 where
     F: FnOnce(&AsstConfig) -> Result<TaskConfig>,
 {
-    let ret = run_core(f, args);
+    let start = Local::now();
+    let state = Arc::new(InstanceState::default());
+    let ret = run_core(f, &args, &state).and_then(|()| {
+        if state.errored.load(atomic::Ordering::Relaxed) {
+            bail!("Some error occurred during running task!");
+        }
+        Ok(())
+    });
 
-    summary::display();
+    if !args.dry_run {
+        finish_run(
+            &state,
+            start,
+            task,
+            None,
+            args.profile.as_deref(),
+            ret.as_ref().err().map(|e| format!("{e:#}")),
+        );
+    }
+
+    let reported = args.report_summary(&state.summary);
 
     ret?;
+    reported
+}
 
-    if callback::MAA_CORE_ERRORED.load(atomic::Ordering::Relaxed) {
-        bail!("Some error occurred during running task!");
-    }
+pub fn run_preset(params: impl preset::IntoTaskConfig, args: CommonArgs) -> Result<()> {
+    run(|config| params.into_task_config(config), None, args)
+}
 
-    Ok(())
+/// Run the daily routine, recorded as task `daily` in the history
+pub fn run_daily(params: preset::DailyParams, args: CommonArgs) -> Result<()> {
+    use preset::IntoTaskConfig;
+    let params = params.with_profile(args.profile.clone());
+    run(
+        |config| params.into_task_config(config),
+        Some("daily"),
+        args,
+    )
 }
 
-pub fn run_preset(params: impl preset::IntoTaskConfig, args: CommonArgs) -> Result<()> {
-    run(|config| params.into_task_config(config), args)
+/// Run a preset task, and export its results recognized in this run if not a dry run
+pub fn run_preset_and_export(
+    params: impl preset::IntoTaskConfig,
+    args: CommonArgs,
+    export: impl FnOnce(DateTime<Local>) -> Result<()>,
+) -> Result<()> {
+    let start = Local::now();
+    let dry_run = args.dry_run;
+    run_preset(params, args)?;
+    if !dry_run {
+        export(start)?;
+    }
+    Ok(())
 }
 
-pub fn run_custom(path: impl AsRef<Path>, args: CommonArgs) -> Result<()> {
+pub fn run_custom(path: &str, args: CommonArgs) -> Result<()> {
+    let profile = args.profile.clone();
     run(
-        |_| {
-            let path = path.as_ref();
-            let config = if let Some(abs_path) = dirs::abs_config(path, Some("tasks")) {
-                TaskConfigTemplate::find_file(abs_path)
-            } else {
-                TaskConfigTemplate::find_file(path)
-            }
-            .context("Failed to find task file!")?;
-
-            config.init().context("Failed to initialize task config!")
-        },
+        |_| load_task_file(path, profile.as_deref()),
+        Some(path),
         args,
     )
 }
 
+/// Record a finished run in the history and send notifications, failures are only logged
+fn finish_run(
+    state: &InstanceState,
+    start: DateTime<Local>,
+    task: Option<&str>,
+    job: Option<&str>,
+    profile: Option<&str>,
+    error: Option<String>,
+) {
+    let tasks = state.summary.tasks().unwrap_or_else(|e| {
+        warn!("Failed to serialize summary: {e}");
+        Vec::new()
+    });
+
+    let record = history::Record {
+        start,
+        end: Local::now(),
+        profile: profile.map(ToOwned::to_owned),
+        task: task.map(ToOwned::to_owned),
+        job: job.map(ToOwned::to_owned),
+        status: if error.is_none() {
+            history::Status::Succeeded
+        } else {
+            history::Status::Failed
+        },
+        error,
+        tasks,
+    };
+
+    if let Err(e) = history::append(&record) {
+        warn!("Failed to record run history: {e:#}");
+    }
+
+    let offline = state.offline_stop.swap(false, atomic::Ordering::Relaxed);
+    notify::notify(&record, offline);
+}
+
+/// Find a task file by name or path and initialize it, checking conditions in given profile
+///
+/// Relative paths are resolved against the `tasks` directory in the config directory first.
+fn load_task_file(path: impl AsRef<Path>, profile: Option<&str>) -> Result<TaskConfig> {
+    let path = path.as_ref();
+    let config = if let Some(abs_path) = dirs::abs_config(path, Some("tasks")) {
+        TaskConfigTemplate::find_file(abs_path)
+    } else {
+        TaskConfigTemplate::find_file(path)
+    }
+    .context("Failed to find task file!")?;
+
+    config
+        .init(profile)
+        .context("Failed to initialize task config!")
+}
+
 pub fn core_version() -> Result<String> {
     load_core()?;
 
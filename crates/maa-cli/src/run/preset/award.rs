@@ -0,0 +1,103 @@
+use maa_value::prelude::*;
+
+#[derive(clap::Args, Default)]
+pub struct AwardParams {
+    #[arg(long)]
+    /// Do not collect daily and weekly mission rewards
+    no_award: bool,
+    #[arg(long)]
+    /// Whether to collect items in the mailbox
+    mail: bool,
+    #[arg(long)]
+    /// Whether to do the free single headhunting of the day
+    recruit: bool,
+    #[arg(long)]
+    /// Whether to do the free Orundum lucky draw of events
+    orundum: bool,
+    #[arg(long)]
+    /// Whether to collect Orundum of limited-time mining permits
+    mining: bool,
+    #[arg(long)]
+    /// Whether to collect the daily reward of the monthly card
+    special_access: bool,
+}
+
+impl super::ToTaskType for AwardParams {
+    fn to_task_type(&self) -> super::TaskType {
+        super::TaskType::Award
+    }
+}
+
+impl super::IntoParameters for AwardParams {
+    fn into_parameters_no_context(self) -> anyhow::Result<MAAValue> {
+        let mut params = MAAValue::default();
+
+        if self.no_award {
+            insert!(params, "award" => false);
+        }
+        if self.mail {
+            insert!(params, "mail" => true);
+        }
+        if self.recruit {
+            insert!(params, "recruit" => true);
+        }
+        if self.orundum {
+            insert!(params, "orundum" => true);
+        }
+        if self.mining {
+            insert!(params, "mining" => true);
+        }
+        if self.special_access {
+            insert!(params, "specialaccess" => true);
+        }
+
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::command::{Command, parse_from};
+
+    #[test]
+    fn parse_award_params() {
+        fn parse<I, T>(args: I) -> MAAValue
+        where
+            I: IntoIterator<Item = T>,
+            T: Into<std::ffi::OsString> + Clone,
+        {
+            match parse_from(args).command {
+                Command::Award { params, .. } => {
+                    use super::super::{IntoParameters, TaskType, ToTaskType};
+                    assert_eq!(params.to_task_type(), TaskType::Award);
+                    params.into_parameters_no_context().unwrap()
+                }
+                _ => panic!("Not an Award command"),
+            }
+        }
+
+        assert_eq!(parse(["maa", "award"]), object!());
+        assert_eq!(
+            parse([
+                "maa",
+                "award",
+                "--no-award",
+                "--mail",
+                "--recruit",
+                "--orundum",
+                "--mining",
+                "--special-access",
+            ]),
+            object!(
+                "award" => false,
+                "mail" => true,
+                "recruit" => true,
+                "orundum" => true,
+                "mining" => true,
+                "specialaccess" => true,
+            )
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     fs,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::{Context, Result, bail};
@@ -13,19 +14,21 @@ use ureq::http::StatusCode;
 
 use super::{IntoParameters, TaskContext, ToTaskType};
 use crate::{
+    config::cli::CLI_CONFIG,
     dirs::{self, Ensure},
+    operbox::{OperBox, Operator},
     state::AGENT,
 };
 
 #[cfg(not(test))]
-const COPILOT_API: &str = "https://prts.maa.plus/copilot/get/";
-#[cfg(not(test))]
-const COPILOT_SET_API: &str = "https://prts.maa.plus/set/get?id=";
+fn api_url() -> &'static str {
+    CLI_CONFIG.copilot_config().api_url()
+}
 
 #[cfg(test)]
-const COPILOT_API: &str = "http://127.0.0.1:18080/copilot/get/";
-#[cfg(test)]
-const COPILOT_SET_API: &str = "http://127.0.0.1:18080/set/get?id=";
+fn api_url() -> &'static str {
+    "http://127.0.0.1:18080"
+}
 
 /// Raid mode for copilot stages.
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
@@ -127,6 +130,29 @@ pub struct CopilotParams {
     /// Loop times
     #[arg(long)]
     loop_times: Option<i32>,
+
+    /// Check required operators against the operator box exported by `maa operbox`
+    ///
+    /// Operators not owned or not meeting the elite, level, potential and skill requirements
+    /// are reported before the copilot starts. Skill level and module requirements are not
+    /// recognized in the operator box, and are reported as not checked.
+    #[arg(long)]
+    check: bool,
+    /// Refuse to start if the formation cannot be satisfied, implies `--check`
+    #[arg(long)]
+    strict: bool,
+
+    /// Profile whose operator box the formation is checked against, from the common arguments
+    #[arg(skip)]
+    profile: Option<String>,
+}
+
+impl CopilotParams {
+    /// Check the formation against the operator box of given profile
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -153,6 +179,20 @@ struct CopilotOperator {
     name: String,
     #[serde(default)]
     skill: i32,
+    #[serde(default)]
+    requirements: OperatorRequirements,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct OperatorRequirements {
+    elite: i64,
+    level: i64,
+    /// Not recognized by the OperBox task, so never checked
+    skill_level: i64,
+    /// Not recognized by the OperBox task, so never checked
+    module: i64,
+    potentiality: i64,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -163,6 +203,8 @@ struct CopilotGroup {
 
 #[derive(Debug, serde::Deserialize)]
 struct CopilotDoc {
+    #[serde(default)]
+    title: String,
     details: String,
 }
 
@@ -214,6 +256,21 @@ impl IntoParameters for CopilotParams {
         let ignore_requirements =
             self.ignore_requirements || default.get_or("ignore_requirements", false);
 
+        let operbox = if self.check || self.strict {
+            match OperBox::load(self.profile.as_deref())? {
+                Some(operbox) => Some(operbox),
+                None if self.strict => {
+                    bail!("No operator box to check the formation, run `maa operbox` first")
+                }
+                None => {
+                    warn!("No operator box to check the formation, run `maa operbox` first");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         let mut stage_list = Vec::new();
         for (_, file, copilot_task) in copilot_files {
             let stage_id = &copilot_task.stage_name;
@@ -221,6 +278,29 @@ impl IntoParameters for CopilotParams {
             let stage_info = get_stage_info(stage_id, base_dirs.iter().map(|dir| dir.as_path()))?;
             let stage_code = &stage_info.code;
 
+            if let Some(operbox) = &operbox {
+                let shortfalls = check_formation(&copilot_task, operbox);
+                let unchecked = unchecked_requirements(&copilot_task);
+                if shortfalls.is_empty() && unchecked.is_empty() {
+                    println!("All operators required by {stage_code} are available");
+                } else if shortfalls.is_empty() {
+                    println!(
+                        "All operators required by {stage_code} are available, \
+                        but some requirements are not checked:\n{}",
+                        shortfall_table(&shortfalls, &unchecked)
+                    );
+                } else {
+                    println!(
+                        "Unavailable operators of {stage_code} (operator box recognized at {}):\n{}",
+                        operbox.time.format("%Y-%m-%d %H:%M"),
+                        shortfall_table(&shortfalls, &unchecked)
+                    );
+                    if self.strict {
+                        bail!("The formation of {stage_code} cannot be satisfied");
+                    }
+                }
+            }
+
             if !formation {
                 println!("Operators:\n{}", operator_table(&copilot_task)?);
                 println!("Please set up your formation manually");
@@ -415,7 +495,7 @@ impl CopilotFile {
             content: String,
         }
 
-        #[derive(serde::Deserialize)]
+        #[derive(serde::Deserialize, serde::Serialize)]
         struct SetData {
             copilot_ids: Vec<u64>,
         }
@@ -425,7 +505,11 @@ impl CopilotFile {
                 let code = code.to_string();
                 let json_file = base_dir.join(&code).with_extension("json");
 
-                if json_file.is_file() {
+                let expired = json_file.is_file()
+                    && !is_fresh(&json_file, CLI_CONFIG.copilot_config().cache_ttl());
+                if expired {
+                    debug!("Cache of {} expired, re-downloading", json_file.display());
+                } else if json_file.is_file() {
                     debug!("Cache hit, using cached json file {}", json_file.display());
                     match json_from_file(&json_file) {
                         Ok(task) => {
@@ -447,12 +531,22 @@ impl CopilotFile {
                     }
                 }
 
-                let url = format!("{COPILOT_API}{code}");
+                let url = format!("{}/copilot/get/{code}", api_url());
                 debug!("Cache miss, downloading copilot from {url}");
-                let mut response = AGENT
+                let response = AGENT
                     .get(&url)
                     .call()
-                    .with_context(|| format!("Failed to send request to {url}"))?;
+                    .with_context(|| format!("Failed to send request to {url}"));
+                let mut response = match response {
+                    // Prefer an expired cache to failure, e.g. when running offline
+                    Err(e) if expired => {
+                        warn!("{e:#}, using expired cache {}", json_file.display());
+                        let task = json_from_file(&json_file)?;
+                        files.push((index, json_file, task));
+                        return Ok(());
+                    }
+                    response => response?,
+                };
 
                 let resp: CopilotResponse<SingleData> = response
                     .body_mut()
@@ -502,42 +596,56 @@ impl CopilotFile {
                 }
             }
             CopilotFile::RemoteSet(code) => {
-                let url = format!("{COPILOT_SET_API}{code}");
+                let set_file = base_dir.join("sets").join(format!("{code}.json"));
+                let url = format!("{}/set/get?id={code}", api_url());
                 debug!("Get copilot set from {url}");
-                let mut response = AGENT
+                let fetched = AGENT
                     .get(&url)
                     .call()
-                    .with_context(|| format!("Failed to send request to {url}"))?;
+                    .with_context(|| format!("Failed to send request to {url}"))
+                    .and_then(|mut response| {
+                        response
+                            .body_mut()
+                            .read_json::<CopilotResponse<SetData>>()
+                            .with_context(|| {
+                                format!("Failed to parse JSON response from {url}. The server may have disconnected or returned invalid data")
+                            })
+                    });
+
+                let set = match fetched {
+                    Ok(resp) if resp.status_code == StatusCode::OK => {
+                        // Cache the set to be used offline, e.g. imported from a bundle
+                        if let Some(parent) = set_file.parent() {
+                            parent.ensure()?;
+                        }
+                        crate::atomic_fs::write(&set_file, serde_json::to_string(&resp.data)?)
+                            .with_context(|| format!("Failed to write {}", set_file.display()))?;
+                        resp.data
+                    }
+                    Ok(_) => bail!("Request Error, code: {}", code),
+                    Err(e) if set_file.is_file() => {
+                        warn!("{e:#}, using cached copilot set {}", set_file.display());
+                        json_from_file(&set_file)?
+                    }
+                    Err(e) => return Err(e),
+                };
 
-                let resp: CopilotResponse<SetData>= response
-                    .body_mut()
-                    .read_json()
-                    .with_context(|| {
-                        format!("Failed to parse JSON response from {url}. The server may have disconnected or returned invalid data")
+                // Download all copilot files in parallel
+                let sub_files = set
+                    .copilot_ids
+                    .into_par_iter()
+                    .try_fold(Vec::new, |mut files, id| {
+                        CopilotFile::Remote(id).push_path_into::<T>(index, base_dir, &mut files)?;
+                        Ok::<_, anyhow::Error>(files)
+                    })
+                    .try_reduce(Vec::new, |mut a, b| {
+                        a.extend(b);
+                        Ok(a)
                     })?;
 
-                if resp.status_code == StatusCode::OK {
-                    let ids = resp.data.copilot_ids;
-
-                    // Download all copilot files in parallel
-                    let sub_files = ids
-                        .into_par_iter()
-                        .try_fold(Vec::new, |mut files, id| {
-                            CopilotFile::Remote(id)
-                                .push_path_into::<T>(index, base_dir, &mut files)?;
-                            Ok::<_, anyhow::Error>(files)
-                        })
-                        .try_reduce(Vec::new, |mut a, b| {
-                            a.extend(b);
-                            Ok(a)
-                        })?;
-
-                    files.extend(sub_files);
+                files.extend(sub_files);
 
-                    Ok(())
-                } else {
-                    bail!("Request Error, code: {}", code);
-                }
+                Ok(())
             }
             CopilotFile::Local(file) => {
                 let file = if file.is_absolute() {
@@ -588,6 +696,120 @@ fn operator_table(task: &CopilotTask) -> Result<Table> {
     Ok(table)
 }
 
+/// Elite phase required to use given skill (1-based)
+fn skill_elite(skill: i32) -> i64 {
+    match skill {
+        2 => 1,
+        3 => 2,
+        _ => 0,
+    }
+}
+
+/// Why an owned operator can not be used as required, `None` if it can
+fn operator_shortfall(required: &CopilotOperator, owned: &Operator) -> Option<String> {
+    let requirements = &required.requirements;
+    let elite = requirements.elite.max(skill_elite(required.skill));
+
+    if owned.elite < elite {
+        return Some(if elite > requirements.elite {
+            format!(
+                "E{} required by skill {}, owned E{}",
+                elite, required.skill, owned.elite
+            )
+        } else {
+            format!("E{elite} required, owned E{}", owned.elite)
+        });
+    }
+    if owned.elite == elite && owned.level < requirements.level {
+        return Some(format!(
+            "E{elite} Lv.{} required, owned Lv.{}",
+            requirements.level, owned.level
+        ));
+    }
+    if owned.potential < requirements.potentiality {
+        return Some(format!(
+            "potential {} required, owned {}",
+            requirements.potentiality, owned.potential
+        ));
+    }
+
+    None
+}
+
+/// Operators of a copilot task that can not be used, with the reason
+///
+/// A group is satisfied if any of its operators can be used.
+fn check_formation(task: &CopilotTask, operbox: &OperBox) -> Vec<(String, String)> {
+    let shortfall = |required: &CopilotOperator| match operbox
+        .operators
+        .iter()
+        .find(|owned| owned.name == required.name)
+    {
+        Some(owned) => operator_shortfall(required, owned),
+        None => Some("not owned".to_owned()),
+    };
+
+    let mut shortfalls = Vec::new();
+    for operator in &task.opers {
+        if let Some(reason) = shortfall(operator) {
+            shortfalls.push((operator.name.clone(), reason));
+        }
+    }
+    for group in &task.groups {
+        let reasons = group
+            .opers
+            .iter()
+            .map(|operator| Some(format!("{}: {}", operator.name, shortfall(operator)?)))
+            .collect::<Option<Vec<_>>>();
+        if let Some(reasons) = reasons {
+            let reason = if reasons.is_empty() {
+                "no operator in group".to_owned()
+            } else {
+                reasons.join("; ")
+            };
+            shortfalls.push((format!("[{}]", group.name), reason));
+        }
+    }
+
+    shortfalls
+}
+
+/// Requirements of operators that can not be checked against the operator box
+///
+/// Skill levels and modules are not recognized by the OperBox task.
+fn unchecked_requirements(task: &CopilotTask) -> Vec<(String, String)> {
+    task.opers
+        .iter()
+        .chain(task.groups.iter().flat_map(|group| &group.opers))
+        .filter_map(|operator| {
+            let requirements = &operator.requirements;
+            let mut items = Vec::new();
+            if requirements.skill_level > 0 {
+                items.push(format!("skill level {}", requirements.skill_level));
+            }
+            if requirements.module > 0 {
+                items.push(format!("module {}", requirements.module));
+            }
+            (!items.is_empty()).then(|| {
+                (
+                    operator.name.clone(),
+                    format!("{} not checked", items.join(", ")),
+                )
+            })
+        })
+        .collect()
+}
+
+fn shortfall_table(shortfalls: &[(String, String)], unchecked: &[(String, String)]) -> Table {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["NAME", "REASON"]);
+    for (name, reason) in shortfalls.iter().chain(unchecked) {
+        table.add_row(row![name, reason]);
+    }
+    table
+}
+
 /// Resolve a list of URIs into local copilot file paths.
 ///
 /// This function handles downloading remote files, expanding task sets,
@@ -611,11 +833,537 @@ fn resolve_copilot_uris(uri_list: Vec<String>) -> Result<Vec<(usize, PathBuf, Co
             a.extend(b);
             Ok(a)
         })?;
-    copilot_files.sort_by(|(index_a, ..), (index_b, ..)| index_a.cmp(index_b));
+    copilot_files.sort_by_key(|(index, ..)| *index);
 
     Ok(copilot_files)
 }
 
+/// Whether a cached file is modified within given time to live, always fresh without one
+fn is_fresh(path: &Path, ttl: Option<Duration>) -> bool {
+    let Some(ttl) = ttl else {
+        return true;
+    };
+
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < ttl)
+}
+
+/// Load a value cached in given file if it's fresh and not refreshed, otherwise fetch and cache it
+///
+/// Expired files next to the cache file are removed when it's written.
+fn cached_json<T>(
+    path: &Path,
+    ttl: Duration,
+    refresh: bool,
+    fetch: impl FnOnce() -> Result<T>,
+) -> Result<T>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    if !refresh && is_fresh(path, Some(ttl)) {
+        match json_from_file(path) {
+            Ok(value) => {
+                debug!("Cache hit, using cached json file {}", path.display());
+                return Ok(value);
+            }
+            Err(e) => warn!(
+                "Cached file {} is corrupted ({e}), fetching again",
+                path.display()
+            ),
+        }
+    }
+
+    let value = fetch()?;
+    if let Some(parent) = path.parent() {
+        parent.ensure()?;
+    }
+    crate::atomic_fs::write(path, serde_json::to_string(&value)?)
+        .with_context(|| format!("Failed to write cache file {}", path.display()))?;
+
+    if let Some(parent) = path.parent()
+        && let Err(e) = prune_expired(parent, ttl)
+    {
+        warn!(
+            "Failed to remove expired cache in {}: {e}",
+            parent.display()
+        );
+    }
+
+    Ok(value)
+}
+
+/// Remove expired json files in given cache directory
+fn prune_expired(dir: &Path, ttl: Duration) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") && !is_fresh(&path, Some(ttl)) {
+            debug!("Removing expired cache {}", path.display());
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Send a GET request to the copilot API and return the data of the response
+fn request_data<D: serde::de::DeserializeOwned>(url: &str, query: &[(&str, String)]) -> Result<D> {
+    #[derive(serde::Deserialize)]
+    struct CopilotResponse<D> {
+        status_code: u16,
+        data: Option<D>,
+    }
+
+    debug!("Requesting {url} with {query:?}");
+    let mut response = AGENT
+        .get(url)
+        .query_pairs(query.iter().map(|(key, value)| (*key, value.as_str())))
+        .call()
+        .with_context(|| format!("Failed to send request to {url}"))?;
+    let resp: CopilotResponse<D> = response
+        .body_mut()
+        .read_json()
+        .with_context(|| format!("Failed to parse JSON response from {url}"))?;
+
+    match resp.data {
+        Some(data) if resp.status_code == StatusCode::OK => Ok(data),
+        _ => bail!("Request Error, status code: {}", resp.status_code),
+    }
+}
+
+/// A copilot task on the copilot site, with its rating
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct CopilotInfo {
+    id: u64,
+    content: String,
+    #[serde(default)]
+    uploader: String,
+    #[serde(default)]
+    views: u64,
+    #[serde(default)]
+    like: u64,
+    #[serde(default)]
+    dislike: u64,
+}
+
+impl CopilotInfo {
+    fn task(&self) -> Result<CopilotTask> {
+        serde_json::from_str(&self.content)
+            .with_context(|| format!("Failed to parse copilot task {}", self.id))
+    }
+
+    /// Percentage of likes in all ratings
+    fn rating(&self) -> String {
+        match self.like + self.dislike {
+            0 => "-".to_owned(),
+            total => format!("{}%", self.like * 100 / total),
+        }
+    }
+}
+
+/// Operators required by a copilot task in a line, groups are shown in brackets
+fn operator_summary(task: &CopilotTask) -> String {
+    task.opers
+        .iter()
+        .map(|operator| format!("{}({})", operator.name, operator.skill))
+        .chain(task.groups.iter().map(|group| format!("[{}]", group.name)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum OrderBy {
+    /// Most popular recently
+    Hot,
+    /// Latest uploaded
+    Latest,
+    /// Most viewed
+    Views,
+}
+
+impl OrderBy {
+    fn to_str(self) -> &'static str {
+        match self {
+            OrderBy::Hot => "hot",
+            OrderBy::Latest => "id",
+            OrderBy::Views => "views",
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct SearchArgs {
+    /// Stage name or code, e.g. `1-7`, which is called level on the copilot site
+    #[arg(long, visible_alias = "level")]
+    stage: Option<String>,
+    /// Operators used in copilot tasks, separated by commas, prefix a name with `~` to exclude it
+    #[arg(long)]
+    operator: Option<String>,
+    /// Keyword in the title or description
+    #[arg(long)]
+    keyword: Option<String>,
+    /// Order of results
+    #[arg(long, default_value = "hot")]
+    order_by: OrderBy,
+    /// Number of results to show
+    #[arg(short = 'n', long, default_value_t = 10)]
+    limit: u32,
+    /// Search again even if results are cached
+    #[arg(long)]
+    refresh: bool,
+}
+
+impl SearchArgs {
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("page", "1".to_owned()),
+            ("limit", self.limit.to_string()),
+            ("orderBy", self.order_by.to_str().to_owned()),
+            ("desc", "true".to_owned()),
+        ];
+        if let Some(stage) = &self.stage {
+            query.push(("levelKeyword", stage.clone()));
+        }
+        if let Some(operator) = &self.operator {
+            query.push(("operator", operator.clone()));
+        }
+        if let Some(keyword) = &self.keyword {
+            query.push(("document", keyword.clone()));
+        }
+        query
+    }
+}
+
+#[derive(clap::Subcommand)]
+pub enum CopilotCommand {
+    /// Search copilot tasks on the copilot site
+    ///
+    /// Results are cached in the copilot cache directory, see `search_ttl` in the CLI config.
+    Search(SearchArgs),
+    /// Show a copilot task with its rating, operators and description
+    Show {
+        /// Code of the copilot task, e.g. `1234` or `maa://1234`
+        code: String,
+    },
+    /// Export copilot tasks into a zip bundle, to be imported on machines without internet access
+    Export {
+        /// URIs of copilot tasks, in the same format as running them
+        #[arg(required = true)]
+        uri_list: Vec<String>,
+        /// Path of the bundle
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Import copilot tasks from a zip bundle exported by `maa copilot export`
+    ///
+    /// Copilot tasks are saved into the copilot cache directory, so they can be run by their
+    /// URIs without internet access.
+    Import {
+        /// Path of the bundle
+        bundle: PathBuf,
+    },
+}
+
+/// Manifest of a copilot bundle
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(serde::Deserialize, serde::Serialize)]
+struct BundleManifest {
+    /// Version of the bundle format
+    version: u32,
+    /// URIs of copilot tasks given to export, local files are referred by their names in the
+    /// bundle
+    uris: Vec<String>,
+    /// Files in the bundle, relative to the copilot cache directory
+    files: Vec<String>,
+}
+
+const BUNDLE_VERSION: u32 = 1;
+const BUNDLE_MANIFEST: &str = "manifest.json";
+
+/// Pack resolved copilot files into a zip bundle, files are stored by paths in the copilot
+/// cache directory if possible, otherwise by their file names
+///
+/// URIs of local files are rewritten to their names in the bundle, which are resolved
+/// against the copilot cache directory after the bundle is imported.
+fn export_bundle(
+    uris: Vec<String>,
+    files: &[PathBuf],
+    copilot_dir: &Path,
+    output: &Path,
+) -> Result<BundleManifest> {
+    use std::io::Write;
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    let mut entries: Vec<(String, PathBuf)> = Vec::new();
+    for file in files {
+        let name = match file.strip_prefix(copilot_dir) {
+            Ok(relative) => relative.to_str(),
+            Err(_) => file.file_name().and_then(|name| name.to_str()),
+        }
+        .with_context(|| format!("Invalid copilot file name {}", file.display()))?
+        .replace('\\', "/");
+
+        match entries.iter().find(|(exist, _)| *exist == name) {
+            Some((_, exist)) if exist == file => continue,
+            Some((_, exist)) => bail!(
+                "Copilot files {} and {} have the same name",
+                exist.display(),
+                file.display()
+            ),
+            None => entries.push((name, file.clone())),
+        }
+    }
+
+    // Sets are cached when resolved, include them to resolve sets offline
+    for uri in &uris {
+        if let CopilotFile::RemoteSet(code) = CopilotFile::from_uri(uri)? {
+            let name = format!("sets/{code}.json");
+            let path = copilot_dir.join(&name);
+            if path.is_file() && !entries.iter().any(|(exist, _)| *exist == name) {
+                entries.push((name, path));
+            }
+        }
+    }
+
+    let uris = uris
+        .into_iter()
+        .map(|uri| match CopilotFile::from_uri(&uri) {
+            Ok(CopilotFile::Local(path)) => {
+                let path = if path.is_absolute() {
+                    path
+                } else {
+                    copilot_dir.join(path)
+                };
+                entries
+                    .iter()
+                    .find(|(_, file)| *file == path)
+                    .map_or(uri, |(name, _)| name.clone())
+            }
+            _ => uri,
+        })
+        .collect();
+
+    let manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        uris,
+        files: entries.iter().map(|(name, _)| name.clone()).collect(),
+    };
+
+    let write = || -> Result<()> {
+        let mut zip = ZipWriter::new(fs::File::create(output)?);
+        let options = SimpleFileOptions::default();
+        for (name, path) in &entries {
+            zip.start_file(name, options)?;
+            zip.write_all(
+                &fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?,
+            )?;
+        }
+        zip.start_file(BUNDLE_MANIFEST, options)?;
+        zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+        zip.finish()?;
+        Ok(())
+    };
+    write().with_context(|| format!("Failed to write bundle {}", output.display()))?;
+
+    Ok(manifest)
+}
+
+fn search(args: &SearchArgs, copilot_dir: &Path, ttl: Duration) -> Result<Vec<CopilotInfo>> {
+    #[derive(serde::Deserialize)]
+    struct QueryData {
+        data: Vec<CopilotInfo>,
+    }
+
+    let query = args.query();
+    let cache = copilot_dir.join("search").join(search_cache_name(&query)?);
+
+    cached_json(&cache, ttl, args.refresh, || {
+        let url = format!("{}/copilot/query", api_url());
+        Ok(request_data::<QueryData>(&url, &query)?.data)
+    })
+}
+
+/// Name of the cache file of a search query, stable across builds and platforms
+fn search_cache_name(query: &[(&str, String)]) -> Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let digest = Sha256::digest(serde_json::to_vec(query)?);
+    let hex: String = digest[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok(format!("{hex}.json"))
+}
+
+fn show(code: u64, copilot_dir: &Path, ttl: Duration) -> Result<CopilotInfo> {
+    let cache = copilot_dir.join("info").join(format!("{code}.json"));
+    let info: CopilotInfo = cached_json(&cache, ttl, false, || {
+        request_data(&format!("{}/copilot/get/{code}", api_url()), &[])
+    })?;
+
+    // Save the task file as well, so it's not downloaded again when running it
+    let json_file = copilot_dir.join(format!("{code}.json"));
+    if !json_file.is_file() {
+        crate::atomic_fs::write(&json_file, &info.content)
+            .with_context(|| format!("Failed to write {}", json_file.display()))?;
+    }
+
+    Ok(info)
+}
+
+fn search_table(results: &[CopilotInfo]) -> Result<Table> {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row![
+        "CODE",
+        "STAGE",
+        "TITLE",
+        "RATING",
+        "VIEWS",
+        "OPERATORS"
+    ]);
+    for info in results {
+        let task = info.task()?;
+        let title = task.doc.as_ref().map_or("", |doc| doc.title.as_str());
+        table.add_row(row![
+            format!("maa://{}", info.id),
+            task.stage_name,
+            title,
+            info.rating(),
+            info.views,
+            operator_summary(&task),
+        ]);
+    }
+    Ok(table)
+}
+
+/// Unpack a bundle into the copilot cache directory, returning its manifest
+///
+/// URIs of local files not referred by their names in the bundle, e.g. in bundles exported
+/// by older versions, are rewritten to the bundled files with the same file names.
+fn import_bundle(bundle: &Path, copilot_dir: &Path) -> Result<BundleManifest> {
+    use maa_installer::extract::ArchiveFile;
+
+    let temp = tempfile::tempdir()?;
+    ArchiveFile::new(bundle)
+        .extract(indicatif::ProgressBar::hidden(), |path| {
+            Some(temp.path().join(path))
+        })
+        .with_context(|| format!("Failed to extract bundle {}", bundle.display()))?;
+
+    let mut manifest: BundleManifest = json_from_file(temp.path().join(BUNDLE_MANIFEST))
+        .with_context(|| format!("Failed to read the manifest of bundle {}", bundle.display()))?;
+    if manifest.version > BUNDLE_VERSION {
+        bail!(
+            "Unsupported bundle version {}, please update maa-cli",
+            manifest.version
+        );
+    }
+
+    for name in &manifest.files {
+        let relative = Path::new(name);
+        if !relative
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+        {
+            bail!("Invalid file {name} in bundle");
+        }
+
+        let src = temp.path().join(relative);
+        json_from_file::<serde_json::Value>(&src)
+            .with_context(|| format!("Invalid file {name} in bundle"))?;
+
+        let dst = copilot_dir.join(relative);
+        if let Some(parent) = dst.parent() {
+            parent.ensure()?;
+        }
+        fs::copy(&src, &dst).with_context(|| format!("Failed to save {}", dst.display()))?;
+    }
+
+    for uri in &mut manifest.uris {
+        let Ok(CopilotFile::Local(path)) = CopilotFile::from_uri(uri) else {
+            continue;
+        };
+        if manifest.files.iter().any(|name| Path::new(name) == path) {
+            continue;
+        }
+        if let Some(name) = manifest
+            .files
+            .iter()
+            .find(|name| Path::new(name).file_name() == path.file_name())
+        {
+            *uri = name.clone();
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Search and show copilot tasks without running them
+pub fn browse_copilot(command: CopilotCommand) -> Result<()> {
+    let copilot_dir = dirs::copilot().ensure()?;
+    let ttl = CLI_CONFIG.copilot_config().search_ttl();
+
+    match command {
+        CopilotCommand::Search(args) => {
+            let results = search(&args, copilot_dir, ttl)?;
+            if results.is_empty() {
+                println!("No copilot task found");
+            } else {
+                search_table(&results)?.printstd();
+            }
+        }
+        CopilotCommand::Show { code } => {
+            let code = match CopilotFile::from_uri(&code) {
+                Ok(CopilotFile::Remote(code)) => code,
+                _ => bail!("Invalid code {code}, expected something like `maa://1234`"),
+            };
+            let info = show(code, copilot_dir, ttl)?;
+            let task = info.task()?;
+
+            if let Some(doc) = &task.doc {
+                println!("{}", doc.title);
+            }
+            println!("Code: maa://{code}");
+            println!("Stage: {}", task.stage_name);
+            println!(
+                "Uploader: {}, rating: {}, views: {}",
+                info.uploader,
+                info.rating(),
+                info.views
+            );
+            println!("Operators:\n{}", operator_table(&task)?);
+            if let Some(doc) = &task.doc {
+                println!("{}", doc.details);
+            }
+        }
+        CopilotCommand::Export { uri_list, output } => {
+            let files: Vec<_> = resolve_copilot_uris(uri_list.clone())?
+                .into_iter()
+                .map(|(_, file, _)| file)
+                .collect();
+            let manifest = export_bundle(uri_list, &files, copilot_dir, &output)?;
+            println!(
+                "Exported {} files to {}",
+                manifest.files.len(),
+                output.display()
+            );
+        }
+        CopilotCommand::Import { bundle } => {
+            let manifest = import_bundle(&bundle, copilot_dir)?;
+            println!(
+                "Imported {} files, run them by: {}",
+                manifest.files.len(),
+                manifest.uris.join(" ")
+            );
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg_attr(test, derive(Default))]
 #[derive(clap::Args)]
 pub struct ParadoxCopilotParams {
@@ -695,6 +1443,20 @@ mod tests {
                         continue;
                     }
 
+                    // Handle /copilot/query?..., results are the same for all queries
+                    if url.starts_with("/copilot/query") {
+                        let content = fs::read_to_string(fixtures_dir.join("query.json")).unwrap();
+                        let response = tiny_http::Response::from_string(content).with_header(
+                            tiny_http::Header::from_bytes(
+                                &b"Content-Type"[..],
+                                &b"application/json"[..],
+                            )
+                            .unwrap(),
+                        );
+                        let _ = request.respond(response);
+                        continue;
+                    }
+
                     // Handle /set/get?id={set_id}
                     if url.starts_with("/set/get") {
                         if let Some(query) = url.split('?').nth(1) {
@@ -1868,6 +2630,422 @@ found"}"#,
         assert_eq!(operator_table(&task).unwrap(), expected_table);
     }
 
+    #[test]
+    fn check_formation_against_operbox() {
+        fn operator(name: &str, elite: i64, level: i64, potential: i64) -> Operator {
+            Operator {
+                id: String::new(),
+                name: name.to_owned(),
+                rarity: 6,
+                elite,
+                level,
+                potential,
+            }
+        }
+
+        let operbox = OperBox {
+            time: chrono::Local::now(),
+            operators: vec![
+                operator("桃金娘", 2, 1, 1),
+                operator("夜莺", 1, 90, 1),
+                operator("史尔特尔", 2, 40, 1),
+                operator("蜜莓", 1, 50, 6),
+                operator("艾雅法拉", 2, 90, 3),
+            ],
+        };
+        let task: CopilotTask = serde_json::from_value(serde_json::json!({
+            "stage_name": "test_stage",
+            "opers": [
+                { "name": "桃金娘", "skill": 1 },
+                { "name": "夜莺", "skill": 3 },
+                { "name": "史尔特尔", "skill": 3, "requirements": { "elite": 2, "level": 60 } },
+                { "name": "艾雅法拉", "skill": 2, "requirements": { "potentiality": 5 } },
+                { "name": "桃金娘", "skill": 1, "requirements": { "skill_level": 7, "module": 1 } },
+                { "name": "U-Official" },
+            ],
+            "groups": [
+                {
+                    "name": "行医",
+                    "opers": [
+                        { "name": "纯烬艾雅法拉", "skill": 1 },
+                        { "name": "蜜莓", "skill": 1 },
+                    ],
+                },
+                {
+                    "name": "狙击",
+                    "opers": [
+                        { "name": "能天使", "skill": 3 },
+                        { "name": "蜜莓", "skill": 2, "requirements": { "elite": 2, "module": 2 } },
+                    ],
+                },
+            ],
+        }))
+        .unwrap();
+
+        let shortfalls = check_formation(&task, &operbox);
+        let expected = [
+            ("夜莺", "E2 required by skill 3, owned E1"),
+            ("史尔特尔", "E2 Lv.60 required, owned Lv.40"),
+            ("艾雅法拉", "potential 5 required, owned 3"),
+            ("U-Official", "not owned"),
+            ("[狙击]", "能天使: not owned; 蜜莓: E2 required, owned E1"),
+        ];
+        assert_eq!(
+            shortfalls,
+            expected.map(|(name, reason)| (name.to_owned(), reason.to_owned()))
+        );
+
+        let mut expected_table = Table::new();
+        expected_table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        expected_table.set_titles(row!["NAME", "REASON"]);
+        expected_table.add_row(row!["U-Official", "not owned"]);
+        assert_eq!(shortfall_table(&shortfalls[3..4], &[]), expected_table);
+
+        // skill levels and modules are reported as not checked
+        let unchecked = unchecked_requirements(&task);
+        assert_eq!(unchecked, [
+            (
+                "桃金娘".to_owned(),
+                "skill level 7, module 1 not checked".to_owned()
+            ),
+            ("蜜莓".to_owned(), "module 2 not checked".to_owned()),
+        ]);
+        expected_table.add_row(row!["蜜莓", "module 2 not checked"]);
+        assert_eq!(
+            shortfall_table(&shortfalls[3..4], &unchecked[1..]),
+            expected_table
+        );
+    }
+
+    mod browse {
+        use super::*;
+
+        fn parse_command<I, T>(args: I) -> Option<CopilotCommand>
+        where
+            I: IntoIterator<Item = T>,
+            T: Into<std::ffi::OsString> + Clone,
+        {
+            match crate::command::parse_from(args).command {
+                crate::Command::Copilot { command, .. } => command,
+                _ => panic!("Not a Copilot command"),
+            }
+        }
+
+        fn parse_search<I, T>(args: I) -> SearchArgs
+        where
+            I: IntoIterator<Item = T>,
+            T: Into<std::ffi::OsString> + Clone,
+        {
+            match parse_command(args) {
+                Some(CopilotCommand::Search(args)) => args,
+                _ => panic!("Not a search command"),
+            }
+        }
+
+        #[test]
+        fn parse_subcommands() {
+            assert!(parse_command(["maa", "copilot", "maa://40051"]).is_none());
+            assert!(matches!(
+                parse_command(["maa", "copilot", "show", "maa://40051"]),
+                Some(CopilotCommand::Show { code }) if code == "maa://40051"
+            ));
+
+            fn query(query: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+                query
+                    .iter()
+                    .map(|&(key, value)| (key, value.to_owned()))
+                    .collect()
+            }
+            assert_eq!(
+                parse_search(["maa", "copilot", "search"]).query(),
+                query(&[
+                    ("page", "1"),
+                    ("limit", "10"),
+                    ("orderBy", "hot"),
+                    ("desc", "true")
+                ])
+            );
+            assert_eq!(
+                parse_search([
+                    "maa",
+                    "copilot",
+                    "search",
+                    "--level",
+                    "1-7",
+                    "--operator",
+                    "夜莺,~桃金娘",
+                    "--keyword",
+                    "low level",
+                    "--order-by",
+                    "latest",
+                    "-n5",
+                ])
+                .query(),
+                query(&[
+                    ("page", "1"),
+                    ("limit", "5"),
+                    ("orderBy", "id"),
+                    ("desc", "true"),
+                    ("levelKeyword", "1-7"),
+                    ("operator", "夜莺,~桃金娘"),
+                    ("document", "low level"),
+                ])
+            );
+        }
+
+        #[test]
+        fn fresh_cache() {
+            let test_root = temp_dir().join("maa-test-copilot-fresh-cache");
+            fs::create_dir_all(&test_root).unwrap();
+            let file = test_root.join("cached.json");
+            fs::write(&file, "{}").unwrap();
+
+            assert!(is_fresh(&file, None));
+            assert!(is_fresh(&file, Some(Duration::from_secs(3600))));
+            assert!(!is_fresh(&file, Some(Duration::ZERO)));
+            assert!(!is_fresh(
+                &test_root.join("missing.json"),
+                Some(Duration::MAX)
+            ));
+
+            fs::remove_dir_all(&test_root).unwrap();
+        }
+
+        #[test]
+        fn search_and_cache() {
+            ensure_test_server();
+
+            let test_root = temp_dir().join("maa-test-copilot-search");
+            let _ = fs::remove_dir_all(&test_root);
+            fs::create_dir_all(&test_root).unwrap();
+            let ttl = Duration::from_secs(3600);
+
+            let args = parse_search(["maa", "copilot", "search", "--stage", "AS-EX-1"]);
+            let results = search(&args, &test_root, ttl).unwrap();
+            let ids: Vec<_> = results.iter().map(|info| info.id).collect();
+            assert_eq!(ids, [40051, 40052]);
+            assert_eq!(results[0].rating(), "98%");
+            assert_eq!(
+                operator_summary(&results[1].task().unwrap()),
+                "煌(2), 艾雅法拉(2), 伊芙利特(2), 能天使(3), [奶盾], [群奶]"
+            );
+            assert_eq!(search_table(&results).unwrap().len(), 2);
+
+            // results are cached by query
+            let cached: Vec<_> = fs::read_dir(test_root.join("search")).unwrap().collect();
+            assert_eq!(cached.len(), 1);
+            let cache = cached[0].as_ref().unwrap().path();
+            fs::write(&cache, "[]").unwrap();
+            assert!(search(&args, &test_root, ttl).unwrap().is_empty());
+
+            let args = parse_search([
+                "maa",
+                "copilot",
+                "search",
+                "--stage",
+                "AS-EX-1",
+                "--refresh",
+            ]);
+            assert_eq!(search(&args, &test_root, ttl).unwrap().len(), 2);
+
+            // cache names are stable, and expired entries are removed when writing
+            assert_eq!(
+                cache.file_name().unwrap(),
+                search_cache_name(&args.query()).unwrap().as_str()
+            );
+            let expired = test_root.join("search").join("expired.json");
+            fs::write(&expired, "[]").unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&expired)
+                .unwrap()
+                .set_modified(std::time::SystemTime::now() - 2 * ttl)
+                .unwrap();
+            assert_eq!(search(&args, &test_root, ttl).unwrap().len(), 2);
+            assert!(!expired.exists());
+            assert!(cache.exists());
+
+            fs::remove_dir_all(&test_root).unwrap();
+        }
+
+        #[test]
+        fn stable_search_cache_name() {
+            let query = [
+                ("page", "1".to_owned()),
+                ("levelKeyword", "AS-EX-1".to_owned()),
+            ];
+            assert_eq!(search_cache_name(&query).unwrap(), "678e0dc8fcfc98c9.json");
+            assert_ne!(
+                search_cache_name(&query[..1]).unwrap(),
+                search_cache_name(&query).unwrap()
+            );
+        }
+
+        #[test]
+        fn show_and_cache() {
+            ensure_test_server();
+
+            let test_root = temp_dir().join("maa-test-copilot-show");
+            let _ = fs::remove_dir_all(&test_root);
+            fs::create_dir_all(&test_root).unwrap();
+
+            let info = show(40051, &test_root, Duration::from_secs(3600)).unwrap();
+            assert_eq!(info.id, 40051);
+            assert_eq!(info.uploader, "星之海.");
+            assert!(test_root.join("info").join("40051.json").is_file());
+
+            // the task file is saved to be used when running it
+            let task: CopilotTask = json_from_file(test_root.join("40051.json")).unwrap();
+            assert_eq!(task.stage_name, "act35side_ex01");
+            assert_eq!(
+                task.doc.unwrap().title,
+                "太阳甩在身后 - AS-EX-1 - 小偷与收款人(含刻章）"
+            );
+
+            assert!(show(1, &test_root, Duration::from_secs(3600)).is_err());
+
+            fs::remove_dir_all(&test_root).unwrap();
+        }
+    }
+
+    mod bundle {
+        use super::*;
+
+        #[test]
+        fn export_and_import() {
+            ensure_test_server();
+
+            let test_root = temp_dir().join("maa-test-copilot-bundle");
+            let _ = fs::remove_dir_all(&test_root);
+            let online = test_root.join("online");
+            let offline = test_root.join("offline");
+            fs::create_dir_all(&online).unwrap();
+            fs::create_dir_all(&offline).unwrap();
+
+            let local = test_root.join("local.json");
+            fs::write(&local, r#"{ "stage_name": "main_01-07", "opers": [] }"#).unwrap();
+
+            let uris = ["maa://23125s", "maa://40051", local.to_str().unwrap()]
+                .map(ToOwned::to_owned)
+                .to_vec();
+            let mut files = Vec::new();
+            for (index, uri) in uris.iter().enumerate() {
+                CopilotFile::from_uri(uri)
+                    .unwrap()
+                    .push_path_into::<CopilotTask>(index, &online, &mut files)
+                    .unwrap();
+            }
+            let files: Vec<_> = files.into_iter().map(|(_, file, _)| file).collect();
+
+            let bundle = test_root.join("bundle.zip");
+            let manifest = export_bundle(uris.clone(), &files, &online, &bundle).unwrap();
+            // 40051 is in the set, and is only packed once
+            let expected_files = [
+                "40051.json",
+                "40052.json",
+                "40053.json",
+                "40055.json",
+                "40056.json",
+                "40057.json",
+                "40058.json",
+                "40059.json",
+                "local.json",
+                "sets/23125.json",
+            ];
+            let mut names = manifest.files.clone();
+            names.sort();
+            assert_eq!(names, expected_files);
+            // local files are referred by their names in the bundle
+            assert_eq!(manifest.uris, ["maa://23125s", "maa://40051", "local.json"]);
+
+            let imported = import_bundle(&bundle, &offline).unwrap();
+            assert_eq!(imported, manifest);
+            for name in expected_files {
+                assert_eq!(
+                    fs::read(offline.join(name)).unwrap(),
+                    fs::read(online.join(name)).unwrap_or_else(|_| fs::read(&local).unwrap()),
+                );
+            }
+
+            // imported URIs resolve to the imported files
+            let mut files = Vec::new();
+            for (index, uri) in imported.uris.iter().enumerate() {
+                CopilotFile::from_uri(uri)
+                    .unwrap()
+                    .push_path_into::<CopilotTask>(index, &offline, &mut files)
+                    .unwrap();
+            }
+            assert!(files.iter().all(|(_, file, _)| file.starts_with(&offline)));
+            assert!(
+                files
+                    .iter()
+                    .any(|(_, file, _)| *file == offline.join("local.json"))
+            );
+
+            // absolute local URIs of older bundles are rewritten on import
+            let legacy = test_root.join("legacy.zip");
+            {
+                use std::io::Write;
+                let options = zip::write::SimpleFileOptions::default();
+                let mut zip = zip::ZipWriter::new(fs::File::create(&legacy).unwrap());
+                zip.start_file("local.json", options).unwrap();
+                zip.write_all(&fs::read(&local).unwrap()).unwrap();
+                zip.start_file(BUNDLE_MANIFEST, options).unwrap();
+                let manifest = BundleManifest {
+                    version: 1,
+                    uris: vec![local.to_str().unwrap().to_owned()],
+                    files: vec!["local.json".to_owned()],
+                };
+                zip.write_all(&serde_json::to_vec(&manifest).unwrap())
+                    .unwrap();
+                zip.finish().unwrap();
+            }
+            assert_eq!(import_bundle(&legacy, &offline).unwrap().uris, [
+                "local.json"
+            ]);
+
+            // cached sets are used if they can not be fetched
+            fs::copy(
+                offline.join("sets/23125.json"),
+                offline.join("sets/99999.json"),
+            )
+            .unwrap();
+            let mut files = Vec::new();
+            CopilotFile::RemoteSet(99999)
+                .push_path_into::<CopilotTask>(0, &offline, &mut files)
+                .unwrap();
+            assert_eq!(files.len(), 8);
+
+            // files with the same name can not be packed together
+            let other = test_root.join("other");
+            fs::create_dir_all(&other).unwrap();
+            fs::copy(&local, other.join("local.json")).unwrap();
+            assert!(
+                export_bundle(
+                    Vec::new(),
+                    &[local.clone(), other.join("local.json")],
+                    &online,
+                    &bundle
+                )
+                .is_err()
+            );
+
+            // bundles without manifest are rejected
+            let invalid = test_root.join("invalid.zip");
+            {
+                use std::io::Write;
+                let mut zip = zip::ZipWriter::new(fs::File::create(&invalid).unwrap());
+                zip.start_file("40051.json", zip::write::SimpleFileOptions::default())
+                    .unwrap();
+                zip.write_all(b"{}").unwrap();
+                zip.finish().unwrap();
+            }
+            assert!(import_bundle(&invalid, &offline).is_err());
+
+            fs::remove_dir_all(&test_root).unwrap();
+        }
+    }
+
     mod paradox_copilot_params {
         use super::*;
 
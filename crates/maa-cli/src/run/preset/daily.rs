@@ -0,0 +1,267 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+
+use super::{
+    AwardParams, FightParams, InfrastParams, IntoParameters, IntoTaskConfig, MallParams,
+    RecruitParams, ToTaskType, into_task,
+};
+use crate::config::{
+    asst::AsstConfig,
+    daily::{DailyConfig, Step},
+    task::{ClientType, TaskConfig, TaskConfigTemplate, TaskTemplate},
+};
+
+#[derive(clap::Args)]
+pub struct DailyParams {
+    #[arg(long)]
+    /// Client type of the game, used to start and close the game, default to Official
+    client_type: Option<ClientType>,
+    #[arg(short, long)]
+    /// Stage to fight, e.g. 1-7, leave empty to fight current/last stage
+    stage: Option<String>,
+    #[arg(short, long)]
+    /// Number of medicine (Sanity Potion) used to fight, default to 0
+    medicine: Option<i32>,
+    #[arg(long)]
+    /// Number of expiring medicine (Sanity Potion) used to fight, default to 0
+    expiring_medicine: Option<i32>,
+    #[arg(long)]
+    /// Number of stone (Originite Prime) used to fight, default to 0
+    stone: Option<i32>,
+    #[arg(long)]
+    /// Number of recruitments, default to 4
+    recruit_times: Option<i32>,
+    #[arg(long)]
+    /// Custom infrast plan file, leave empty to shift operators automatically
+    infrast_plan: Option<PathBuf>,
+    #[arg(long, value_delimiter = ',', value_name = "STEP")]
+    /// Steps to skip, e.g. `--skip mall,award`
+    ///
+    /// If given, steps to skip in the `[daily]` section of the profile are ignored.
+    skip: Vec<Step>,
+
+    /// Profile whose history and depot conditions are checked in, from the common arguments
+    #[arg(skip)]
+    profile: Option<String>,
+}
+
+impl DailyParams {
+    /// Check conditions of the routine in given profile
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Override the `[daily]` section of the profile with given options
+    fn apply_to(self, config: &mut DailyConfig) {
+        if let Some(client_type) = self.client_type {
+            config.client_type = Some(client_type);
+        }
+        if let Some(stage) = self.stage {
+            config.stage = Some(stage);
+        }
+        if let Some(medicine) = self.medicine {
+            config.medicine = Some(medicine);
+        }
+        if let Some(expiring_medicine) = self.expiring_medicine {
+            config.expiring_medicine = Some(expiring_medicine);
+        }
+        if let Some(stone) = self.stone {
+            config.stone = Some(stone);
+        }
+        if let Some(recruit_times) = self.recruit_times {
+            config.recruit_times = Some(recruit_times);
+        }
+        if let Some(infrast_plan) = self.infrast_plan {
+            config.infrast_plan = Some(infrast_plan);
+        }
+        if !self.skip.is_empty() {
+            config.skip = self.skip;
+        }
+    }
+}
+
+impl IntoTaskConfig for DailyParams {
+    fn into_task_config(self, config: &AsstConfig) -> Result<TaskConfig> {
+        let profile = self.profile.clone();
+        let mut daily = config.daily.clone();
+        self.apply_to(&mut daily);
+        daily_template(&daily, config)?.init(profile.as_deref())
+    }
+}
+
+fn template<T>(params: T, config: &AsstConfig) -> Result<TaskTemplate>
+where
+    T: ToTaskType + IntoParameters,
+{
+    let task = into_task(params, config)?;
+    Ok(TaskTemplate::new(task.task_type, task.params.into()))
+}
+
+/// Build tasks of the daily routine, startup and closedown tasks are left to the template
+fn daily_template(daily: &DailyConfig, config: &AsstConfig) -> Result<TaskConfigTemplate> {
+    use clap::ValueEnum;
+
+    if Step::value_variants()
+        .iter()
+        .all(|&step| daily.skipped(step))
+    {
+        bail!("All steps of the daily routine are skipped");
+    }
+
+    let mut tasks = Vec::new();
+
+    if !daily.skipped(Step::Fight) {
+        let mut fight = FightParams::default();
+        fight.stage = daily.stage.clone();
+        fight.medicine = daily.medicine;
+        fight.expiring_medicine = daily.expiring_medicine;
+        fight.stone = daily.stone;
+        tasks.push(template(fight, config)?);
+    }
+    if !daily.skipped(Step::Recruit) {
+        let mut recruit = RecruitParams::default();
+        recruit.times = daily.recruit_times;
+        tasks.push(template(recruit, config)?);
+    }
+    if !daily.skipped(Step::Infrast) {
+        let mut infrast = InfrastParams::default();
+        infrast.plan = daily.infrast_plan.clone();
+        tasks.push(template(infrast, config)?);
+    }
+    if !daily.skipped(Step::Mall) {
+        tasks.push(template(MallParams::default(), config)?);
+    }
+    if !daily.skipped(Step::Award) {
+        tasks.push(template(AwardParams::default(), config)?);
+    }
+
+    Ok(TaskConfigTemplate::new(
+        daily.client_type,
+        !daily.skipped(Step::Startup),
+        !daily.skipped(Step::Closedown),
+        tasks,
+    ))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use maa_types::TaskType;
+    use maa_value::prelude::*;
+
+    use super::*;
+    use crate::command::{Command, parse_from};
+
+    fn parse<I, T>(args: I) -> DailyParams
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        match parse_from(args).command {
+            Command::Daily { params, .. } => params,
+            _ => panic!("Not a Daily command"),
+        }
+    }
+
+    fn task_types(config: &TaskConfig) -> Vec<TaskType> {
+        config.tasks.iter().map(|task| task.task_type).collect()
+    }
+
+    #[test]
+    fn apply_to() {
+        let mut config = DailyConfig {
+            stage: Some("1-7".to_owned()),
+            medicine: Some(1),
+            recruit_times: Some(3),
+            skip: vec![Step::Mall],
+            ..Default::default()
+        };
+        parse([
+            "maa",
+            "daily",
+            "--client-type=YoStarEN",
+            "-sCE-6",
+            "-m2",
+            "--skip=award,closedown",
+        ])
+        .apply_to(&mut config);
+
+        assert_eq!(config, DailyConfig {
+            client_type: Some(ClientType::YoStarEN),
+            stage: Some("CE-6".to_owned()),
+            medicine: Some(2),
+            recruit_times: Some(3),
+            skip: vec![Step::Award, Step::Closedown],
+            ..Default::default()
+        });
+
+        let mut unchanged = config.clone();
+        parse(["maa", "daily"]).apply_to(&mut unchanged);
+        assert_eq!(unchanged, config);
+
+        use clap::Parser;
+        assert!(crate::command::Cli::try_parse_from(["maa", "daily", "--skip=kitchen"]).is_err());
+    }
+
+    #[test]
+    fn into_task_config() {
+        use TaskType::*;
+
+        let config = AsstConfig::default();
+        let task_config = parse(["maa", "daily", "-s1-7", "-m2", "--recruit-times=3"])
+            .into_task_config(&config)
+            .unwrap();
+
+        assert_eq!(task_types(&task_config), [
+            StartUp, Fight, Recruit, Infrast, Mall, Award, CloseDown
+        ]);
+        assert!(task_config.start_app);
+        assert!(task_config.close_app);
+        assert_eq!(
+            task_config.tasks[0].params,
+            object!("client_type" => "Official", "start_game_enabled" => true)
+        );
+        assert_eq!(
+            task_config.tasks[1].params,
+            object!(
+                "stage" => "1-7",
+                "DrGrandet" => false,
+                "medicine" => 2,
+                "client_type" => "Official",
+            )
+        );
+        assert_eq!(
+            task_config.tasks[2].params.get_typed::<i32>("times"),
+            Some(3)
+        );
+
+        let config = AsstConfig {
+            daily: DailyConfig {
+                client_type: Some(ClientType::YoStarJP),
+                skip: vec![Step::Startup, Step::Infrast, Step::Closedown],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let task_config = parse(["maa", "daily"]).into_task_config(&config).unwrap();
+        assert_eq!(task_types(&task_config), [Fight, Recruit, Mall, Award]);
+        assert!(!task_config.start_app);
+        assert!(!task_config.close_app);
+        assert_eq!(task_config.client_type, ClientType::YoStarJP);
+
+        // steps to skip given in command line override the profile
+        let task_config = parse(["maa", "daily", "--skip=fight,recruit,mall,award"])
+            .into_task_config(&config)
+            .unwrap();
+        assert_eq!(task_types(&task_config), [StartUp, Infrast, CloseDown]);
+
+        let all = "--skip=startup,fight,recruit,infrast,mall,award,closedown";
+        assert!(
+            parse(["maa", "daily", all])
+                .into_task_config(&config)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use std::{borrow::Cow, fmt::Write, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
+use log::info;
+use maa_value::prelude::*;
+use serde_json::json;
+
+use crate::{depot::Depot, operbox::OperBox};
+
+#[derive(clap::Args)]
+pub struct DepotParams {
+    #[arg(short, long)]
+    /// File to export the recognized items to
+    ///
+    /// Items are always saved to the state directory to be used in task conditions,
+    /// this option exports them to another file in given format.
+    output: Option<PathBuf>,
+    #[arg(short, long, requires = "output")]
+    /// Format of the exported file, default to csv if the output ends with `.csv`, json otherwise
+    format: Option<DepotFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+enum DepotFormat {
+    /// Items with their ids, names and counts, same as the saved file
+    Json,
+    /// Table of `id,name,count`
+    Csv,
+    /// Import format of ArkPlanner (Penguin Statistics)
+    Arkplanner,
+    /// Import format of Arknights Toolbox (lolicon), a map from item id to count
+    Lolicon,
+}
+
+#[derive(clap::Args)]
+pub struct OperBoxParams {
+    #[arg(short, long)]
+    /// File to export the recognized operators to
+    ///
+    /// Operators are always saved to the state directory,
+    /// this option exports them to another file in given format.
+    output: Option<PathBuf>,
+    #[arg(short, long, requires = "output")]
+    /// Format of the exported file, default to csv if the output ends with `.csv`, json otherwise
+    format: Option<OperBoxFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+enum OperBoxFormat {
+    /// Operators with their ids, names, rarities, elites, levels and potentials
+    Json,
+    /// Table of `id,name,rarity,elite,level,potential`
+    Csv,
+}
+
+impl super::ToTaskType for DepotParams {
+    fn to_task_type(&self) -> super::TaskType {
+        super::TaskType::Depot
+    }
+}
+
+impl super::IntoParameters for DepotParams {
+    fn into_parameters_no_context(self) -> Result<MAAValue> {
+        Ok(MAAValue::default())
+    }
+}
+
+impl super::ToTaskType for OperBoxParams {
+    fn to_task_type(&self) -> super::TaskType {
+        super::TaskType::OperBox
+    }
+}
+
+impl super::IntoParameters for OperBoxParams {
+    fn into_parameters_no_context(self) -> Result<MAAValue> {
+        Ok(MAAValue::default())
+    }
+}
+
+/// Whether the output should be a csv file by its extension
+fn is_csv(output: &std::path::Path) -> bool {
+    output
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn write_file(output: &std::path::Path, content: String) -> Result<()> {
+    crate::atomic_fs::write(output, content)
+        .with_context(|| format!("Failed to write {}", output.display()))
+}
+
+impl DepotParams {
+    /// Export items of given profile recognized after the time passed to the returned closure
+    pub fn export(
+        &self,
+        profile: Option<String>,
+    ) -> impl FnOnce(DateTime<Local>) -> Result<()> + use<> {
+        let output = self.output.clone();
+        let format = self.format;
+        move |since| {
+            let Some(output) = output else {
+                return Ok(());
+            };
+            let Some(depot) = Depot::load(profile.as_deref())?.filter(|depot| depot.time >= since)
+            else {
+                bail!("Depot is not recognized in this run, nothing to export");
+            };
+            let format = format.unwrap_or(if is_csv(&output) {
+                DepotFormat::Csv
+            } else {
+                DepotFormat::Json
+            });
+            write_file(&output, render_depot(&depot, format)?)?;
+            info!(
+                "Exported {} items to {}",
+                depot.items.len(),
+                output.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+impl OperBoxParams {
+    /// Export operators of given profile recognized after the time passed to the returned closure
+    pub fn export(
+        &self,
+        profile: Option<String>,
+    ) -> impl FnOnce(DateTime<Local>) -> Result<()> + use<> {
+        let output = self.output.clone();
+        let format = self.format;
+        move |since| {
+            let Some(output) = output else {
+                return Ok(());
+            };
+            let Some(operbox) =
+                OperBox::load(profile.as_deref())?.filter(|operbox| operbox.time >= since)
+            else {
+                bail!("Operators are not recognized in this run, nothing to export");
+            };
+            let format = format.unwrap_or(if is_csv(&output) {
+                OperBoxFormat::Csv
+            } else {
+                OperBoxFormat::Json
+            });
+            write_file(&output, render_operbox(&operbox, format)?)?;
+            info!(
+                "Exported {} operators to {}",
+                operbox.operators.len(),
+                output.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn render_depot(depot: &Depot, format: DepotFormat) -> Result<String> {
+    let content = match format {
+        DepotFormat::Json => serde_json::to_string_pretty(depot)?,
+        DepotFormat::Csv => {
+            let mut csv = String::from("id,name,count\n");
+            for item in &depot.items {
+                writeln!(
+                    csv,
+                    "{},{},{}",
+                    csv_field(&item.id),
+                    csv_field(&item.name),
+                    item.count
+                )?;
+            }
+            csv
+        }
+        DepotFormat::Arkplanner => {
+            let items: Vec<_> = depot
+                .items
+                .iter()
+                .map(|item| json!({ "id": item.id, "have": item.count, "name": item.name }))
+                .collect();
+            serde_json::to_string(&json!({
+                "@type": "@penguin-statistics/depot",
+                "items": items,
+            }))?
+        }
+        DepotFormat::Lolicon => {
+            let items: serde_json::Map<_, _> = depot
+                .items
+                .iter()
+                .map(|item| (item.id.clone(), item.count.into()))
+                .collect();
+            serde_json::to_string(&items)?
+        }
+    };
+    Ok(content)
+}
+
+fn render_operbox(operbox: &OperBox, format: OperBoxFormat) -> Result<String> {
+    let content = match format {
+        OperBoxFormat::Json => serde_json::to_string_pretty(operbox)?,
+        OperBoxFormat::Csv => {
+            let mut csv = String::from("id,name,rarity,elite,level,potential\n");
+            for oper in &operbox.operators {
+                writeln!(
+                    csv,
+                    "{},{},{},{},{},{}",
+                    csv_field(&oper.id),
+                    csv_field(&oper.name),
+                    oper.rarity,
+                    oper.elite,
+                    oper.level,
+                    oper.potential
+                )?;
+            }
+            csv
+        }
+    };
+    Ok(content)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::{
+        command::{Command, parse_from},
+        depot::Item,
+        operbox::Operator,
+    };
+
+    #[test]
+    fn parse_depot_params() {
+        use super::super::{IntoParameters, TaskType, ToTaskType};
+
+        match parse_from(["maa", "depot", "-o", "depot.txt", "-f", "arkplanner"]).command {
+            Command::Depot { params, .. } => {
+                assert_eq!(params.to_task_type(), TaskType::Depot);
+                assert_eq!(params.output, Some(PathBuf::from("depot.txt")));
+                assert_eq!(params.format, Some(DepotFormat::Arkplanner));
+                assert_eq!(params.into_parameters_no_context().unwrap(), object!());
+            }
+            _ => panic!("Not a Depot command"),
+        }
+
+        match parse_from(["maa", "operbox", "-o", "operbox.csv"]).command {
+            Command::OperBox { params, .. } => {
+                assert_eq!(params.to_task_type(), TaskType::OperBox);
+                assert_eq!(params.output, Some(PathBuf::from("operbox.csv")));
+                assert_eq!(params.format, None);
+                assert_eq!(params.into_parameters_no_context().unwrap(), object!());
+            }
+            _ => panic!("Not an OperBox command"),
+        }
+
+        use clap::Parser;
+        assert!(crate::command::Cli::try_parse_from(["maa", "depot", "-f", "csv"]).is_err());
+        assert!(
+            crate::command::Cli::try_parse_from(["maa", "operbox", "-o", "a", "-f", "lolicon"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn csv() {
+        assert!(is_csv("depot.csv".as_ref()));
+        assert!(is_csv("depot.CSV".as_ref()));
+        assert!(!is_csv("depot.json".as_ref()));
+        assert!(!is_csv("depot".as_ref()));
+
+        assert_eq!(csv_field("固源岩"), "固源岩");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("a \"b\""), "\"a \"\"b\"\"\"");
+    }
+
+    #[test]
+    fn render() {
+        let depot = Depot {
+            time: Local::now(),
+            items: vec![
+                Item {
+                    id: "2004".to_owned(),
+                    name: "高级作战记录".to_owned(),
+                    count: 4,
+                },
+                Item {
+                    id: "30012".to_owned(),
+                    name: "固源岩".to_owned(),
+                    count: 25,
+                },
+            ],
+        };
+
+        assert_eq!(
+            render_depot(&depot, DepotFormat::Csv).unwrap(),
+            "id,name,count\n2004,高级作战记录,4\n30012,固源岩,25\n"
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(
+                &render_depot(&depot, DepotFormat::Arkplanner).unwrap()
+            )
+            .unwrap(),
+            json!({
+                "@type": "@penguin-statistics/depot",
+                "items": [
+                    { "id": "2004", "have": 4, "name": "高级作战记录" },
+                    { "id": "30012", "have": 25, "name": "固源岩" },
+                ],
+            })
+        );
+        assert_eq!(
+            render_depot(&depot, DepotFormat::Lolicon).unwrap(),
+            r#"{"2004":4,"30012":25}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Depot>(&render_depot(&depot, DepotFormat::Json).unwrap())
+                .unwrap(),
+            depot
+        );
+
+        let operbox = OperBox {
+            time: Local::now(),
+            operators: vec![Operator {
+                id: "char_002_amiya".to_owned(),
+                name: "阿米娅".to_owned(),
+                rarity: 5,
+                elite: 2,
+                level: 50,
+                potential: 6,
+            }],
+        };
+        assert_eq!(
+            render_operbox(&operbox, OperBoxFormat::Csv).unwrap(),
+            "id,name,rarity,elite,level,potential\nchar_002_amiya,阿米娅,5,2,50,6\n"
+        );
+        assert_eq!(
+            serde_json::from_str::<OperBox>(
+                &render_operbox(&operbox, OperBoxFormat::Json).unwrap()
+            )
+            .unwrap(),
+            operbox
+        );
+    }
+}
@@ -1,21 +1,36 @@
 use anyhow::{Context, bail};
 use maa_value::prelude::*;
 
-use crate::config::task::ClientType;
+use crate::config::task::{AUTO_STAGE, ClientType};
 
-#[derive(clap::Args)]
+#[derive(clap::Args, Default)]
 pub struct FightParams {
     /// Stage to fight, e.g. 1-7, leave empty to fight current/last stage
-    stage: Option<String>,
+    ///
+    /// With `--auto`, this is a comma separated list of stages in priority order,
+    /// e.g. `SL-8,CE-6,1-7`, and the first one open now is fought.
+    pub(super) stage: Option<String>,
+    #[clap(long)]
+    /// Select the stage automatically, skipping stages not open now
+    ///
+    /// Resource stages are skipped on days they are closed,
+    /// and side story stages are skipped outside the side story.
+    auto: bool,
+    #[clap(long, requires = "auto")]
+    /// Select the open stage with the most drops of given item per sanity
+    ///
+    /// Drop rates are read from `drops.toml` (or json/yaml) in the config directory.
+    /// Given stages are used as a fallback if no open stage in the table drops the item.
+    target_item: Option<String>,
     #[clap(short, long)]
     /// Number of medicine (Sanity Potion) used to fight, default to 0
-    medicine: Option<i32>,
+    pub(super) medicine: Option<i32>,
     #[clap(long)]
     /// Number of expiring medicine (Sanity Potion) used to fight, default to 0
-    expiring_medicine: Option<i32>,
+    pub(super) expiring_medicine: Option<i32>,
     #[clap(long)]
     /// Number of stone (Originite Prime) used to fight, default to 0
-    stone: Option<i32>,
+    pub(super) stone: Option<i32>,
     #[clap(long)]
     /// Exit after fighting given times, default to infinite
     times: Option<i32>,
@@ -66,10 +81,26 @@ impl super::ToTaskType for FightParams {
 
 impl super::IntoParameters for FightParams {
     fn into_parameters_no_context(self) -> anyhow::Result<MAAValue> {
-        let mut params = object!(
-            "stage" => self.stage.unwrap_or_default(),
-            "DrGrandet" => self.dr_grandet
-        );
+        let mut params = if self.auto {
+            let stages: Vec<String> = self
+                .stage
+                .iter()
+                .flat_map(|stages| stages.split(','))
+                .map(|stage| stage.trim().to_owned())
+                .filter(|stage| !stage.is_empty())
+                .collect();
+            let mut params = object!("stage" => AUTO_STAGE, "DrGrandet" => self.dr_grandet);
+            if !stages.is_empty() {
+                insert!(params, "stages" => stages?);
+            }
+            insert!(params, "target_item" =>? self.target_item);
+            params
+        } else {
+            object!(
+                "stage" => self.stage.unwrap_or_default(),
+                "DrGrandet" => self.dr_grandet
+            )
+        };
 
         // Fight conditions - optional parameters
         insert!(params,
@@ -237,5 +268,31 @@ mod tests {
         );
 
         assert!(parse(["maa", "fight", "1-7", "-D30012=100", "-D30011"]).is_err());
+
+        assert_eq!(
+            parse(["maa", "fight", "--auto", "SL-8, CE-6,1-7", "-m1"]).unwrap(),
+            object!(
+                "stage" => "auto",
+                "DrGrandet" => false,
+                "stages" => ["SL-8", "CE-6", "1-7"],
+                "medicine" => 1,
+            )
+        );
+        assert_eq!(
+            parse(["maa", "fight", "--auto", "--target-item=固源岩"]).unwrap(),
+            object!(
+                "stage" => "auto",
+                "DrGrandet" => false,
+                "target_item" => "固源岩",
+            )
+        );
+        assert!(
+            <crate::command::Cli as clap::Parser>::try_parse_from([
+                "maa",
+                "fight",
+                "--target-item=固源岩"
+            ])
+            .is_err()
+        );
     }
 }
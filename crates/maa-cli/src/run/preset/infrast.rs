@@ -0,0 +1,299 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use chrono::NaiveTime;
+use log::{info, warn};
+use maa_value::prelude::*;
+
+use super::TaskContext;
+use crate::config::infrast::{CUSTOM_MODE, PlanFile};
+
+const FACILITIES: [&str; 9] = [
+    "Mfg",
+    "Trade",
+    "Control",
+    "Power",
+    "Reception",
+    "Office",
+    "Dorm",
+    "Processing",
+    "Training",
+];
+
+#[derive(clap::Args, Default)]
+pub struct InfrastParams {
+    /// Custom plan file, leave empty to shift operators automatically
+    ///
+    /// Relative paths are resolved against the `infrast` directory in the config directory.
+    pub(super) plan: Option<PathBuf>,
+    #[arg(short = 'i', long, requires = "plan")]
+    /// Index of the plan to use in the plan file
+    ///
+    /// Default to the first plan whose period contains the current time,
+    /// or the first plan if no period matches.
+    plan_index: Option<i32>,
+    #[arg(short, long, value_delimiter = ',', value_parser = FACILITIES)]
+    /// Facilities to shift, default to all facilities except Processing and Training
+    ///
+    /// Multiple facilities can be given by comma separated list or repeating this option,
+    /// e.g. `-fMfg,Trade` or `-fMfg -fTrade`.
+    facility: Vec<String>,
+    #[arg(short, long, value_parser = [
+        "_NotUse", "Money", "SyntheticJade", "CombatRecord", "PureGold", "OriginStone", "Chip",
+    ])]
+    /// Usage of drones, default to not use drones
+    drones: Option<String>,
+    #[arg(short, long)]
+    /// Morale threshold (0 ~ 1) to put operators to rest in dormitories, default to 0.3
+    threshold: Option<f32>,
+    #[arg(long)]
+    /// Whether to replenish Originium Shards in trading posts automatically
+    replenish: bool,
+    #[arg(long)]
+    /// Whether to put operators not stationed into dormitories
+    dorm_notstationed: bool,
+    #[arg(long)]
+    /// Whether to fill remaining dormitory slots with operators to gain trust
+    dorm_trust: bool,
+}
+
+impl super::ToTaskType for InfrastParams {
+    fn to_task_type(&self) -> super::TaskType {
+        super::TaskType::Infrast
+    }
+}
+
+impl super::IntoParameters for InfrastParams {
+    fn into_parameters(self, context: TaskContext<'_>) -> anyhow::Result<MAAValue> {
+        // `facility` is required by MaaCore
+        let mut params = object!("facility" => FACILITIES[..7].to_vec()?);
+        params.merge(context.default);
+        params.merge(self.into_parameters_no_context()?);
+        select_plan(&mut params, chrono::Local::now().time())?;
+        Ok(params)
+    }
+
+    fn into_parameters_no_context(self) -> anyhow::Result<MAAValue> {
+        let mut params = MAAValue::default();
+
+        if let Some(plan) = self.plan {
+            insert!(params,
+                "mode" => CUSTOM_MODE,
+                "filename" => plan?,
+                "plan_index" =>? self.plan_index
+            );
+        }
+
+        if !self.facility.is_empty() {
+            insert!(params, "facility" => self.facility?);
+        }
+        insert!(params, "drones" =>? self.drones);
+
+        if let Some(threshold) = self.threshold {
+            if !(0.0..=1.0).contains(&threshold) {
+                bail!("Threshold must be between 0 and 1, got {threshold}");
+            }
+            insert!(params, "threshold" => threshold);
+        }
+
+        if self.replenish {
+            insert!(params, "replenish" => true);
+        }
+        if self.dorm_notstationed {
+            insert!(params, "dorm_notstationed_enabled" => true);
+        }
+        if self.dorm_trust {
+            insert!(params, "dorm_trust_enabled" => true);
+        }
+
+        Ok(params)
+    }
+}
+
+/// Check the plan file in custom mode, and select the plan for given time if not specified
+fn select_plan(params: &mut MAAValue, time: NaiveTime) -> anyhow::Result<()> {
+    if params.get_or("mode", 0) != CUSTOM_MODE {
+        return Ok(());
+    }
+
+    let Some(filename) = params.get_typed::<&str>("filename") else {
+        bail!("No plan file given for custom infrast mode");
+    };
+    let path = Path::new(filename);
+    let path = crate::dirs::abs_config(path, Some("infrast")).unwrap_or_else(|| path.to_owned());
+    let plan_file = PlanFile::load(&path)?;
+
+    let index = match params.get_typed::<i32>("plan_index") {
+        Some(index) => index,
+        None => match plan_file.select(time) {
+            Some(index) => index as i32,
+            None => {
+                warn!("No plan matches the current time, use the first plan");
+                0
+            }
+        },
+    };
+    let plan = usize::try_from(index)
+        .context("Plan index must not be negative")
+        .and_then(|index| plan_file.plan(index))?;
+    info!(
+        "Use plan {index} {} of {}",
+        plan.name.as_deref().unwrap_or("(unnamed)"),
+        path.display()
+    );
+
+    params.insert("filename", path.try_into()?);
+    params.insert("plan_index", index.into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::command::{Command, parse_from};
+
+    fn parse<I, T>(args: I) -> InfrastParams
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        match parse_from(args).command {
+            Command::Infrast { params, .. } => {
+                use super::super::{TaskType, ToTaskType};
+                assert_eq!(params.to_task_type(), TaskType::Infrast);
+                params
+            }
+            _ => panic!("Not an Infrast command"),
+        }
+    }
+
+    #[test]
+    fn parse_infrast_params() {
+        use super::super::IntoParameters;
+
+        assert_eq!(
+            parse(["maa", "infrast"])
+                .into_parameters_no_context()
+                .unwrap(),
+            object!()
+        );
+
+        assert_eq!(
+            parse([
+                "maa",
+                "infrast",
+                "/tmp/plan.json",
+                "-i1",
+                "-fMfg,Trade",
+                "-fDorm",
+                "--drones=Money",
+                "--threshold=0.5",
+                "--replenish",
+                "--dorm-notstationed",
+                "--dorm-trust",
+            ])
+            .into_parameters_no_context()
+            .unwrap(),
+            object!(
+                "mode" => CUSTOM_MODE,
+                "filename" => "/tmp/plan.json",
+                "plan_index" => 1,
+                "facility" => ["Mfg", "Trade", "Dorm"],
+                "drones" => "Money",
+                "threshold" => 0.5,
+                "replenish" => true,
+                "dorm_notstationed_enabled" => true,
+                "dorm_trust_enabled" => true,
+            )
+        );
+
+        assert!(
+            parse(["maa", "infrast", "--threshold=2"])
+                .into_parameters_no_context()
+                .is_err()
+        );
+
+        use clap::Parser;
+        let cli = |args: &[&str]| crate::command::Cli::try_parse_from(args);
+        assert!(cli(&["maa", "infrast", "-i1"]).is_err());
+        assert!(cli(&["maa", "infrast", "-fKitchen"]).is_err());
+        assert!(cli(&["maa", "infrast", "--drones=Sugar"]).is_err());
+    }
+
+    #[test]
+    fn into_parameters() {
+        use super::super::{IntoParameters, test_context};
+
+        assert_eq!(
+            parse(["maa", "infrast"])
+                .into_parameters(test_context())
+                .unwrap(),
+            object!("facility" => [
+                "Mfg",
+                "Trade",
+                "Control",
+                "Power",
+                "Reception",
+                "Office",
+                "Dorm",
+            ])
+        );
+    }
+
+    #[test]
+    fn select_plan_by_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plan.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "plans": [
+                    { "name": "Night", "period": [["22:00", "05:59"]] },
+                    { "name": "Day", "period": [["06:00", "17:59"]] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        let select = |params: MAAValue, time| {
+            let mut params = params;
+            select_plan(&mut params, time).map(|_| params)
+        };
+
+        let custom = object!("mode" => CUSTOM_MODE, "filename" => path);
+        assert_eq!(
+            select(custom.clone(), time(23)).unwrap(),
+            custom.join(object!("plan_index" => 0))
+        );
+        assert_eq!(
+            select(custom.clone(), time(12)).unwrap(),
+            custom.join(object!("plan_index" => 1))
+        );
+        // fallback to the first plan if no period matches
+        assert_eq!(
+            select(custom.clone(), time(20)).unwrap(),
+            custom.join(object!("plan_index" => 0))
+        );
+        // the plan index given is used regardless of the time
+        let given = custom.join(object!("plan_index" => 1));
+        assert_eq!(select(given.clone(), time(23)).unwrap(), given);
+
+        assert!(select(custom.join(object!("plan_index" => 2)), time(23)).is_err());
+        assert!(select(custom.join(object!("plan_index" => -1)), time(23)).is_err());
+        assert!(select(object!("mode" => CUSTOM_MODE), time(23)).is_err());
+        assert!(
+            select(
+                object!("mode" => CUSTOM_MODE, "filename" => "/nonexistent/plan.json"),
+                time(23)
+            )
+            .is_err()
+        );
+
+        // plan file is not checked in other modes
+        let default = object!("mode" => 0, "filename" => "/nonexistent/plan.json");
+        assert_eq!(select(default.clone(), time(23)).unwrap(), default);
+    }
+}
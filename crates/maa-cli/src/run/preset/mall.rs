@@ -0,0 +1,126 @@
+use maa_value::prelude::*;
+
+#[derive(clap::Args, Default)]
+pub struct MallParams {
+    #[arg(long)]
+    /// Whether to buy items with credits, default to only collect credits
+    shopping: bool,
+    #[arg(short, long, requires = "shopping")]
+    /// Items to buy first, repeat this option to give multiple items
+    ///
+    /// Example: `-b招聘许可 -b龙门币` to buy Recruitment Permits and LMD first.
+    buy_first: Vec<String>,
+    #[arg(short = 'B', long, requires = "shopping")]
+    /// Items never to buy, repeat this option to give multiple items
+    blacklist: Vec<String>,
+    #[arg(long, requires = "shopping")]
+    /// Whether to buy items in blacklist if credits are going to overflow
+    force_shopping_if_credit_full: bool,
+    #[arg(long, requires = "shopping")]
+    /// Whether to only buy discounted items
+    only_buy_discount: bool,
+    #[arg(long, requires = "shopping")]
+    /// Whether to stop buying when credits are less than 300
+    reserve_max_credit: bool,
+    #[arg(long)]
+    /// Whether to fight a stage with a support operator to get credits
+    credit_fight: bool,
+    #[arg(long)]
+    /// Do not visit friends to get credits
+    no_visit_friends: bool,
+}
+
+impl super::ToTaskType for MallParams {
+    fn to_task_type(&self) -> super::TaskType {
+        super::TaskType::Mall
+    }
+}
+
+impl super::IntoParameters for MallParams {
+    fn into_parameters_no_context(self) -> anyhow::Result<MAAValue> {
+        let mut params = MAAValue::default();
+
+        if self.shopping {
+            insert!(params, "shopping" => true);
+            if !self.buy_first.is_empty() {
+                insert!(params, "buy_first" => self.buy_first?);
+            }
+            if !self.blacklist.is_empty() {
+                insert!(params, "blacklist" => self.blacklist?);
+            }
+            if self.force_shopping_if_credit_full {
+                insert!(params, "force_shopping_if_credit_full" => true);
+            }
+            if self.only_buy_discount {
+                insert!(params, "only_buy_discount" => true);
+            }
+            if self.reserve_max_credit {
+                insert!(params, "reserve_max_credit" => true);
+            }
+        }
+
+        if self.credit_fight {
+            insert!(params, "credit_fight" => true);
+        }
+        if self.no_visit_friends {
+            insert!(params, "visit_friends" => false);
+        }
+
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::command::{Command, parse_from};
+
+    #[test]
+    fn parse_mall_params() {
+        fn parse<I, T>(args: I) -> MAAValue
+        where
+            I: IntoIterator<Item = T>,
+            T: Into<std::ffi::OsString> + Clone,
+        {
+            match parse_from(args).command {
+                Command::Mall { params, .. } => {
+                    use super::super::{IntoParameters, TaskType, ToTaskType};
+                    assert_eq!(params.to_task_type(), TaskType::Mall);
+                    params.into_parameters_no_context().unwrap()
+                }
+                _ => panic!("Not a Mall command"),
+            }
+        }
+
+        assert_eq!(parse(["maa", "mall"]), object!());
+        assert_eq!(
+            parse(["maa", "mall", "--credit-fight", "--no-visit-friends"]),
+            object!("credit_fight" => true, "visit_friends" => false)
+        );
+        assert_eq!(
+            parse([
+                "maa",
+                "mall",
+                "--shopping",
+                "-b招聘许可",
+                "-b龙门币",
+                "-B家具零件",
+                "--force-shopping-if-credit-full",
+                "--only-buy-discount",
+                "--reserve-max-credit",
+            ]),
+            object!(
+                "shopping" => true,
+                "buy_first" => ["招聘许可", "龙门币"],
+                "blacklist" => ["家具零件"],
+                "force_shopping_if_credit_full" => true,
+                "only_buy_discount" => true,
+                "reserve_max_credit" => true,
+            )
+        );
+
+        use clap::Parser;
+        assert!(crate::command::Cli::try_parse_from(["maa", "mall", "-b龙门币"]).is_err());
+    }
+}
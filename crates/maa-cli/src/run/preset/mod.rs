@@ -47,16 +47,23 @@ where
     T: ToTaskType + IntoParameters,
 {
     fn into_task_config(self, config: &AsstConfig) -> Result<TaskConfig> {
-        let task_type = self.to_task_type();
-        let default = MAAValue::find_file_or_default(default_file(task_type))
-            .context("Failed to load default task config")?;
-        let context = TaskContext { default, config };
-        let params: MAAValue = self.into_parameters(context)?;
-        let task = Task::new(task_type, params);
-        TaskConfig::new_with_task(task)
+        TaskConfig::new_with_task(into_task(self, config)?)
     }
 }
 
+/// Build a task from params, with defaults from the overlay of its task type
+fn into_task<T>(params: T, config: &AsstConfig) -> Result<Task>
+where
+    T: ToTaskType + IntoParameters,
+{
+    let task_type = params.to_task_type();
+    let default = MAAValue::find_file_or_default(default_file(task_type))
+        .context("Failed to load default task config")?;
+    let context = TaskContext { default, config };
+    let params: MAAValue = params.into_parameters(context)?;
+    Ok(Task::new(task_type, params))
+}
+
 #[derive(clap::Args)]
 pub(crate) struct StartUpParams {
     client_type: Option<ClientType>,
@@ -106,7 +113,9 @@ mod fight;
 pub use fight::FightParams;
 
 mod copilot;
-pub use copilot::{CopilotParams, ParadoxCopilotParams, SSSCopilotParams};
+pub use copilot::{
+    CopilotCommand, CopilotParams, ParadoxCopilotParams, SSSCopilotParams, browse_copilot,
+};
 
 mod roguelike;
 pub use roguelike::RoguelikeParams;
@@ -114,6 +123,24 @@ pub use roguelike::RoguelikeParams;
 mod reclamation;
 pub use reclamation::ReclamationParams;
 
+mod recruit;
+pub use recruit::RecruitParams;
+
+mod infrast;
+pub use infrast::InfrastParams;
+
+mod mall;
+pub use mall::MallParams;
+
+mod award;
+pub use award::AwardParams;
+
+mod depot;
+pub use depot::{DepotParams, OperBoxParams};
+
+mod daily;
+pub use daily::DailyParams;
+
 #[cfg(test)]
 fn test_context() -> TaskContext<'static> {
     use std::sync::LazyLock;
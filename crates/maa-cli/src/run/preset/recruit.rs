@@ -0,0 +1,276 @@
+use anyhow::{Context, bail};
+use maa_value::prelude::*;
+
+use super::TaskContext;
+use crate::config::task::ClientType;
+
+#[derive(clap::Args, Default)]
+pub struct RecruitParams {
+    #[arg(short, long, value_delimiter = ',', value_name = "LEVEL")]
+    /// Levels of tags to select, default to 4
+    ///
+    /// Multiple levels can be given by comma separated list or repeating this option,
+    /// e.g. `-s4,5` or `-s4 -s5`.
+    select: Vec<i32>,
+    #[arg(short, long, value_delimiter = ',', value_name = "LEVEL")]
+    /// Levels of tags to confirm the recruitment, default to 3 and 4
+    ///
+    /// Tags of levels not in this list are only recognized but not recruited,
+    /// so that you can choose them manually.
+    confirm: Vec<i32>,
+    #[arg(short, long)]
+    /// Number of recruitments, default to 4, 0 to only recognize tags
+    pub(super) times: Option<i32>,
+    #[arg(long)]
+    /// Whether to refresh tags when only 3-star tags are detected
+    refresh: bool,
+    #[arg(long)]
+    /// Whether to use Expedited Plans to finish recruitments
+    expedite: bool,
+    #[arg(long, requires = "expedite")]
+    /// Number of Expedited Plans to use, default to no limit
+    expedite_times: Option<i32>,
+    #[arg(long)]
+    /// Do not set the recruitment time, leave it as default
+    no_set_time: bool,
+    #[arg(short = 'T', long, value_name = "LEVEL=MINUTES")]
+    /// Recruitment time of given level in minutes, default to 9 hours for all levels
+    ///
+    /// Example: `-T3=460` to set the recruitment time of 3-star tags to 7 hours 40 minutes.
+    /// You can specify multiple levels by repeating this option.
+    recruitment_time: Vec<String>,
+    #[arg(long)]
+    /// Whether to recruit with the Robot tag instead of skipping it
+    no_skip_robot: bool,
+    #[arg(long)]
+    /// Whether report recognized tags to the Penguin Statistics
+    report_to_penguin: bool,
+    #[arg(long)]
+    /// Penguin Statistics ID to report tags, leave empty to report anonymously
+    penguin_id: Option<String>,
+    #[arg(long)]
+    /// Whether report recognized tags to the yituliu
+    report_to_yituliu: bool,
+    #[arg(long)]
+    /// Yituliu ID to report tags, leave empty to report anonymously
+    yituliu_id: Option<String>,
+    #[arg(long)]
+    /// Client type used to determine the server to report tags
+    client_type: Option<ClientType>,
+}
+
+impl super::ToTaskType for RecruitParams {
+    fn to_task_type(&self) -> super::TaskType {
+        super::TaskType::Recruit
+    }
+}
+
+impl super::IntoParameters for RecruitParams {
+    fn into_parameters(self, context: TaskContext<'_>) -> anyhow::Result<MAAValue> {
+        // `select` and `confirm` are required by MaaCore, and MaaCore only recognizes tags
+        // without `times`, so give them defaults which can be overridden by the overlay
+        let mut params = object!(
+            "select" => [4],
+            "confirm" => [3, 4],
+            "times" => 4,
+        );
+        params.merge(context.default);
+        params.merge(self.into_parameters_no_context()?);
+        Ok(params)
+    }
+
+    fn into_parameters_no_context(self) -> anyhow::Result<MAAValue> {
+        let mut params = MAAValue::default();
+
+        if !self.select.is_empty() {
+            insert!(params, "select" => self.select?);
+        }
+        if !self.confirm.is_empty() {
+            insert!(params, "confirm" => self.confirm?);
+        }
+        insert!(params, "times" =>? self.times);
+
+        if self.refresh {
+            insert!(params, "refresh" => true);
+        }
+
+        if self.expedite {
+            insert!(params,
+                "expedite" => true,
+                "expedite_times" =>? self.expedite_times
+            );
+        }
+
+        if self.no_set_time {
+            insert!(params, "set_time" => false);
+        }
+
+        if !self.recruitment_time.is_empty() {
+            let mut time_map = maa_value::map::StringMap::new();
+
+            for time in self.recruitment_time {
+                let Some((level, minutes)) = time.split_once('=') else {
+                    bail!("Invalid recruitment time format: {}", time)
+                };
+                let level: u8 = level
+                    .parse()
+                    .with_context(|| format!("Failed to parse recruitment level: {level}"))?;
+                let minutes: i32 = minutes
+                    .parse()
+                    .with_context(|| format!("Failed to parse recruitment time: {minutes}"))?;
+
+                time_map.insert(level.to_string(), minutes.into());
+            }
+
+            insert!(params, "recruitment_time" => MAAValue::Object(time_map));
+        }
+
+        if self.no_skip_robot {
+            insert!(params, "skip_robot" => false);
+        }
+
+        // Penguin Statistics reporting
+        if self.report_to_penguin {
+            insert!(params,
+                "report_to_penguin" => true,
+                "penguin_id" =>? self.penguin_id
+            );
+        }
+
+        // Yituliu reporting
+        if self.report_to_yituliu {
+            insert!(params,
+                "report_to_yituliu" => true,
+                "yituliu_id" =>? self.yituliu_id
+            );
+        }
+
+        if let Some(client_type) = self.client_type {
+            insert!(params, "server" =>? client_type.server_report());
+        }
+
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use super::*;
+    use crate::command::{Command, parse_from};
+
+    fn parse<I, T>(args: I) -> RecruitParams
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        match parse_from(args).command {
+            Command::Recruit { params, .. } => {
+                use super::super::{TaskType, ToTaskType};
+                assert_eq!(params.to_task_type(), TaskType::Recruit);
+                params
+            }
+            _ => panic!("Not a Recruit command"),
+        }
+    }
+
+    #[test]
+    fn parse_recruit_params() {
+        use super::super::IntoParameters;
+
+        assert_eq!(
+            parse(["maa", "recruit"])
+                .into_parameters_no_context()
+                .unwrap(),
+            object!()
+        );
+
+        assert_eq!(
+            parse([
+                "maa",
+                "recruit",
+                "-s4,5",
+                "-c3",
+                "-c4",
+                "-t2",
+                "--refresh",
+                "--expedite",
+                "--expedite-times=1",
+                "--no-set-time",
+                "-T3=460",
+                "-T4=540",
+                "--no-skip-robot",
+                "--report-to-penguin",
+                "--penguin-id=123456789",
+                "--report-to-yituliu",
+                "--client-type=YoStarJP",
+            ])
+            .into_parameters_no_context()
+            .unwrap(),
+            object!(
+                "select" => [4, 5],
+                "confirm" => [3, 4],
+                "times" => 2,
+                "refresh" => true,
+                "expedite" => true,
+                "expedite_times" => 1,
+                "set_time" => false,
+                "recruitment_time" => object!("3" => 460, "4" => 540),
+                "skip_robot" => false,
+                "report_to_penguin" => true,
+                "penguin_id" => "123456789",
+                "report_to_yituliu" => true,
+                "server" => "JP",
+            )
+        );
+
+        assert!(
+            parse(["maa", "recruit", "-T3"])
+                .into_parameters_no_context()
+                .is_err()
+        );
+        assert!(
+            parse(["maa", "recruit", "-Tx=460"])
+                .into_parameters_no_context()
+                .is_err()
+        );
+        assert!(
+            <crate::command::Cli as clap::Parser>::try_parse_from([
+                "maa",
+                "recruit",
+                "--expedite-times=1"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn into_parameters() {
+        use super::super::{IntoParameters, test_context};
+
+        assert_eq!(
+            parse(["maa", "recruit"])
+                .into_parameters(test_context())
+                .unwrap(),
+            object!(
+                "select" => [4],
+                "confirm" => [3, 4],
+                "times" => 4,
+            )
+        );
+
+        let mut context = test_context();
+        context.default = object!("confirm" => [3, 4, 5], "refresh" => true);
+        assert_eq!(
+            parse(["maa", "recruit", "-s5", "-t0"])
+                .into_parameters(context)
+                .unwrap(),
+            object!(
+                "select" => [5],
+                "confirm" => [3, 4, 5],
+                "times" => 0,
+                "refresh" => true,
+            )
+        );
+    }
+}
@@ -2,6 +2,8 @@ use anyhow::bail;
 use clap::ValueEnum;
 use maa_value::prelude::*;
 
+use crate::history::roguelike::{InvestmentProgress, investment_progress};
+
 #[repr(i8)]
 #[cfg_attr(test, derive(PartialEq, Debug))]
 #[derive(Clone, Copy)]
@@ -97,6 +99,15 @@ pub struct RoguelikeParams {
     /// Do not stop exploration when investment is full
     #[arg(long)]
     no_stop_when_investment_full: bool,
+    /// Invest until the bank reaches given count of originium ingots, across multiple runs
+    ///
+    /// The ingots in the bank are tracked per profile in the history of roguelike explorations.
+    /// With a goal, the investments count is capped by the remaining ingots,
+    /// and the starts count is lowered to the number of explorations estimated
+    /// to reach the goal by ingots invested in previous explorations of the same mode.
+    /// The task fails if the goal has already been reached.
+    #[arg(long, conflicts_with = "disable_investment")]
+    investment_goal: Option<i32>,
 
     // Support related parameters
     /// Use support operator
@@ -139,6 +150,18 @@ pub struct RoguelikeParams {
     /// Whether to start with seed, only available in Sarkaz theme and mode 1
     #[arg(long)]
     start_with_seed: bool,
+
+    /// Profile whose history the investment goal is tracked in, from the common arguments
+    #[arg(skip)]
+    profile: Option<String>,
+}
+
+impl RoguelikeParams {
+    /// Track the investment goal in the history of given profile
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
 }
 
 impl super::ToTaskType for RoguelikeParams {
@@ -189,6 +212,11 @@ impl super::IntoParameters for RoguelikeParams {
                 "investment_with_more_score" => self.investment_with_more_score,
                 "stop_when_investment_full" => !self.no_stop_when_investment_full,
             );
+
+            if let Some(goal) = self.investment_goal {
+                let progress = investment_progress(self.profile.as_deref(), theme.to_str(), mode)?;
+                apply_investment_goal(&mut value, goal, &progress)?;
+            }
         }
 
         // Support unit settings
@@ -245,6 +273,45 @@ impl super::IntoParameters for RoguelikeParams {
     }
 }
 
+/// Cap the investments and starts count by the remaining ingots to reach the goal
+fn apply_investment_goal(
+    value: &mut MAAValue,
+    goal: i32,
+    progress: &InvestmentProgress,
+) -> anyhow::Result<()> {
+    let deposit = progress.deposit.unwrap_or_default();
+    if progress.deposit.is_none() {
+        log::warn!("No deposit of originium ingots recorded, assume the bank is empty");
+    }
+
+    let remaining = (i64::from(goal) - deposit) as i32;
+    if remaining <= 0 {
+        bail!("Investment goal reached, {deposit} / {goal} originium ingots deposited");
+    }
+
+    let investments = value
+        .get_typed::<i32>("investments_count")
+        .map_or(remaining, |count| count.min(remaining));
+    value.insert("investments_count", investments.into());
+
+    match progress.average.filter(|&average| average > 0.0) {
+        Some(average) => {
+            let explorations = (f64::from(remaining) / average).ceil() as i32;
+            let starts = value
+                .get_typed::<i32>("starts_count")
+                .map_or(explorations, |count| count.min(explorations));
+            value.insert("starts_count", starts.into());
+            log::info!(
+                "Investment goal: {deposit} / {goal} originium ingots deposited, \
+                 about {explorations} explorations left"
+            );
+        }
+        None => log::info!("Investment goal: {deposit} / {goal} originium ingots deposited"),
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 mod tests {
@@ -489,4 +556,59 @@ mod tests {
             )),
         );
     }
+
+    #[test]
+    fn investment_goal() {
+        use clap::Parser;
+
+        let params = || object!("investment_enabled" => true, "starts_count" => 10);
+
+        let mut value = params();
+        apply_investment_goal(&mut value, 999, &InvestmentProgress {
+            deposit: Some(900),
+            average: Some(12.5),
+        })
+        .unwrap();
+        assert_eq!(
+            value,
+            params().join(object!("investments_count" => 99, "starts_count" => 8))
+        );
+
+        // given counts are kept if less than the remaining
+        let mut value = params().join(object!("investments_count" => 20, "starts_count" => 2));
+        apply_investment_goal(&mut value, 999, &InvestmentProgress {
+            deposit: Some(900),
+            average: Some(12.5),
+        })
+        .unwrap();
+        assert_eq!(
+            value,
+            params().join(object!("investments_count" => 20, "starts_count" => 2))
+        );
+
+        // starts count is not changed without previous explorations
+        let mut value = params();
+        apply_investment_goal(&mut value, 500, &InvestmentProgress::default()).unwrap();
+        assert_eq!(value, params().join(object!("investments_count" => 500)));
+
+        let mut value = params();
+        assert!(
+            apply_investment_goal(&mut value, 999, &InvestmentProgress {
+                deposit: Some(999),
+                average: None,
+            })
+            .is_err()
+        );
+
+        assert!(
+            crate::command::Cli::try_parse_from([
+                "maa",
+                "roguelike",
+                "Sarkaz",
+                "--investment-goal=999",
+                "--disable-investment",
+            ])
+            .is_err()
+        );
+    }
 }
@@ -0,0 +1,521 @@
+//! Run tasks one by one, so that each task can be retried, timed out or stop the run on failure,
+//! and tasks depending on other tasks can be run according to results of their predecessors.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::{self, AtomicBool},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{debug, info, warn};
+use maa_core::Assistant;
+use maa_types::{TaskType, primitive::AsstTaskId};
+
+use super::{
+    InstanceState,
+    summary::{Reason, Summary},
+};
+use crate::config::task::{OnFailure, Task};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Max time to wait for the result of a task after MaaCore stopped running,
+/// because callback messages are delivered asynchronously
+const RESULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Operations of MaaCore to run tasks, abstracted for testing without MaaCore
+pub(super) trait Queue {
+    fn append_task(&self, task_type: TaskType, params: &str) -> Result<AsstTaskId>;
+    fn start(&self) -> Result<()>;
+    fn stop(&self) -> Result<()>;
+    fn running(&self) -> bool;
+}
+
+impl Queue for Assistant {
+    fn append_task(&self, task_type: TaskType, params: &str) -> Result<AsstTaskId> {
+        Ok(Assistant::append_task(self, task_type, params)?)
+    }
+
+    fn start(&self) -> Result<()> {
+        Ok(Assistant::start(self)?)
+    }
+
+    fn stop(&self) -> Result<()> {
+        Ok(Assistant::stop(self)?)
+    }
+
+    fn running(&self) -> bool {
+        Assistant::running(self)
+    }
+}
+
+/// Result of running a task
+enum Outcome {
+    Succeeded,
+    Failed,
+    /// The game went offline and auto reconnect is disabled
+    Offline,
+}
+
+/// Run tasks one by one according to their policy and dependency
+///
+/// A failed task is retried as configured, and remaining tasks are run, skipped or aborted
+/// according to `on_failure` if it failed after all retries.
+/// The error flag of the state is set only if any task failed after all retries.
+///
+/// A task with a dependency is deferred until its predecessor finished, and then it is run
+/// right after the predecessor if the result of the predecessor is expected, or skipped otherwise.
+pub(super) fn run_tasks(
+    queue: &impl Queue,
+    tasks: Vec<Task>,
+    state: &InstanceState,
+    stop_bool: &AtomicBool,
+) -> Result<()> {
+    state.summary.init(Summary::new());
+
+    // Final status and detail of finished named tasks
+    let mut results: HashMap<&str, (bool, serde_json::Value)> = HashMap::new();
+    let mut pending: VecDeque<&Task> = tasks.iter().collect();
+    let mut deferred: Vec<&Task> = Vec::new();
+
+    let mut failed = false;
+    while let Some(task) = pending.pop_front() {
+        if let Some(dependency) = &task.dependency {
+            let Some((succeeded, detail)) = results.get(dependency.after.as_str()) else {
+                deferred.push(task);
+                continue;
+            };
+            if !dependency.when.matches(*succeeded, detail) {
+                info!(
+                    "Skipping task {} due to the result of task {}",
+                    task.name_or_default(),
+                    dependency.after
+                );
+                continue;
+            }
+        }
+
+        let (id, outcome) = run_with_retry(queue, task, state, stop_bool)?;
+        match outcome {
+            Outcome::Succeeded => {}
+            Outcome::Offline => break,
+            Outcome::Failed => {
+                failed = true;
+                match task.policy.on_failure {
+                    OnFailure::Continue => {}
+                    OnFailure::Abort => {
+                        bail!("Task {} failed, aborting!", task.name_or_default())
+                    }
+                    OnFailure::SkipRest => {
+                        warn!(
+                            "Task {} failed, skipping remaining tasks",
+                            task.name_or_default()
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(name) = task.name.as_deref() {
+            let succeeded = matches!(outcome, Outcome::Succeeded);
+            let detail = state.summary.detail(id).unwrap_or_default();
+            results.insert(name, (succeeded, detail));
+
+            // Successors are run right after their predecessor in the original order
+            let (ready, rest) = deferred.into_iter().partition::<Vec<_>, _>(|successor| {
+                successor
+                    .dependency
+                    .as_ref()
+                    .is_some_and(|dependency| dependency.after == name)
+            });
+            deferred = rest;
+            for successor in ready.into_iter().rev() {
+                pending.push_front(successor);
+            }
+        }
+    }
+
+    for task in deferred {
+        if let Some(dependency) = &task.dependency {
+            info!(
+                "Skipping task {} because task {} was not run",
+                task.name_or_default(),
+                dependency.after
+            );
+        }
+    }
+
+    state.errored.store(failed, atomic::Ordering::Relaxed);
+
+    Ok(())
+}
+
+fn run_with_retry(
+    queue: &impl Queue,
+    task: &Task,
+    state: &InstanceState,
+    stop_bool: &AtomicBool,
+) -> Result<(AsstTaskId, Outcome)> {
+    let params = serde_json::to_string_pretty(&task.params)?;
+
+    let mut attempt = 0;
+    loop {
+        // Each attempt is recorded in the summary separately
+        let name = match attempt {
+            0 => task.name.clone(),
+            n => Some(format!("{} (retry {n})", task.name_or_default())),
+        };
+        let (id, outcome) = run_once(queue, task, &params, name, state, stop_bool)?;
+
+        if !matches!(outcome, Outcome::Failed) || attempt >= task.policy.retry {
+            return Ok((id, outcome));
+        }
+
+        attempt += 1;
+        warn!(
+            "Task {} failed, retrying ({attempt}/{})",
+            task.name_or_default(),
+            task.policy.retry
+        );
+    }
+}
+
+fn run_once(
+    queue: &impl Queue,
+    task: &Task,
+    params: &str,
+    name: Option<String>,
+    state: &InstanceState,
+    stop_bool: &AtomicBool,
+) -> Result<(AsstTaskId, Outcome)> {
+    debug!(
+        "Adding task [{}] with params: {params}",
+        task.name_or_default()
+    );
+    let id = queue.append_task(task.task_type, params).with_context(|| {
+        format!(
+            "Failed to add task {} with params: {params}",
+            task.name_or_default()
+        )
+    })?;
+    state.summary.insert(id, name, task);
+
+    queue.start()?;
+
+    let start = Instant::now();
+    let mut timed_out = false;
+    while queue.running() {
+        if stop_bool.load(atomic::Ordering::Relaxed) {
+            bail!("Interrupted by user!");
+        }
+        if state.offline_stop.load(atomic::Ordering::Relaxed) {
+            queue.stop()?;
+            return Ok((id, Outcome::Offline));
+        }
+        if !timed_out
+            && let Some(timeout) = task.policy.timeout
+            && start.elapsed() > timeout
+        {
+            warn!("Task {} timed out, stopping", task.name_or_default());
+            queue.stop()?;
+            timed_out = true;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    if timed_out {
+        return Ok((id, Outcome::Failed));
+    }
+
+    let deadline = Instant::now() + RESULT_TIMEOUT;
+    let reason = loop {
+        match state.summary.reason(id) {
+            Some(reason) => break Some(reason),
+            None if Instant::now() >= deadline => break None,
+            None => std::thread::sleep(POLL_INTERVAL / 10),
+        }
+    };
+
+    let outcome = match reason {
+        Some(Reason::Completed) => Outcome::Succeeded,
+        Some(_) => Outcome::Failed,
+        None => {
+            warn!("No result of task {}", task.name_or_default());
+            Outcome::Failed
+        }
+    };
+
+    Ok((id, outcome))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    use maa_core::Callback;
+    use maa_types::MessageKind;
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::{
+        config::task::{Dependency, Policy},
+        run::callback::{InstanceState, MaaCallback},
+    };
+
+    /// How a fake task ends
+    #[derive(Clone, Copy)]
+    enum Script {
+        Complete,
+        Error,
+        /// Keep running until stopped
+        Hang,
+    }
+
+    /// A fake MaaCore which runs tasks by sending callback messages in given order
+    struct FakeQueue {
+        callback: MaaCallback,
+        scripts: Mutex<VecDeque<Script>>,
+        appended: Mutex<Vec<(AsstTaskId, TaskType)>>,
+        running: Mutex<Option<AsstTaskId>>,
+    }
+
+    impl FakeQueue {
+        fn new(state: &Arc<InstanceState>, scripts: impl IntoIterator<Item = Script>) -> Self {
+            Self {
+                callback: MaaCallback::new(true, Arc::clone(state)),
+                scripts: Mutex::new(scripts.into_iter().collect()),
+                appended: Mutex::default(),
+                running: Mutex::default(),
+            }
+        }
+
+        fn send(&self, kind: MessageKind, id: AsstTaskId) {
+            let message = format!(r#"{{"taskchain":"Fake","taskid":{id}}}"#);
+            self.callback.on_message(kind, Some(&message));
+        }
+
+        fn appended(&self) -> Vec<TaskType> {
+            let appended = self.appended.lock().unwrap();
+            appended.iter().map(|(_, task_type)| *task_type).collect()
+        }
+    }
+
+    impl Queue for FakeQueue {
+        fn append_task(&self, task_type: TaskType, _: &str) -> Result<AsstTaskId> {
+            let mut appended = self.appended.lock().unwrap();
+            let id = appended.len() as AsstTaskId + 1;
+            appended.push((id, task_type));
+            Ok(id)
+        }
+
+        fn start(&self) -> Result<()> {
+            let (id, _) = *self.appended.lock().unwrap().last().unwrap();
+            self.send(MessageKind::TaskChainStart, id);
+            match self.scripts.lock().unwrap().pop_front().unwrap() {
+                Script::Complete => self.send(MessageKind::TaskChainCompleted, id),
+                Script::Error => self.send(MessageKind::TaskChainError, id),
+                Script::Hang => *self.running.lock().unwrap() = Some(id),
+            }
+            Ok(())
+        }
+
+        fn stop(&self) -> Result<()> {
+            if let Some(id) = self.running.lock().unwrap().take() {
+                self.send(MessageKind::TaskChainStopped, id);
+            }
+            Ok(())
+        }
+
+        fn running(&self) -> bool {
+            self.running.lock().unwrap().is_some()
+        }
+    }
+
+    fn task(task_type: TaskType, retry: u32, on_failure: OnFailure) -> Task {
+        Task::new(task_type, Default::default()).with_policy(Policy {
+            retry,
+            timeout: None,
+            on_failure,
+        })
+    }
+
+    fn reasons(state: &InstanceState) -> Vec<(Option<String>, String)> {
+        state
+            .summary
+            .tasks::<Value>()
+            .unwrap()
+            .into_iter()
+            .map(|task| {
+                (
+                    task["name"].as_str().map(ToOwned::to_owned),
+                    task["reason"].as_str().unwrap().to_owned(),
+                )
+            })
+            .collect()
+    }
+
+    fn errored(state: &InstanceState) -> bool {
+        state.errored.load(atomic::Ordering::Relaxed)
+    }
+
+    #[test]
+    fn retry_until_succeeded() {
+        use Script::*;
+        use TaskType::*;
+
+        let state = Arc::new(InstanceState::default());
+        let queue = FakeQueue::new(&state, [Error, Error, Complete, Complete]);
+        let tasks = vec![
+            task(Recruit, 2, OnFailure::Continue),
+            task(Fight, 0, OnFailure::Continue),
+        ];
+
+        run_tasks(&queue, tasks, &state, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(queue.appended(), [Recruit, Recruit, Recruit, Fight]);
+        assert_eq!(reasons(&state), [
+            (None, "Error".to_owned()),
+            (Some("Recruit (retry 1)".to_owned()), "Error".to_owned()),
+            (Some("Recruit (retry 2)".to_owned()), "Completed".to_owned()),
+            (None, "Completed".to_owned()),
+        ]);
+        // The task succeeded at last, so the run is not failed
+        assert!(!errored(&state));
+    }
+
+    #[test]
+    fn on_failure() {
+        use Script::*;
+        use TaskType::*;
+
+        let run = |on_failure, scripts: &[Script]| {
+            let state = Arc::new(InstanceState::default());
+            let queue = FakeQueue::new(&state, scripts.iter().copied());
+            let tasks = vec![task(Recruit, 1, on_failure), task(Fight, 0, on_failure)];
+            let ret = run_tasks(&queue, tasks, &state, &AtomicBool::new(false));
+            (ret, queue.appended(), errored(&state))
+        };
+
+        let (ret, appended, errored) = run(OnFailure::Continue, &[Error, Error, Complete]);
+        assert!(ret.is_ok());
+        assert_eq!(appended, [Recruit, Recruit, Fight]);
+        assert!(errored);
+
+        let (ret, appended, errored) = run(OnFailure::SkipRest, &[Error, Error]);
+        assert!(ret.is_ok());
+        assert_eq!(appended, [Recruit, Recruit]);
+        assert!(errored);
+
+        let (ret, appended, _) = run(OnFailure::Abort, &[Error, Error]);
+        assert_eq!(
+            ret.unwrap_err().to_string(),
+            "Task Recruit failed, aborting!"
+        );
+        assert_eq!(appended, [Recruit, Recruit]);
+    }
+
+    #[test]
+    fn timeout() {
+        use Script::*;
+        use TaskType::*;
+
+        let state = Arc::new(InstanceState::default());
+        let queue = FakeQueue::new(&state, [Hang, Complete, Complete]);
+        let mut hung = task(Recruit, 1, OnFailure::Continue);
+        hung.policy.timeout = Some(Duration::from_millis(150));
+        let tasks = vec![hung, task(Fight, 0, OnFailure::Continue)];
+
+        run_tasks(&queue, tasks, &state, &AtomicBool::new(false)).unwrap();
+
+        assert_eq!(queue.appended(), [Recruit, Recruit, Fight]);
+        assert_eq!(reasons(&state)[0].1, "Stopped");
+        assert!(!errored(&state));
+    }
+
+    #[test]
+    fn dependency() {
+        use Script::*;
+        use TaskType::*;
+
+        fn after(mut task: Task, name: &str, when: serde_json::Value) -> Task {
+            task.dependency = Some(Dependency {
+                after: name.to_owned(),
+                when: serde_json::from_value(when).unwrap(),
+            });
+            task
+        }
+
+        fn named(task_type: TaskType, name: &str) -> Task {
+            let mut task = task(task_type, 0, OnFailure::Continue);
+            task.name = Some(name.to_owned());
+            task
+        }
+
+        let tasks = || {
+            vec![
+                // deferred until Fight finished
+                after(named(Mall, "Mall"), "Fight", json!("succeeded")),
+                named(Fight, "Fight"),
+                after(named(Fight, "Fallback"), "Fight", json!("failed")),
+                // no medicine is used by the fake fight
+                after(
+                    task(Award, 0, OnFailure::Continue),
+                    "Fallback",
+                    json!({
+                        "status": "any",
+                        "detail": { "medicine": { "min": 1 } },
+                    }),
+                ),
+                after(
+                    task(Recruit, 0, OnFailure::Continue),
+                    "Fallback",
+                    json!({
+                        "detail": { "times": 0 },
+                    }),
+                ),
+                // Mall is skipped if Fight failed, so this task is never run
+                after(task(Infrast, 0, OnFailure::Continue), "Mall", json!("any")),
+            ]
+        };
+
+        let state = Arc::new(InstanceState::default());
+        let queue = FakeQueue::new(&state, [Error, Complete, Complete]);
+        run_tasks(&queue, tasks(), &state, &AtomicBool::new(false)).unwrap();
+        assert_eq!(queue.appended(), [Fight, Fight, Recruit]);
+        assert!(errored(&state));
+
+        let state = Arc::new(InstanceState::default());
+        let queue = FakeQueue::new(&state, [Complete, Complete, Complete]);
+        run_tasks(&queue, tasks(), &state, &AtomicBool::new(false)).unwrap();
+        assert_eq!(queue.appended(), [Fight, Mall, Infrast]);
+        assert!(!errored(&state));
+    }
+
+    #[test]
+    fn stop() {
+        use Script::*;
+        use TaskType::*;
+
+        let state = Arc::new(InstanceState::default());
+        let queue = FakeQueue::new(&state, [Hang]);
+        let tasks = vec![
+            task(Recruit, 0, OnFailure::Continue),
+            task(Fight, 0, OnFailure::Continue),
+        ];
+        state.offline_stop.store(true, atomic::Ordering::Relaxed);
+        run_tasks(&queue, tasks, &state, &AtomicBool::new(false)).unwrap();
+        assert_eq!(queue.appended(), [Recruit]);
+
+        let state = Arc::new(InstanceState::default());
+        let queue = FakeQueue::new(&state, [Hang]);
+        let tasks = vec![task(Recruit, 0, OnFailure::Continue)];
+        let ret = run_tasks(&queue, tasks, &state, &AtomicBool::new(true));
+        assert_eq!(ret.unwrap_err().to_string(), "Interrupted by user!");
+    }
+}
@@ -0,0 +1,556 @@
+//! A local HTTP/JSON API to control a running MaaCore instance.
+//!
+//! All requests are handled one by one on the main thread, which owns the [`Assistant`],
+//! except the message streams, which are served in their own threads.
+
+use std::{
+    io::Write,
+    sync::{
+        Arc, Mutex,
+        atomic::{self, AtomicBool},
+        mpsc::{Receiver, SyncSender, TrySendError, sync_channel},
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use log::{debug, info, warn};
+use maa_core::Assistant;
+use maa_types::{MessageKind, TaskType, primitive::AsstTaskId};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::{
+    CommonArgs, InstanceState, callback::Observer, create_assistant, load_asst_config, load_core,
+    new_callback, register_stop_signal, setup_core,
+};
+use crate::config::{asst::AsstConfig, task::ClientType};
+
+/// Number of messages buffered for each stream before new messages are dropped
+const STREAM_BUFFER: usize = 1024;
+
+/// Serve the control API on the given address until interrupted
+pub fn serve(listen: &str, client: Option<ClientType>, args: CommonArgs) -> Result<()> {
+    let mut asst_config = load_asst_config(&args)?;
+    if let Some(resource) = client.and_then(|c| c.resource()) {
+        asst_config.resource.use_global_resource(resource);
+    }
+
+    if args.dry_run {
+        info!("Dry run, skip starting server on {listen}");
+        return Ok(());
+    }
+
+    load_core().context("Failed to load MaaCore!")?;
+    setup_core(&asst_config)?;
+
+    let stop_bool = register_stop_signal()?;
+
+    let hub = Arc::new(Hub::default());
+    let observer: Observer = {
+        let hub = Arc::clone(&hub);
+        Box::new(move |kind, message| hub.publish(kind, message))
+    };
+    let state = Arc::new(InstanceState::default());
+    let callback = new_callback(&asst_config, args.no_auto_reconnect, Arc::clone(&state))
+        .with_observer(Some(observer))
+        .with_profile(args.profile.as_deref());
+    let asst = create_assistant(&asst_config, callback)?;
+
+    let server = Server::http(listen).map_err(|e| anyhow!("Failed to listen on {listen}: {e}"))?;
+    if let Some(addr) = server.server_addr().to_ip() {
+        if !addr.ip().is_loopback() {
+            warn!("The control API has no authentication, do not expose it to untrusted networks!");
+        }
+        info!("Listening on http://{addr}");
+    }
+
+    let core = Core {
+        asst,
+        asst_config,
+        state,
+    };
+    serve_requests(&server, &core, &hub, &stop_bool);
+
+    // TODO: Better ways to restore signal handlers?
+    stop_bool.store(true, atomic::Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Operations exposed by the API, abstracted for testing without MaaCore
+trait Controller {
+    fn connect(&self) -> Result<()>;
+    fn append_task(&self, task_type: TaskType, params: &str) -> Result<AsstTaskId>;
+    fn set_task_params(&self, id: AsstTaskId, params: &str) -> Result<()>;
+    fn start(&self) -> Result<()>;
+    fn stop(&self) -> Result<()>;
+    fn running(&self) -> bool;
+    fn connected(&self) -> bool;
+    fn screenshot(&self, fresh: bool) -> Result<Option<Vec<u8>>>;
+    /// Called periodically between requests
+    fn poll(&self) {}
+}
+
+struct Core {
+    asst: Assistant,
+    asst_config: AsstConfig,
+    state: Arc<InstanceState>,
+}
+
+impl Controller for Core {
+    fn connect(&self) -> Result<()> {
+        let (adb_path, address, config) = self.asst_config.connection.connect_args();
+        self.asst
+            .async_connect(adb_path, address.as_ref(), config, true)?;
+        Ok(())
+    }
+
+    fn append_task(&self, task_type: TaskType, params: &str) -> Result<AsstTaskId> {
+        Ok(self.asst.append_task(task_type, params)?)
+    }
+
+    fn set_task_params(&self, id: AsstTaskId, params: &str) -> Result<()> {
+        Ok(self.asst.set_task_params(id, params)?)
+    }
+
+    fn start(&self) -> Result<()> {
+        Ok(self.asst.start()?)
+    }
+
+    fn stop(&self) -> Result<()> {
+        Ok(self.asst.stop()?)
+    }
+
+    fn running(&self) -> bool {
+        self.asst.running()
+    }
+
+    fn connected(&self) -> bool {
+        self.asst.connected()
+    }
+
+    fn screenshot(&self, fresh: bool) -> Result<Option<Vec<u8>>> {
+        Ok(if fresh {
+            self.asst.get_fresh_image()?
+        } else {
+            self.asst.get_image()?
+        })
+    }
+
+    fn poll(&self) {
+        // Stop tasks when game is offline and auto reconnect is disabled
+        if self
+            .state
+            .offline_stop
+            .swap(false, atomic::Ordering::Relaxed)
+        {
+            info!("Game is offline, stopping tasks");
+            if let Err(e) = self.asst.stop() {
+                warn!("Failed to stop MaaCore: {e}");
+            }
+        }
+    }
+}
+
+fn serve_requests(server: &Server, ctrl: &impl Controller, hub: &Arc<Hub>, stop_bool: &AtomicBool) {
+    while !stop_bool.load(atomic::Ordering::Relaxed) {
+        ctrl.poll();
+
+        let mut request = match server.recv_timeout(Duration::from_millis(100)) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Failed to receive request: {e}");
+                continue;
+            }
+        };
+
+        debug!("{} {}", request.method(), request.url());
+
+        if *request.method() == Method::Get && request.url() == "/messages" {
+            let rx = hub.subscribe();
+            std::thread::spawn(move || stream_messages(request, rx));
+            continue;
+        }
+
+        let mut body = String::new();
+        let reply = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => handle(ctrl, request.method(), request.url(), &body),
+            Err(e) => Reply::error(400, format!("Failed to read request body: {e}")),
+        };
+
+        if let Err(e) = request.respond(reply.into_response()) {
+            warn!("Failed to send response: {e}");
+        }
+    }
+}
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
+enum Reply {
+    Json(u16, Value),
+    Png(Vec<u8>),
+}
+
+impl Reply {
+    fn ok(value: Value) -> Self {
+        Reply::Json(200, value)
+    }
+
+    fn error(status: u16, message: impl std::fmt::Display) -> Self {
+        Reply::Json(status, json!({ "error": message.to_string() }))
+    }
+
+    fn into_response(self) -> Response<std::io::Cursor<Vec<u8>>> {
+        let (status, content_type, body) = match self {
+            Reply::Json(status, value) => (status, "application/json", value.to_string().into()),
+            Reply::Png(image) => (200, "image/png", image),
+        };
+        Response::from_data(body)
+            .with_status_code(status)
+            .with_header(content_type_header(content_type))
+    }
+}
+
+fn content_type_header(content_type: &str) -> Header {
+    Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap()
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewTask {
+    #[serde(rename = "type")]
+    task_type: TaskType,
+    #[serde(default = "empty_object")]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaskParams {
+    params: Value,
+}
+
+fn empty_object() -> Value {
+    json!({})
+}
+
+fn handle(ctrl: &impl Controller, method: &Method, url: &str, body: &str) -> Reply {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let status = || {
+        json!({
+            "running": ctrl.running(),
+            "connected": ctrl.connected(),
+        })
+    };
+
+    match (method, path) {
+        (Method::Get, "/status") => Reply::ok(status()),
+        (Method::Post, "/connect") => match ctrl.connect() {
+            Ok(()) => Reply::ok(status()),
+            Err(e) => Reply::error(500, format!("Failed to connect: {e:#}")),
+        },
+        (Method::Post, "/start") => match ctrl.start() {
+            Ok(()) => Reply::ok(status()),
+            Err(e) => Reply::error(500, format!("Failed to start: {e:#}")),
+        },
+        (Method::Post, "/stop") => match ctrl.stop() {
+            Ok(()) => Reply::ok(status()),
+            Err(e) => Reply::error(500, format!("Failed to stop: {e:#}")),
+        },
+        (Method::Post, "/tasks") => {
+            let task: NewTask = match serde_json::from_str(body) {
+                Ok(task) => task,
+                Err(e) => return Reply::error(400, format!("Invalid task: {e}")),
+            };
+            match ctrl.append_task(task.task_type, &task.params.to_string()) {
+                Ok(id) => Reply::Json(201, json!({ "id": id })),
+                Err(e) => Reply::error(500, format!("Failed to append task: {e:#}")),
+            }
+        }
+        (Method::Put, path) if path.starts_with("/tasks/") => {
+            let Ok(id) = path["/tasks/".len()..].parse::<AsstTaskId>() else {
+                return Reply::error(404, "Invalid task id");
+            };
+            let params: TaskParams = match serde_json::from_str(body) {
+                Ok(params) => params,
+                Err(e) => return Reply::error(400, format!("Invalid params: {e}")),
+            };
+            match ctrl.set_task_params(id, &params.params.to_string()) {
+                Ok(()) => Reply::ok(json!({ "id": id })),
+                Err(e) => Reply::error(500, format!("Failed to set task params: {e:#}")),
+            }
+        }
+        (Method::Get, "/screenshot") => {
+            let fresh = query.split('&').any(|q| q == "fresh" || q == "fresh=true");
+            match ctrl.screenshot(fresh) {
+                Ok(Some(image)) => Reply::Png(image),
+                Ok(None) => Reply::error(404, "No screenshot available"),
+                Err(e) => Reply::error(500, format!("Failed to get screenshot: {e:#}")),
+            }
+        }
+        (
+            _,
+            "/status" | "/connect" | "/start" | "/stop" | "/tasks" | "/screenshot" | "/messages",
+        ) => Reply::error(405, "Method not allowed"),
+        _ => Reply::error(404, "Not found"),
+    }
+}
+
+/// Broadcast callback messages to all connected streams
+#[derive(Default)]
+struct Hub {
+    subscribers: Mutex<Vec<SyncSender<String>>>,
+}
+
+impl Hub {
+    fn subscribe(&self) -> Receiver<String> {
+        let (tx, rx) = sync_channel(STREAM_BUFFER);
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    fn publish(&self, kind: MessageKind, details: &Value) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let mut line = json!({ "kind": format!("{kind:?}"), "details": details }).to_string();
+        line.push('\n');
+        subscribers.retain(|tx| match tx.try_send(line.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                debug!("Message stream is too slow, dropping message");
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+/// Write messages as JSON lines until the client disconnects
+///
+/// The response is written manually, because `tiny_http` buffers chunked responses,
+/// which would delay messages until the buffer is full.
+fn stream_messages(request: Request, rx: Receiver<String>) {
+    let mut writer = request.into_writer();
+    let header = "HTTP/1.1 200 OK\r\n\
+                  Content-Type: application/x-ndjson\r\n\
+                  Cache-Control: no-cache\r\n\
+                  Connection: close\r\n\r\n";
+    if writer
+        .write_all(header.as_bytes())
+        .and_then(|()| writer.flush())
+        .is_err()
+    {
+        return;
+    }
+
+    for line in rx {
+        if writer
+            .write_all(line.as_bytes())
+            .and_then(|()| writer.flush())
+            .is_err()
+        {
+            debug!("Message stream closed");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use std::io::{BufRead, BufReader};
+
+    use super::*;
+
+    /// A fake controller recording the calls
+    #[derive(Default)]
+    struct Fake {
+        tasks: Mutex<Vec<(TaskType, String)>>,
+        running: AtomicBool,
+        connected: AtomicBool,
+    }
+
+    impl Controller for Fake {
+        fn connect(&self) -> Result<()> {
+            self.connected.store(true, atomic::Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn append_task(&self, task_type: TaskType, params: &str) -> Result<AsstTaskId> {
+            let mut tasks = self.tasks.lock().unwrap();
+            tasks.push((task_type, params.to_owned()));
+            Ok(tasks.len() as AsstTaskId)
+        }
+
+        fn set_task_params(&self, id: AsstTaskId, params: &str) -> Result<()> {
+            let mut tasks = self.tasks.lock().unwrap();
+            let task = tasks
+                .get_mut((id as usize).wrapping_sub(1))
+                .context("Task not found")?;
+            task.1 = params.to_owned();
+            Ok(())
+        }
+
+        fn start(&self) -> Result<()> {
+            if !self.connected.load(atomic::Ordering::Relaxed) {
+                anyhow::bail!("Not connected");
+            }
+            self.running.store(true, atomic::Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn stop(&self) -> Result<()> {
+            self.running.store(false, atomic::Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn running(&self) -> bool {
+            self.running.load(atomic::Ordering::Relaxed)
+        }
+
+        fn connected(&self) -> bool {
+            self.connected.load(atomic::Ordering::Relaxed)
+        }
+
+        fn screenshot(&self, fresh: bool) -> Result<Option<Vec<u8>>> {
+            Ok(fresh.then(|| b"\x89PNG".to_vec()))
+        }
+    }
+
+    fn status(running: bool, connected: bool) -> Reply {
+        Reply::ok(json!({ "running": running, "connected": connected }))
+    }
+
+    #[test]
+    fn handle_requests() {
+        let fake = Fake::default();
+        let get = |url| handle(&fake, &Method::Get, url, "");
+        let post = |url, body| handle(&fake, &Method::Post, url, body);
+        let put = |url, body| handle(&fake, &Method::Put, url, body);
+
+        assert_eq!(get("/status"), status(false, false));
+        assert!(matches!(post("/start", ""), Reply::Json(500, _)));
+        assert_eq!(post("/connect", ""), status(false, true));
+
+        assert_eq!(
+            post("/tasks", r#"{"type": "Fight", "params": {"stage": "1-7"}}"#),
+            Reply::Json(201, json!({ "id": 1 }))
+        );
+        assert_eq!(
+            post("/tasks", r#"{"type": "StartUp"}"#),
+            Reply::Json(201, json!({ "id": 2 }))
+        );
+        assert!(matches!(
+            post("/tasks", r#"{"type": "NotATask"}"#),
+            Reply::Json(400, _)
+        ));
+        assert!(matches!(post("/tasks", "not json"), Reply::Json(400, _)));
+        assert_eq!(
+            put("/tasks/1", r#"{"params": {"stage": "CE-6"}}"#),
+            Reply::ok(json!({ "id": 1 }))
+        );
+        assert!(matches!(
+            put("/tasks/9", r#"{"params": {}}"#),
+            Reply::Json(500, _)
+        ));
+        assert!(matches!(put("/tasks/x", "{}"), Reply::Json(404, _)));
+        assert_eq!(*fake.tasks.lock().unwrap(), vec![
+            (TaskType::Fight, r#"{"stage":"CE-6"}"#.to_owned()),
+            (TaskType::StartUp, "{}".to_owned()),
+        ]);
+
+        assert_eq!(post("/start", ""), status(true, true));
+        assert_eq!(post("/stop", ""), status(false, true));
+
+        assert!(matches!(get("/screenshot"), Reply::Json(404, _)));
+        assert_eq!(
+            get("/screenshot?fresh=true"),
+            Reply::Png(b"\x89PNG".to_vec())
+        );
+
+        assert!(matches!(get("/start"), Reply::Json(405, _)));
+        assert!(matches!(get("/unknown"), Reply::Json(404, _)));
+    }
+
+    #[test]
+    fn hub_publish() {
+        let hub = Hub::default();
+        // no subscriber, should not panic
+        hub.publish(MessageKind::AllTasksCompleted, &json!({}));
+
+        let rx1 = hub.subscribe();
+        let rx2 = hub.subscribe();
+        drop(rx2);
+
+        hub.publish(
+            MessageKind::TaskChainStart,
+            &json!({ "taskchain": "Fight" }),
+        );
+        assert_eq!(hub.subscribers.lock().unwrap().len(), 1);
+
+        let line: Value = serde_json::from_str(&rx1.recv().unwrap()).unwrap();
+        assert_eq!(
+            line,
+            json!({ "kind": "TaskChainStart", "details": { "taskchain": "Fight" } })
+        );
+    }
+
+    #[test]
+    fn serve_over_http() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let url = |path: &str| format!("http://{addr}{path}");
+
+        let hub = Arc::new(Hub::default());
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let hub = Arc::clone(&hub);
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || serve_requests(&server, &Fake::default(), &hub, &stop))
+        };
+
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+
+        let mut response = agent.post(url("/connect")).send_empty().unwrap();
+        assert_eq!(response.status(), 200);
+        let body: Value = response.body_mut().read_json().unwrap();
+        assert_eq!(body, json!({ "running": false, "connected": true }));
+
+        let mut response = agent
+            .post(url("/tasks"))
+            .send_json(json!({ "type": "Fight", "params": { "stage": "1-7" } }))
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        let body: Value = response.body_mut().read_json().unwrap();
+        assert_eq!(body, json!({ "id": 1 }));
+
+        let response = agent.get(url("/nowhere")).call().unwrap();
+        assert_eq!(response.status(), 404);
+
+        // Stream messages published by the callback
+        let response = agent.get(url("/messages")).call().unwrap();
+        assert_eq!(response.status(), 200);
+        // wait until the stream is subscribed
+        while hub.subscribers.lock().unwrap().is_empty() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        hub.publish(MessageKind::AllTasksCompleted, &json!({ "uuid": "test" }));
+        let mut lines = BufReader::new(response.into_body().into_reader()).lines();
+        let line: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(
+            line,
+            json!({ "kind": "AllTasksCompleted", "details": { "uuid": "test" } })
+        );
+
+        stop.store(true, atomic::Ordering::Relaxed);
+        handle.join().unwrap();
+    }
+}
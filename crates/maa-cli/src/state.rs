@@ -1,6 +1,6 @@
 //! Module for managing the global state of the maa-cli.
 
-use std::sync::LazyLock;
+use std::{path::PathBuf, sync::LazyLock};
 
 use semver::Version;
 use ureq::{
@@ -23,6 +23,28 @@ pub static CORE_VERSION: LazyLock<Option<Version>> = LazyLock::new(|| {
     })
 });
 
+#[cfg(test)]
+thread_local! {
+    static TEST_DIR: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Directory to save states of maa-cli, like the history and the recognized depot
+///
+/// In tests, it can be replaced for the current thread by [`set_test_dir`].
+pub fn dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = TEST_DIR.with_borrow(Clone::clone) {
+        return dir;
+    }
+    maa_dirs::state().to_owned()
+}
+
+/// Replace the state directory of the current thread, `None` to use the default one
+#[cfg(test)]
+pub fn set_test_dir(dir: Option<PathBuf>) {
+    TEST_DIR.set(dir);
+}
+
 pub static AGENT: LazyLock<Agent> = LazyLock::new(|| {
     Agent::config_builder()
         .tls_config(
@@ -1,6 +1,7 @@
 mod download_impl;
 pub mod etag;
 pub mod mirror;
+mod segmented;
 
 use std::{borrow::Cow, path::Path, time::Duration};
 
@@ -18,6 +19,7 @@ pub struct DownloadOptions<'a, I: Iterator<Item = Cow<'a, str>>> {
     url: Cow<'a, str>,
     test_duration: u64,
     mirror_opts: Option<MirrorOptions<'a, I>>,
+    connections: usize,
 }
 
 impl<'a, I: Iterator<Item = Cow<'a, str>>> DownloadOptions<'a, I> {
@@ -30,8 +32,19 @@ impl<'a, I: Iterator<Item = Cow<'a, str>>> DownloadOptions<'a, I> {
             url,
             test_duration,
             mirror_opts,
+            connections: 1,
         }
     }
+
+    /// Download with up to given number of connections in parallel
+    ///
+    /// The file is split into segments downloaded from the chosen URL and other mirrors in turn,
+    /// each segment can be resumed separately. It falls back to a single connection if the server
+    /// does not support range requests or the file is too small.
+    pub fn with_connections(mut self, connections: usize) -> Self {
+        self.connections = connections.max(1);
+        self
+    }
 }
 
 /// Download a file to given path
@@ -39,6 +52,8 @@ impl<'a, I: Iterator<Item = Cow<'a, str>>> DownloadOptions<'a, I> {
 /// If any mirrors are provided, it will perform a speed test to choose the fastest mirror.
 /// If no mirrors are provided or the speed test is skipped, it will download from the default URL.
 ///
+/// With multiple connections, the file is downloaded in segments from the chosen URL and mirrors.
+///
 /// Progress bar is optional.
 pub fn download<'a, V: Verifier>(
     agent: &ureq::Agent,
@@ -57,31 +72,78 @@ pub fn download<'a, V: Verifier>(
     }
 
     let url = opts.url;
-    let chosen_url = match (opts.test_duration, opts.mirror_opts) {
-        (0, _) | (_, None) => url,
-        (t, Some(opts)) => {
-            download_main_ui.set_message("Speed testing to find fastest mirror...");
-            let choose_mirror_ui = style.init_spinner();
-            ui.add(choose_mirror_ui.clone());
+    // Mirrors are kept to download segments from after the speed test
+    let (mirrors, max_bytes) = match opts.mirror_opts {
+        Some(mirror_opts) => (mirror_opts.mirrors.collect(), mirror_opts.max_bytes),
+        None => (Vec::new(), 0),
+    };
+    let chosen_url = if opts.test_duration == 0 || mirrors.is_empty() {
+        url.clone()
+    } else {
+        download_main_ui.set_message("Speed testing to find fastest mirror...");
+        let choose_mirror_ui = style.init_spinner();
+        ui.add(choose_mirror_ui.clone());
+
+        let max_time = Duration::from_secs(opts.test_duration);
+        let mirror_opts = MirrorOptions::new(mirrors.iter().cloned(), max_bytes);
+        let chosen_url = fastest_mirror(
+            agent,
+            url.clone(),
+            max_time,
+            mirror_opts,
+            choose_mirror_ui.clone(),
+        );
+        choose_mirror_ui.finish_and_clear();
+        chosen_url
+    };
 
-            let max_time = Duration::from_secs(t);
-            let url = fastest_mirror(agent, url, max_time, opts, choose_mirror_ui.clone());
-            choose_mirror_ui.finish_and_clear();
-            url
+    // Size of the file to split into segments, only probed with multiple connections
+    let total = if opts.connections > 1 {
+        match segmented::probe(agent, &chosen_url) {
+            Ok(total) => total,
+            Err(e) => {
+                log::debug!("Failed to probe {chosen_url}: {e}");
+                None
+            }
         }
+    } else {
+        None
     };
 
     download_main_ui.set_message(format!("Downloading: {chosen_url}"));
 
     let download_progress_ui = style.init_bar();
     ui.add(download_progress_ui.clone());
-    download_impl::download(
-        agent,
-        chosen_url.as_ref(),
-        dest,
-        download_progress_ui.clone(),
-        verifier,
-    )?;
+    if let Some(total) = total
+        && let segments = segmented::segment_count(total, opts.connections)
+        && segments > 1
+    {
+        // Segments start from the chosen URL, then the others in turn
+        let urls: Vec<_> = std::iter::once(chosen_url.clone())
+            .chain(
+                std::iter::once(url)
+                    .chain(mirrors)
+                    .filter(|u| *u != chosen_url),
+            )
+            .collect();
+        segmented::download(
+            agent,
+            &urls,
+            total,
+            segments,
+            dest,
+            download_progress_ui.clone(),
+            verifier,
+        )?;
+    } else {
+        download_impl::download(
+            agent,
+            chosen_url.as_ref(),
+            dest,
+            download_progress_ui.clone(),
+            verifier,
+        )?;
+    }
     download_progress_ui.finish_and_clear();
     download_main_ui.finish_with_message("Download complete");
 
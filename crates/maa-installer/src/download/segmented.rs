@@ -0,0 +1,461 @@
+//! Download a file in segments over multiple connections.
+//!
+//! The file is split into ranges, and each range is downloaded by its own connection to a part
+//! file `<dest>.partial.<i>`, so that every segment can be resumed independently. The layout of
+//! segments is kept in `<dest>.segments`, part files of another layout are discarded.
+//! Once all segments are downloaded, they are joined in order while feeding the verifier.
+
+use std::{
+    borrow::Cow,
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use indicatif::ProgressBar;
+
+use crate::{
+    error::{Error, ErrorKind, Result, WithDesc},
+    verify::Verifier,
+};
+
+/// Minimum size of a segment, smaller files are downloaded with fewer connections
+pub(super) const MIN_SEGMENT_SIZE: u64 = 1 << 20;
+
+/// Number of segments to download a file of given size with at most `connections` connections
+pub(super) fn segment_count(total: u64, connections: usize) -> usize {
+    (total / MIN_SEGMENT_SIZE).clamp(1, connections as u64) as usize
+}
+
+/// Parse the start and the total size from a `Content-Range` header, e.g. `bytes 0-99/1000`
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()?))
+}
+
+fn content_range(resp: &ureq::http::Response<ureq::Body>) -> Option<(u64, u64)> {
+    resp.headers()
+        .get("content-range")
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range)
+}
+
+/// Get the total size of the file, `None` if the server does not support range requests
+pub(super) fn probe(agent: &ureq::Agent, url: &str) -> Result<Option<u64>> {
+    let resp = agent
+        .get(url)
+        .header("Range", "bytes=0-0")
+        .call()
+        .with_desc("Failed to send download request")?;
+
+    if resp.status() != 206 {
+        return Ok(None);
+    }
+    Ok(content_range(&resp).map(|(_, total)| total))
+}
+
+/// Split `0..total` into `count` contiguous ranges of nearly equal size
+fn split(total: u64, count: usize) -> Vec<Range<u64>> {
+    let count = count as u64;
+    (0..count)
+        .map(|i| total * i / count..total * (i + 1) / count)
+        .collect()
+}
+
+fn part_path(dest: &Path, index: usize) -> PathBuf {
+    dest.with_added_extension(format!("partial.{index}"))
+}
+
+fn layout_path(dest: &Path) -> PathBuf {
+    dest.with_added_extension("segments")
+}
+
+/// Remove part files and the layout of a previous download
+fn clean(dest: &Path) -> Result<()> {
+    let layout_path = layout_path(dest);
+    let Ok(layout) = fs::read_to_string(&layout_path) else {
+        return Ok(());
+    };
+
+    let count = layout
+        .split_whitespace()
+        .nth(1)
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0);
+    for index in 0..count {
+        let path = part_path(dest, index);
+        if path.exists() {
+            fs::remove_file(&path).with_desc("Failed to remove part file")?;
+        }
+    }
+    fs::remove_file(&layout_path).with_desc("Failed to remove segment layout")?;
+
+    Ok(())
+}
+
+/// Download a file of `total` bytes in `count` segments to given path
+///
+/// Segments are assigned to `urls` in turn, if a URL fails, the segment is resumed from the
+/// next one. The joined file is verified by `verifier` before it is moved to `dest`.
+pub(super) fn download(
+    agent: &ureq::Agent,
+    urls: &[Cow<'_, str>],
+    total: u64,
+    count: usize,
+    dest: &Path,
+    ui: ProgressBar,
+    mut verifier: impl Verifier,
+) -> Result<()> {
+    let ranges = split(total, count);
+
+    // Keep part files only if they are downloaded with the same layout
+    let layout = format!("{total} {count}");
+    let layout_path = layout_path(dest);
+    if fs::read_to_string(&layout_path).ok().as_deref() != Some(layout.as_str()) {
+        clean(dest)?;
+        fs::write(&layout_path, &layout).with_desc("Failed to write segment layout")?;
+    }
+
+    ui.set_length(total);
+    ui.set_position(0);
+
+    std::thread::scope(|s| {
+        let handles: Vec<_> = ranges
+            .iter()
+            .enumerate()
+            .map(|(index, range)| {
+                let path = part_path(dest, index);
+                let ui = ui.clone();
+                s.spawn(move || download_segment(agent, urls, index, range.clone(), &path, &ui))
+            })
+            .collect();
+
+        // Segments are joined in the scope even if one of them failed
+        handles.into_iter().try_for_each(|handle| {
+            handle.join().unwrap_or_else(|_| {
+                Err(Error::new(ErrorKind::Other).with_desc("Download thread panicked"))
+            })
+        })
+    })?;
+
+    // Join segments in order
+    let partial_path = dest.with_added_extension("partial");
+    let mut file = File::create(&partial_path).with_desc("Failed to create new file")?;
+    let mut buffer = [0; 8192];
+    for index in 0..ranges.len() {
+        let mut part = File::open(part_path(dest, index)).with_desc("Failed to open part file")?;
+        loop {
+            let bytes_read = part.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            let chunk = &buffer[..bytes_read];
+            file.write_all(chunk)
+                .with_desc("Failed to write data to file")?;
+            verifier.update(chunk);
+        }
+    }
+    file.flush()?;
+    drop(file);
+    clean(dest)?;
+
+    // Verify the downloaded file
+    if let Err(e) = verifier.verify() {
+        fs::remove_file(&partial_path).with_desc("Failed to remove partial file")?;
+        return Err(e);
+    }
+
+    fs::rename(partial_path, dest).with_desc("Failed to rename partial file")?;
+
+    Ok(())
+}
+
+/// Download a segment to its part file, resuming from the bytes already downloaded
+fn download_segment(
+    agent: &ureq::Agent,
+    urls: &[Cow<'_, str>],
+    index: usize,
+    range: Range<u64>,
+    path: &Path,
+    ui: &ProgressBar,
+) -> Result<()> {
+    let len = range.end - range.start;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_desc("Failed to open part file")?;
+    let mut downloaded = file.metadata()?.len();
+    if downloaded > len {
+        file.set_len(0).with_desc("Failed to truncate part file")?;
+        downloaded = 0;
+    }
+    ui.inc(downloaded);
+
+    let mut error = None;
+    for url in urls.iter().cycle().skip(index).take(urls.len()) {
+        if downloaded == len {
+            break;
+        }
+
+        let start = range.start + downloaded;
+        if let Err(e) = fetch_range(agent, url, start..range.end, &mut file, ui, &mut downloaded) {
+            log::debug!("Failed to download segment {index} from {url}: {e}");
+            error = Some(e);
+        }
+    }
+
+    match error {
+        Some(e) if downloaded < len => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Append given range of the file at `url` to `file`
+fn fetch_range(
+    agent: &ureq::Agent,
+    url: &str,
+    range: Range<u64>,
+    file: &mut File,
+    ui: &ProgressBar,
+    downloaded: &mut u64,
+) -> Result<()> {
+    let mut resp = agent
+        .get(url)
+        .header("Range", format!("bytes={}-{}", range.start, range.end - 1))
+        .call()
+        .with_desc("Failed to send download request")?;
+
+    if resp.status() != 206 || content_range(&resp).map(|(start, _)| start) != Some(range.start) {
+        return Err(Error::new(ErrorKind::Network)
+            .with_desc(format!("{url} does not support range requests")));
+    }
+
+    let len = range.end - range.start;
+    let mut fetched = 0;
+    let mut buffer = [0; 8192];
+    let mut reader = resp.body_mut().as_reader().take(len);
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..bytes_read])
+            .with_desc("Failed to write data to part file")?;
+        ui.inc(bytes_read as u64);
+        fetched += bytes_read as u64;
+        *downloaded += bytes_read as u64;
+    }
+
+    if fetched < len {
+        return Err(Error::new(ErrorKind::Network)
+            .with_desc(format!("Connection closed after {fetched} of {len} bytes")));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage_nightly, coverage(off))]
+mod tests {
+    use tiny_http::{Header, Response, Server};
+
+    use super::*;
+    use crate::verify::SizeVerifier;
+
+    /// Serve `data` at a local URL, range requests are supported only if `ranges` is true
+    fn serve(data: Vec<u8>, ranges: bool) -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/asset", server.server_addr());
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let range = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Range"))
+                    .and_then(|h| {
+                        let (start, end) =
+                            h.value.as_str().strip_prefix("bytes=")?.split_once('-')?;
+                        Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?))
+                    })
+                    .filter(|_| ranges);
+                let response = match range {
+                    Some((start, end)) => {
+                        let end = end.min(data.len() - 1);
+                        let content_range = format!("bytes {start}-{end}/{}", data.len());
+                        Response::from_data(&data[start..=end])
+                            .with_status_code(206)
+                            .with_header(
+                                Header::from_bytes("Content-Range", content_range).unwrap(),
+                            )
+                    }
+                    None => Response::from_data(data.clone()),
+                };
+                let _ = request.respond(response);
+            }
+        });
+        url
+    }
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_segment_count() {
+        assert_eq!(segment_count(0, 4), 1);
+        assert_eq!(segment_count(MIN_SEGMENT_SIZE - 1, 4), 1);
+        assert_eq!(segment_count(3 * MIN_SEGMENT_SIZE, 4), 3);
+        assert_eq!(segment_count(100 * MIN_SEGMENT_SIZE, 4), 4);
+        assert_eq!(segment_count(100 * MIN_SEGMENT_SIZE, 1), 1);
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(parse_content_range("bytes 0-0/1234"), Some((0, 1234)));
+        assert_eq!(parse_content_range("bytes 100-199/1000"), Some((100, 1000)));
+        assert_eq!(parse_content_range("bytes */1000"), None);
+        assert_eq!(parse_content_range("bytes 0-99/*"), None);
+        assert_eq!(parse_content_range("0-99/1000"), None);
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(10, 3), [0..3, 3..6, 6..10]);
+        assert_eq!(split(9, 3), [0..3, 3..6, 6..9]);
+        assert_eq!(split(10, 1), vec![0..10]);
+    }
+
+    #[test]
+    fn test_probe() {
+        let agent = ureq::Agent::new_with_defaults();
+        assert_eq!(probe(&agent, &serve(data(100), true)).unwrap(), Some(100));
+        assert_eq!(probe(&agent, &serve(data(100), false)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_download() {
+        let agent = ureq::Agent::new_with_defaults();
+        let data = data(10000);
+        let url = serve(data.clone(), true);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path().join("asset");
+
+        download(
+            &agent,
+            &[Cow::Borrowed(&url)],
+            10000,
+            3,
+            &dest,
+            ProgressBar::hidden(),
+            SizeVerifier::new(10000),
+        )
+        .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), data);
+        assert!(!layout_path(&dest).exists());
+        assert!(!part_path(&dest, 0).exists());
+        assert!(!dest.with_added_extension("partial").exists());
+    }
+
+    #[test]
+    fn test_download_resume() {
+        let agent = ureq::Agent::new_with_defaults();
+        let data = data(10000);
+        let url = serve(data.clone(), true);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path().join("asset");
+
+        // Segment 0 is partially downloaded, segment 1 is corrupted and too long
+        fs::write(layout_path(&dest), "10000 3").unwrap();
+        fs::write(part_path(&dest, 0), &data[..1000]).unwrap();
+        fs::write(part_path(&dest, 1), vec![0; 5000]).unwrap();
+
+        let ui = ProgressBar::hidden();
+        download(
+            &agent,
+            &[Cow::Borrowed(&url)],
+            10000,
+            3,
+            &dest,
+            ui.clone(),
+            SizeVerifier::new(10000),
+        )
+        .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), data);
+        assert_eq!(ui.position(), 10000);
+
+        // Part files of another layout are discarded
+        fs::remove_file(&dest).unwrap();
+        fs::write(layout_path(&dest), "10000 2").unwrap();
+        fs::write(part_path(&dest, 0), vec![0; 3000]).unwrap();
+        download(
+            &agent,
+            &[Cow::Borrowed(&url)],
+            10000,
+            3,
+            &dest,
+            ProgressBar::hidden(),
+            SizeVerifier::new(10000),
+        )
+        .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), data);
+    }
+
+    #[test]
+    fn test_download_mirrors() {
+        let agent = ureq::Agent::new_with_defaults();
+        let data = data(10000);
+        let urls = [
+            Cow::Owned(serve(data.clone(), true)),
+            // Segments assigned to this mirror fall back to the others
+            Cow::Owned(serve(data.clone(), false)),
+        ];
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest = temp_dir.path().join("asset");
+
+        download(
+            &agent,
+            &urls,
+            10000,
+            4,
+            &dest,
+            ProgressBar::hidden(),
+            SizeVerifier::new(10000),
+        )
+        .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), data);
+
+        // All URLs fail
+        let dest = temp_dir.path().join("failed");
+        let err = download(
+            &agent,
+            &urls[1..],
+            10000,
+            2,
+            &dest,
+            ProgressBar::hidden(),
+            SizeVerifier::new(10000),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Network);
+        assert!(!dest.exists());
+
+        // Verification failed
+        let dest = temp_dir.path().join("mismatch");
+        let err = download(
+            &agent,
+            &urls[..1],
+            10000,
+            2,
+            &dest,
+            ProgressBar::hidden(),
+            SizeVerifier::new(9999),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+        assert!(!dest.exists());
+        assert!(!dest.with_added_extension("partial").exists());
+    }
+}
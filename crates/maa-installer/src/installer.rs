@@ -14,11 +14,14 @@ use indicatif::{ProgressBar, ProgressStyle};
 use semver::Version;
 use ureq::Agent;
 
+#[cfg(feature = "signature")]
+use crate::verify::signature::{PublicKey, SignatureVerifier};
 use crate::{
     download::{DownloadOptions, download, etag::download_with_etag},
-    error::{Result, WithDesc},
+    error::{Error, ErrorKind, Result, WithDesc},
     extract::ArchiveFile,
     manifest::{Asset, Manifest},
+    verify::Verifier,
 };
 
 pub struct Installer<'a, M, MP, E> {
@@ -26,12 +29,14 @@ pub struct Installer<'a, M, MP, E> {
     manifest_url: Cow<'a, str>,
     manifest_processor: MP,
     test_duration: u64,
+    connections: usize,
     current_version: Option<&'a Version>,
     extractor: E,
     progress_style: InstallerStyle,
-    pre_install_hook: Option<Box<dyn FnOnce() -> Result<()> + 'a>>,
-    post_install_hook: Option<Box<dyn FnOnce() -> Result<()> + 'a>>,
+    pre_install_hook: Hook<'a>,
+    post_install_hook: Hook<'a>,
     min_check_interval: Option<Duration>,
+    signature: SignaturePolicy,
     _marker: std::marker::PhantomData<M>,
 }
 
@@ -91,11 +96,13 @@ where
             manifest_processor,
             extractor,
             test_duration: 0,
+            connections: 1,
             current_version: None,
             progress_style: InstallerStyle::default(),
             pre_install_hook: None,
             post_install_hook: None,
             min_check_interval: None,
+            signature: SignaturePolicy::default(),
             _marker: std::marker::PhantomData,
         }
     }
@@ -120,6 +127,12 @@ where
         self
     }
 
+    /// Download assets with up to given number of connections in parallel
+    pub fn with_connections(mut self, connections: usize) -> Self {
+        self.connections = connections;
+        self
+    }
+
     pub fn with_current_version(mut self, current_version: &'a Version) -> Self {
         self.current_version = Some(current_version);
         self
@@ -130,6 +143,20 @@ where
         self
     }
 
+    /// Verify detached signatures of assets with given public key
+    ///
+    /// The signature is fetched from [`Asset::signature_url`], or read from `<asset>.sig` next to
+    /// the asset when installing from local files. If it is not available, the asset is
+    /// installed with a warning, or rejected when `required` is true.
+    #[cfg(feature = "signature")]
+    pub fn with_signature_key(mut self, key: PublicKey, required: bool) -> Self {
+        self.signature = SignaturePolicy {
+            key: Some(key),
+            required,
+        };
+        self
+    }
+
     pub fn exec(self, cache_dir: &Path, manifest_name: &str) -> Result<()> {
         let fetching_ui = self.progress_style.init_spinner();
 
@@ -169,33 +196,189 @@ where
             return Ok(());
         };
 
+        // Fetch signature from the primary URL, since mirrors may not be trusted
+        let signature_url = asset.signature_url();
+        let signature = self.signature.verifier(&signature_url, || {
+            Ok(self
+                .agent
+                .get(signature_url.as_ref())
+                .call()?
+                .into_body()
+                .read_to_string()?)
+        })?;
+
         // Download asset
         let dest = cache_dir.join(asset.name());
         let download_opts =
-            DownloadOptions::new(asset.url(), self.test_duration, asset.mirror_opts());
+            DownloadOptions::new(asset.url(), self.test_duration, asset.mirror_opts())
+                .with_connections(self.connections);
         download(
             &self.agent,
             download_opts,
             &dest,
             &self.progress_style,
-            asset.verifier()?,
+            (asset.verifier()?, signature),
         )?;
 
-        if let Some(pre_install_hook) = self.pre_install_hook {
-            pre_install_hook()?;
+        install(
+            &dest,
+            self.extractor,
+            &self.progress_style,
+            self.pre_install_hook,
+            self.post_install_hook,
+        )
+    }
+
+    /// Install from a local version manifest instead of fetching it
+    ///
+    /// The asset is read from the directory of the manifest by its name, so nothing is
+    /// downloaded. The asset is verified by its own verifier and the verifier created by
+    /// `verifier` from the path of the asset, e.g. one built from a checksum file.
+    pub fn exec_offline<V, F>(self, manifest_path: &Path, verifier: F) -> Result<()>
+    where
+        V: Verifier,
+        F: FnOnce(&Path) -> Result<V>,
+    {
+        let reading_ui = self.progress_style.init_spinner();
+        reading_ui.set_message("Reading version manifest...");
+
+        let manifest_file = File::open(manifest_path).then_with_desc(|| {
+            format!(
+                "Failed to open version manifest {}",
+                manifest_path.display()
+            )
+        })?;
+        let manifest = (self.manifest_processor)(manifest_file)?;
+
+        if let Some(current_version) = self.current_version
+            && current_version == manifest.version()
+        {
+            reading_ui.finish_with_message("Read version manifest, already up-to-date!");
+            return Ok(());
         }
 
-        // Extract asset (Install)
-        let extract_ui = self.progress_style.init_spinner();
-        let archive = ArchiveFile::new(&dest);
-        archive.extract(extract_ui.clone(), self.extractor)?;
-        if let Some(post_install_hook) = self.post_install_hook {
-            post_install_hook()?;
+        let Some(asset) = manifest.asset() else {
+            reading_ui.finish_with_message("No asset found for current platform");
+            return Ok(());
+        };
+        reading_ui.finish_with_message(format_fetched_message(
+            self.current_version,
+            manifest.version(),
+        ));
+
+        let archive = manifest_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(asset.name());
+        if !archive.is_file() {
+            return Err(Error::new(ErrorKind::Io).with_desc(format!(
+                "Asset {} not found next to the version manifest",
+                archive.display()
+            )));
+        }
+        let signature = self.signature.local_verifier(&archive)?;
+        ((asset.verifier()?, verifier(&archive)?), signature).verify_file(&archive)?;
+
+        install(
+            &archive,
+            self.extractor,
+            &self.progress_style,
+            self.pre_install_hook,
+            self.post_install_hook,
+        )
+    }
+
+    /// Install from a local archive, without any version manifest
+    pub fn exec_archive(self, archive: &Path, verifier: impl Verifier) -> Result<()> {
+        let signature = self.signature.local_verifier(archive)?;
+        (verifier, signature).verify_file(archive)?;
+        install(
+            archive,
+            self.extractor,
+            &self.progress_style,
+            self.pre_install_hook,
+            self.post_install_hook,
+        )
+    }
+}
+
+type Hook<'a> = Option<Box<dyn FnOnce() -> Result<()> + 'a>>;
+
+/// How to verify detached signatures of assets
+#[derive(Default)]
+struct SignaturePolicy {
+    #[cfg(feature = "signature")]
+    key: Option<PublicKey>,
+    #[cfg(feature = "signature")]
+    required: bool,
+}
+
+impl SignaturePolicy {
+    /// Verifier of the signature fetched by `fetch`, `None` if no key is trusted
+    #[cfg(feature = "signature")]
+    fn verifier(
+        &self,
+        source: &str,
+        fetch: impl FnOnce() -> Result<String>,
+    ) -> Result<Option<SignatureVerifier>> {
+        let Some(key) = &self.key else {
+            return Ok(None);
+        };
+
+        match fetch() {
+            Ok(content) => SignatureVerifier::from_signature_file(key.clone(), &content)
+                .map(Some)
+                .then_with_desc(|| format!("Invalid signature from {source}")),
+            Err(e) if self.required => {
+                Err(Error::new(ErrorKind::Verify)
+                    .with_source(e)
+                    .with_desc(format!(
+                        "Signature is required but not available from {source}"
+                    )))
+            }
+            Err(e) => {
+                log::warn!(
+                    "No signature available from {source}, skip signature verification: {e}"
+                );
+                Ok(None)
+            }
         }
-        extract_ui.finish_with_message("Installation completed successfully!");
+    }
 
+    #[cfg(not(feature = "signature"))]
+    fn verifier(&self, _source: &str, _fetch: impl FnOnce() -> Result<String>) -> Result<()> {
         Ok(())
     }
+
+    /// Verifier of the signature `<archive>.sig` next to a local archive
+    fn local_verifier(&self, archive: &Path) -> Result<impl Verifier + use<>> {
+        let path = archive.with_added_extension("sig");
+        self.verifier(&path.display().to_string(), || {
+            std::fs::read_to_string(&path).map_err(Into::into)
+        })
+    }
+}
+
+/// Extract the archive (Install), with hooks run before and after
+fn install(
+    archive: &Path,
+    extractor: impl FnMut(&Path) -> Option<PathBuf>,
+    style: &InstallerStyle,
+    pre_install_hook: Hook<'_>,
+    post_install_hook: Hook<'_>,
+) -> Result<()> {
+    if let Some(pre_install_hook) = pre_install_hook {
+        pre_install_hook()?;
+    }
+
+    let extract_ui = style.init_spinner();
+    ArchiveFile::new(archive).extract(extract_ui.clone(), extractor)?;
+    if let Some(post_install_hook) = post_install_hook {
+        post_install_hook()?;
+    }
+    extract_ui.finish_with_message("Installation completed successfully!");
+
+    Ok(())
 }
 
 fn format_fetched_message(current_version: Option<&Version>, latest_version: &Version) -> String {
@@ -298,6 +481,7 @@ mod tests {
             |_path| None,
         );
         assert_eq!(installer.test_duration, 0);
+        assert_eq!(installer.connections, 1);
         assert_eq!(installer.current_version, None);
         assert!(installer.min_check_interval.is_none());
 
@@ -311,6 +495,15 @@ mod tests {
         .with_test_duration(10);
         assert_eq!(installer.test_duration, 10);
 
+        let installer = Installer::new(
+            agent.clone(),
+            "https://example.com/manifest.json",
+            |_body| -> Result<TestManifest> { unreachable!() },
+            |_path| None,
+        )
+        .with_connections(4);
+        assert_eq!(installer.connections, 4);
+
         // Test with_current_version sets the correct reference
         let installer = Installer::new(
             agent.clone(),
@@ -366,4 +559,206 @@ mod tests {
         assert_eq!(installer.current_version.unwrap(), &Version::new(2, 0, 0));
         assert_eq!(installer.min_check_interval, Some(Duration::from_secs(300)));
     }
+
+    // A manifest read from a local file with content `<version> <size of asset>`
+    struct LocalManifest {
+        version: Version,
+        size: u64,
+    }
+
+    impl Manifest for LocalManifest {
+        type Asset<'a>
+            = LocalAsset
+        where
+            Self: 'a;
+
+        fn version(&self) -> &Version {
+            &self.version
+        }
+
+        fn asset(&self) -> Option<Self::Asset<'_>> {
+            Some(LocalAsset { size: self.size })
+        }
+    }
+
+    struct LocalAsset {
+        size: u64,
+    }
+
+    impl Asset for LocalAsset {
+        type Verifier = crate::verify::SizeVerifier;
+
+        fn name(&self) -> &str {
+            "test.tar"
+        }
+
+        fn url(&self) -> Cow<'_, str> {
+            Cow::Borrowed("https://example.com/test.tar")
+        }
+
+        fn verifier(&self) -> Result<Self::Verifier> {
+            Ok(crate::verify::SizeVerifier::new(self.size))
+        }
+    }
+
+    fn read_local_manifest(mut file: File) -> Result<LocalManifest> {
+        use std::io::Read;
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let (version, size) = content.split_once(' ').unwrap();
+        Ok(LocalManifest {
+            version: Version::parse(version).unwrap(),
+            size: size.parse().unwrap(),
+        })
+    }
+
+    /// Write a tar archive with a single file `lib/file.txt`, return the size of the archive
+    #[cfg(feature = "tar")]
+    fn write_tar(path: &Path, data: &[u8]) -> u64 {
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "lib/file.txt", data)
+            .unwrap();
+        builder.into_inner().unwrap();
+        std::fs::metadata(path).unwrap().len()
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn test_exec_offline() {
+        use std::fs;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mirror = temp_dir.path().join("mirror");
+        fs::create_dir(&mirror).unwrap();
+
+        let archive = mirror.join("test.tar");
+        let data = b"content";
+        let size = write_tar(&archive, data);
+
+        let manifest = mirror.join("stable.json");
+        let installer = |dest: PathBuf| {
+            Installer::new(
+                ureq::Agent::new_with_defaults(),
+                "https://example.com/stable.json",
+                read_local_manifest,
+                move |path: &Path| Some(dest.join(path)),
+            )
+        };
+
+        // install from the manifest with the asset next to it
+        fs::write(&manifest, format!("1.0.0 {size}")).unwrap();
+        let dest = temp_dir.path().join("offline");
+        installer(dest.clone())
+            .exec_offline(&manifest, |path| {
+                assert_eq!(path, archive);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(fs::read(dest.join("lib/file.txt")).unwrap(), data);
+
+        // already up-to-date
+        let dest = temp_dir.path().join("up-to-date");
+        let version = Version::new(1, 0, 0);
+        installer(dest.clone())
+            .with_current_version(&version)
+            .exec_offline(&manifest, |_| Ok(()))
+            .unwrap();
+        assert!(!dest.exists());
+
+        // verification failed
+        fs::write(&manifest, format!("1.0.0 {}", size + 1)).unwrap();
+        let err = installer(temp_dir.path().join("mismatch"))
+            .exec_offline(&manifest, |_| Ok(()))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Verify);
+
+        // asset not found
+        fs::rename(&archive, temp_dir.path().join("test.tar")).unwrap();
+        fs::write(&manifest, format!("1.0.0 {size}")).unwrap();
+        assert!(
+            installer(temp_dir.path().join("missing"))
+                .exec_offline(&manifest, |_| Ok(()))
+                .is_err()
+        );
+
+        // install from the archive directly
+        let archive = temp_dir.path().join("test.tar");
+        let dest = temp_dir.path().join("archive");
+        installer(dest.clone())
+            .exec_archive(&archive, crate::verify::SizeVerifier::new(size))
+            .unwrap();
+        assert_eq!(fs::read(dest.join("lib/file.txt")).unwrap(), data);
+        assert!(
+            installer(temp_dir.path().join("archive-mismatch"))
+                .exec_archive(&archive, crate::verify::SizeVerifier::new(size + 1))
+                .is_err()
+        );
+    }
+
+    #[cfg(all(feature = "tar", feature = "signature"))]
+    #[test]
+    fn test_signature() {
+        use std::fs;
+
+        use base64::{Engine, engine::general_purpose::STANDARD};
+        use ring::{
+            digest::{SHA512, digest},
+            rand::SystemRandom,
+            signature::{Ed25519KeyPair, KeyPair},
+        };
+
+        use crate::verify::signature::PublicKey;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("test.tar");
+        write_tar(&archive, b"content");
+        let signature = archive.with_added_extension("sig");
+
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let key = PublicKey::new(key_pair.public_key().as_ref().try_into().unwrap());
+
+        let exec = |name: &str, required: bool| {
+            let dest = temp_dir.path().join(name);
+            Installer::new(
+                ureq::Agent::new_with_defaults(),
+                "https://example.com/stable.json",
+                read_local_manifest,
+                move |path: &Path| Some(dest.join(path)),
+            )
+            .with_signature_key(key.clone(), required)
+            .exec_archive(&archive, ())
+        };
+
+        // no signature
+        exec("optional", false).unwrap();
+        assert!(temp_dir.path().join("optional/lib/file.txt").exists());
+        assert_eq!(
+            exec("required", true).unwrap_err().kind(),
+            ErrorKind::Verify
+        );
+        assert!(!temp_dir.path().join("required").exists());
+
+        // signed by the trusted key
+        let data = fs::read(&archive).unwrap();
+        let signed = key_pair.sign(digest(&SHA512, &data).as_ref());
+        fs::write(&signature, STANDARD.encode(signed.as_ref())).unwrap();
+        exec("signed", true).unwrap();
+        assert!(temp_dir.path().join("signed/lib/file.txt").exists());
+
+        // signature of other data
+        let signed = key_pair.sign(digest(&SHA512, b"other").as_ref());
+        fs::write(&signature, STANDARD.encode(signed.as_ref())).unwrap();
+        assert_eq!(
+            exec("tampered", false).unwrap_err().kind(),
+            ErrorKind::Verify
+        );
+        assert!(!temp_dir.path().join("tampered").exists());
+    }
 }
@@ -83,6 +83,14 @@ pub trait Asset {
     /// Returns an error if the verifier cannot be constructed (e.g., invalid
     /// digest string format).
     fn verifier(&self) -> crate::error::Result<Self::Verifier>;
+
+    /// Get the URL of the detached signature of this asset.
+    ///
+    /// The signature is always fetched from the primary URL instead of mirrors,
+    /// by default it is the URL of the asset with the suffix `.sig`.
+    fn signature_url(&self) -> Cow<'_, str> {
+        Cow::Owned(format!("{}.sig", self.url()))
+    }
 }
 
 #[cfg(test)]
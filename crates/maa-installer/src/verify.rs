@@ -221,6 +221,114 @@ pub mod digest {
     }
 }
 
+#[cfg(feature = "signature")]
+pub mod signature {
+    //! Detached Ed25519 signatures of assets.
+    //!
+    //! The signature is a plain Ed25519 signature of the SHA-512 digest of the asset, so that the
+    //! asset can be verified while streaming. Keys and signatures are encoded in base64, and lines
+    //! starting with `untrusted comment:` in a signature file are ignored. Note that this is not
+    //! the minisign format, minisign signatures can not be verified.
+
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use ring::{
+        digest::{Context, SHA512},
+        signature::{ED25519, UnparsedPublicKey},
+    };
+
+    use super::*;
+
+    const PUBLIC_KEY_LEN: usize = 32;
+    const SIGNATURE_LEN: usize = 64;
+
+    fn decode_base64<const N: usize>(encoded: &str, what: &str) -> Result<[u8; N]> {
+        let bytes = STANDARD.decode(encoded.trim()).map_err(|e| {
+            Error::new(ErrorKind::Verifier)
+                .with_source(e)
+                .with_desc(format!("Failed to decode {what} in base64"))
+        })?;
+
+        let len = bytes.len();
+        bytes.try_into().map_err(|_| {
+            Error::new(ErrorKind::Verifier).with_desc(format!(
+                "Invalid {what} length: expected {N} bytes, got {len}"
+            ))
+        })
+    }
+
+    /// An Ed25519 public key trusted to sign assets.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PublicKey([u8; PUBLIC_KEY_LEN]);
+
+    impl PublicKey {
+        pub fn new(bytes: [u8; PUBLIC_KEY_LEN]) -> Self {
+            Self(bytes)
+        }
+
+        pub fn from_base64(encoded: &str) -> Result<Self> {
+            decode_base64(encoded, "public key").map(Self)
+        }
+    }
+
+    /// A verifier that checks the detached signature of data with a trusted public key.
+    pub struct SignatureVerifier {
+        key: PublicKey,
+        signature: [u8; SIGNATURE_LEN],
+        state: Context,
+    }
+
+    impl std::fmt::Debug for SignatureVerifier {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("SignatureVerifier")
+                .field("key", &self.key)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl SignatureVerifier {
+        pub fn new(key: PublicKey, signature: [u8; SIGNATURE_LEN]) -> Self {
+            Self {
+                key,
+                signature,
+                state: Context::new(&SHA512),
+            }
+        }
+
+        /// Create a verifier from the content of a signature file.
+        ///
+        /// The first line that is neither empty nor a comment is the signature in base64.
+        pub fn from_signature_file(key: PublicKey, content: &str) -> Result<Self> {
+            let encoded = content
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+                .ok_or_else(|| {
+                    Error::new(ErrorKind::Verifier)
+                        .with_desc("No signature found in signature file")
+                })?;
+
+            Ok(Self::new(key, decode_base64(encoded, "signature")?))
+        }
+    }
+
+    impl Verifier for SignatureVerifier {
+        fn update(&mut self, data: &[u8]) {
+            self.state.update(data);
+        }
+
+        fn verify(&mut self) -> Result<()> {
+            let state = std::mem::replace(&mut self.state, Context::new(&SHA512));
+            let digest = state.finish();
+            UnparsedPublicKey::new(&ED25519, &self.key.0)
+                .verify(digest.as_ref(), &self.signature)
+                .map_err(|_| {
+                    Error::new(ErrorKind::Verify)
+                        .with_desc("signature mismatch, the file is not signed by the trusted key")
+                })
+        }
+    }
+}
+
 #[cfg(test)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 mod tests {
@@ -434,4 +542,105 @@ mod tests {
             assert_eq!(result.unwrap_err().kind(), ErrorKind::Verify);
         }
     }
+
+    #[cfg(feature = "signature")]
+    mod signature_verifier {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+        use ring::{
+            digest::{SHA512, digest},
+            rand::SystemRandom,
+            signature::{Ed25519KeyPair, KeyPair},
+        };
+
+        use super::{
+            super::signature::{PublicKey, SignatureVerifier},
+            *,
+        };
+
+        fn key_pair() -> Ed25519KeyPair {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+        }
+
+        fn public_key(key_pair: &Ed25519KeyPair) -> PublicKey {
+            PublicKey::new(key_pair.public_key().as_ref().try_into().unwrap())
+        }
+
+        fn sign(key_pair: &Ed25519KeyPair, data: &[u8]) -> String {
+            let signature = key_pair.sign(digest(&SHA512, data).as_ref());
+            STANDARD.encode(signature.as_ref())
+        }
+
+        #[test]
+        fn public_key_from_base64() {
+            let key_pair = key_pair();
+            let encoded = STANDARD.encode(key_pair.public_key().as_ref());
+            assert_eq!(
+                PublicKey::from_base64(&encoded).unwrap(),
+                public_key(&key_pair)
+            );
+
+            let err = PublicKey::from_base64("not base64!").unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verifier);
+            let err = PublicKey::from_base64(&STANDARD.encode([0; 16])).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verifier);
+        }
+
+        #[test]
+        fn verify_signature() {
+            let key_pair = key_pair();
+            let content = format!(
+                "untrusted comment: signature of hello world\n{}\n",
+                sign(&key_pair, b"hello world\n")
+            );
+            let mut verifier =
+                SignatureVerifier::from_signature_file(public_key(&key_pair), &content).unwrap();
+
+            verifier.update(b"hello ");
+            verifier.update(b"world\n");
+            assert!(verifier.verify().is_ok());
+
+            verifier.update(b"wrong data\n");
+            let err = verifier.verify().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Verify);
+
+            // Reusable after a failed verification
+            verifier.update(b"hello world\n");
+            assert!(verifier.verify().is_ok());
+
+            // Signed by another key
+            let another = public_key(&self::key_pair());
+            let mut verifier = SignatureVerifier::from_signature_file(another, &content).unwrap();
+            verifier.update(b"hello world\n");
+            assert_eq!(verifier.verify().unwrap_err().kind(), ErrorKind::Verify);
+        }
+
+        #[test]
+        fn openssl_signature() {
+            // Signed by `openssl dgst -sha512 -binary | openssl pkeyutl -sign -rawin`
+            let key =
+                PublicKey::from_base64("8WOTdIwAIOxrg1xy50bI8njtMpoUC/AY80RyGtO71II=").unwrap();
+            let mut verifier = SignatureVerifier::from_signature_file(
+                key,
+                "utXwAl96DXwnFx6R1hpkIzHV2kiyPGpLk3ztr12dx1XZuPErfwRq63XiPf1Nrnff5Y5S0leOXFYncHYybyoxAg==",
+            )
+            .unwrap();
+            verifier.update(b"hello world\n");
+            assert!(verifier.verify().is_ok());
+        }
+
+        #[test]
+        fn invalid_signature_file() {
+            let key = public_key(&key_pair());
+            for content in [
+                "",
+                "untrusted comment: nothing\n",
+                "not base64!",
+                "aGVsbG8=",
+            ] {
+                let err = SignatureVerifier::from_signature_file(key.clone(), content).unwrap_err();
+                assert_eq!(err.kind(), ErrorKind::Verifier);
+            }
+        }
+    }
 }
@@ -12,12 +12,26 @@ pub struct VersionManifest<D> {
     pub details: D,
 }
 
-fn deserialize_version<'de, D: serde::Deserializer<'de>>(de: D) -> Result<Version, D::Error> {
+/// Parse a version with an optional `v` prefix, e.g. `v5.2.0`
+pub fn parse_version(s: &str) -> Result<Version, semver::Error> {
+    Version::parse(s.strip_prefix('v').unwrap_or(s))
+}
+
+/// Deserialize a version with an optional `v` prefix
+pub fn deserialize_version<'de, D: serde::Deserializer<'de>>(de: D) -> Result<Version, D::Error> {
     use serde::de::Error;
     let s = String::deserialize(de)?;
-    let s = s.as_str();
-    let s = s.strip_prefix('v').unwrap_or(s);
-    Version::parse(s).map_err(D::Error::custom)
+    parse_version(&s).map_err(D::Error::custom)
+}
+
+/// Deserialize an optional version with an optional `v` prefix
+pub fn deserialize_version_opt<'de, D: serde::Deserializer<'de>>(
+    de: D,
+) -> Result<Option<Version>, D::Error> {
+    use serde::de::Error;
+    Option::<String>::deserialize(de)?
+        .map(|s| parse_version(&s).map_err(D::Error::custom))
+        .transpose()
 }
 
 pub mod cli {
@@ -57,6 +71,8 @@ pub mod core {
         pub name: String,
         pub size: u64,
         pub browser_download_url: String,
+        /// Mirrors of the asset, which may be missing in manifests of historical releases
+        #[serde(default)]
         pub mirrors: Vec<String>,
     }
 }
@@ -132,6 +148,23 @@ mod tests {
             assert_eq!(version_info.version, Version::parse("1.2.3").unwrap());
         }
 
+        #[test]
+        fn deserialize_optional_version() {
+            #[derive(Deserialize)]
+            struct Pin {
+                #[serde(default, deserialize_with = "deserialize_version_opt")]
+                version: Option<Version>,
+            }
+
+            let pin: Pin = serde_json::from_str(r#"{"version": "v5.2.0"}"#).unwrap();
+            assert_eq!(pin.version, Some(Version::new(5, 2, 0)));
+            let pin: Pin = serde_json::from_str(r#"{"version": "5.2.0-beta.1"}"#).unwrap();
+            assert_eq!(pin.version, Some(Version::parse("5.2.0-beta.1").unwrap()));
+            let pin: Pin = serde_json::from_str("{}").unwrap();
+            assert_eq!(pin.version, None);
+            assert!(serde_json::from_str::<Pin>(r#"{"version": "5.2"}"#).is_err());
+        }
+
         #[test]
         fn deserialize_invalid_version() {
             let result: Result<VersionManifest<()>, _> =
@@ -330,6 +363,19 @@ mod tests {
             ]);
         }
 
+        #[test]
+        fn historical_release() {
+            let asset: Asset = serde_json::from_str(
+                r#"{
+                    "name": "MAA-v4.20.0-linux-x86_64.tar.gz",
+                    "size": 123456,
+                    "browser_download_url": "https://example.com/linux.tar.gz"
+                }"#,
+            )
+            .unwrap();
+            assert!(asset.mirrors.is_empty());
+        }
+
         #[test]
         fn round_trip() {
             let details = Details {